@@ -0,0 +1,197 @@
+//! Anti-aliasing effects, MSAA is a setting of [`crate::post::PostProcess`] instead since it
+//! changes how the scene is drawn.
+
+use crate::post::{Effect, Frame, Target, bind_textures, post_shader};
+use crate::shader::uniform;
+use glam::{Mat4, Vec2};
+use glazer::glow::{self, HasContext};
+
+/// Temporal anti-aliasing, blends each frame drawn at a sub-pixel offset into the frames before
+/// it, reprojected to where they are seen now.
+pub struct Taa {
+    pub enabled: bool,
+    /// Weight of each new frame, lower is smoother but leaves trails behind moving things.
+    pub blend: f32,
+    shader: glow::Program,
+    /// Result of the last frame and the one being drawn, swapped every frame.
+    history: Vec<Target>,
+    /// Projection and view of the last frame, `None` if there is no history to blend with.
+    previous_view_proj: Option<Mat4>,
+    frame_index: u32,
+}
+
+impl Taa {
+    /// Frames before the jitter repeats.
+    const JITTER_FRAMES: u32 = 8;
+
+    pub fn new(gl: &glow::Context) -> Self {
+        Self {
+            enabled: false,
+            blend: 0.1,
+            shader: post_shader!(gl, "shaders/taa.frag", ["source", "history", "depth"]),
+            history: Vec::new(),
+            previous_view_proj: None,
+            frame_index: 0,
+        }
+    }
+}
+
+/// Low discrepancy sequence that spreads samples evenly over `0..1`.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+impl Effect for Taa {
+    fn name(&self) -> &'static str {
+        "TAA"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn jitter(&self) -> Vec2 {
+        // starts at 1 since every sequence starts at 0
+        let index = self.frame_index % Self::JITTER_FRAMES + 1;
+        Vec2::new(halton(index, 2), halton(index, 3)) - 0.5
+    }
+
+    fn apply(&mut self, gl: &glow::Context, frame: &Frame, input: glow::Texture, output: &Target) {
+        if self
+            .history
+            .first()
+            .is_none_or(|target| (target.width, target.height) != (frame.width, frame.height))
+        {
+            for target in self.history.drain(..) {
+                target.delete(gl);
+            }
+            self.history = (0..2)
+                .map(|_| Target::new(gl, frame.width, frame.height, glow::RGBA16F))
+                .collect();
+            self.previous_view_proj = None;
+        }
+
+        let view_proj = frame.proj * frame.view;
+        let (reprojection, blend) = match self.previous_view_proj {
+            Some(previous) => (previous * view_proj.inverse(), self.blend),
+            // nothing to blend with
+            None => (Mat4::IDENTITY, 1.0),
+        };
+
+        self.history.swap(0, 1);
+        let [current, previous] = &self.history[..] else {
+            unreachable!()
+        };
+        current.bind(gl);
+        unsafe {
+            gl.use_program(Some(self.shader));
+            uniform(gl, self.shader, "reprojection", |location| {
+                gl.uniform_matrix_4_f32_slice(location, false, &reprojection.to_cols_array());
+            });
+            uniform(gl, self.shader, "texel_size", |location| {
+                gl.uniform_2_f32_slice(location, &frame.texel_size().to_array());
+            });
+            uniform(gl, self.shader, "blend", |location| {
+                gl.uniform_1_f32(location, blend);
+            });
+            bind_textures(gl, &[input, previous.texture, frame.depth]);
+            frame.draw(gl);
+
+            // the result is kept for the next frame, later effects draw over the output
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(current.framebuffer));
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(output.framebuffer));
+            gl.blit_framebuffer(
+                0,
+                0,
+                frame.width,
+                frame.height,
+                0,
+                0,
+                frame.width,
+                frame.height,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+        }
+
+        self.previous_view_proj = Some(view_proj);
+        self.frame_index = self.frame_index.wrapping_add(1);
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let name = self.name();
+            if ui.checkbox(&mut self.enabled, name).changed() {
+                // the history is from before it was turned off
+                self.previous_view_proj = None;
+            }
+            ui.add_enabled(
+                self.enabled,
+                egui::Slider::new(&mut self.blend, 0.02..=0.5)
+                    .logarithmic(true)
+                    .text("Blend"),
+            );
+        });
+    }
+}
+
+/// Fast approximate anti-aliasing, blurs along edges found by their contrast.
+pub struct Fxaa {
+    pub enabled: bool,
+    /// Texels an edge is blurred along at most.
+    pub span: f32,
+    shader: glow::Program,
+}
+
+impl Fxaa {
+    pub fn new(gl: &glow::Context) -> Self {
+        Self {
+            enabled: true,
+            span: 8.0,
+            shader: post_shader!(gl, "shaders/fxaa.frag", ["source"]),
+        }
+    }
+}
+
+impl Effect for Fxaa {
+    fn name(&self) -> &'static str {
+        "FXAA"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(&mut self, gl: &glow::Context, frame: &Frame, input: glow::Texture, output: &Target) {
+        output.bind(gl);
+        unsafe {
+            gl.use_program(Some(self.shader));
+            uniform(gl, self.shader, "texel_size", |location| {
+                gl.uniform_2_f32_slice(location, &frame.texel_size().to_array());
+            });
+            uniform(gl, self.shader, "span", |location| {
+                gl.uniform_1_f32(location, self.span);
+            });
+        }
+        bind_textures(gl, &[input]);
+        frame.draw(gl);
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let name = self.name();
+            ui.checkbox(&mut self.enabled, name);
+            ui.add_enabled(
+                self.enabled,
+                egui::Slider::new(&mut self.span, 1.0..=16.0).text("Span"),
+            );
+        });
+    }
+}
@@ -0,0 +1,367 @@
+//! Minecraft worlds stored as Anvil region files.
+//!
+//! https://minecraft.wiki/w/Region_file_format
+//! https://minecraft.wiki/w/Chunk_format
+
+use crate::{
+    block::Block,
+    chunk::{CHUNK_HEIGHT, MIN_Y, local_index},
+    nbt::{self, Tag},
+};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+/// First version where block state entries no longer span across longs (20w17a).
+const NON_SPANNING_DATA_VERSION: i64 = 2529;
+
+/// Maps Minecraft block names to voxl blocks.
+///
+/// The table is read from lines of `minecraft:name = block`, where `*` on the left is used for
+/// any name that is not listed.
+pub struct BlockNames {
+    names: HashMap<String, Block>,
+    fallback: Block,
+}
+
+impl Default for BlockNames {
+    fn default() -> Self {
+        Self::parse(include_str!("../assets/minecraft_blocks.txt"))
+            .expect("built-in block name table is valid")
+    }
+}
+
+impl BlockNames {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut names = HashMap::new();
+        let mut fallback = Block::Air;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, block) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `name = block`"))?;
+            let block = Block::from_name(block.trim())
+                .ok_or_else(|| invalid("unknown block in name table"))?;
+            match name.trim() {
+                "*" => fallback = block,
+                name => {
+                    names.insert(name.to_string(), block);
+                }
+            }
+        }
+        Ok(Self { names, fallback })
+    }
+
+    pub fn get(&self, name: &str) -> Block {
+        self.names.get(name).copied().unwrap_or(self.fallback)
+    }
+}
+
+/// A directory of `r.<x>.<z>.mca` files, loaded as chunks are requested.
+pub struct AnvilWorld {
+    directory: PathBuf,
+    names: BlockNames,
+    /// Added to Minecraft heights, which reach further than the height of a chunk.
+    pub y_offset: i64,
+    regions: HashMap<(i64, i64), Option<Vec<u8>>>,
+}
+
+impl AnvilWorld {
+    /// `directory` is either a save or its `region` directory.
+    pub fn open(directory: impl Into<PathBuf>, names: BlockNames, y_offset: i64) -> Self {
+        let mut directory = directory.into();
+        if directory.join("region").is_dir() {
+            directory = directory.join("region");
+        }
+        Self {
+            directory,
+            names,
+            y_offset,
+            regions: HashMap::new(),
+        }
+    }
+
+    /// Fills `blocks` with the chunk at `x`, `z`, returning `false` if it was never generated.
+    pub fn load_chunk(&mut self, x: i64, z: i64, blocks: &mut [Block]) -> io::Result<bool> {
+        blocks.fill(Block::Air);
+
+        let key = (x.div_euclid(32), z.div_euclid(32));
+        let region = match self.regions.get(&key) {
+            Some(region) => region,
+            None => {
+                let path = self.directory.join(format!("r.{}.{}.mca", key.0, key.1));
+                let region = match std::fs::read(path) {
+                    Ok(region) => Some(region),
+                    Err(err) if err.kind() == ErrorKind::NotFound => None,
+                    Err(err) => return Err(err),
+                };
+                self.regions.entry(key).or_insert(region)
+            }
+        };
+        let Some(region) = region else {
+            return Ok(false);
+        };
+
+        let Some(data) = chunk_data(region, x.rem_euclid(32) as usize, z.rem_euclid(32) as usize)?
+        else {
+            return Ok(false);
+        };
+        let root = nbt::parse(&data)?;
+        read_chunk(&root, &self.names, self.y_offset, blocks)?;
+        Ok(true)
+    }
+}
+
+/// Decompressed NBT of a chunk in a region file.
+fn chunk_data(region: &[u8], x: usize, z: usize) -> io::Result<Option<Vec<u8>>> {
+    let header = (x + z * 32) * 4;
+    let Some(location) = region.get(header..header + 4) else {
+        return Err(invalid("region header is truncated"));
+    };
+    let sector = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+    if sector == 0 && location[3] == 0 {
+        return Ok(None);
+    }
+
+    let offset = sector * 4096;
+    let Some(length) = region.get(offset..offset + 5) else {
+        return Err(invalid("chunk is outside of the region"));
+    };
+    let len = u32::from_be_bytes(length[..4].try_into().unwrap()) as usize;
+    let compression = length[4];
+    let Some(compressed) = region.get(offset + 5..offset + 4 + len) else {
+        return Err(invalid("chunk is truncated"));
+    };
+
+    let mut data = Vec::new();
+    match compression {
+        1 => {
+            GzDecoder::new(compressed).read_to_end(&mut data)?;
+        }
+        2 => {
+            ZlibDecoder::new(compressed).read_to_end(&mut data)?;
+        }
+        3 => data.extend_from_slice(compressed),
+        // stored in a separate `c.<x>.<z>.mcc` file or compressed with LZ4
+        _ => return Err(invalid("unsupported chunk compression")),
+    }
+    Ok(Some(data))
+}
+
+fn read_chunk(
+    root: &Tag,
+    names: &BlockNames,
+    y_offset: i64,
+    blocks: &mut [Block],
+) -> io::Result<()> {
+    let data_version = root.get("DataVersion").and_then(Tag::as_i64).unwrap_or(0);
+
+    // chunks written before 1.18 keep their sections under `Level`
+    let (sections, palette_key, states_key) = match root.get("sections") {
+        Some(sections) => (sections, "palette", "data"),
+        None => (
+            root.get("Level")
+                .and_then(|level| level.get("Sections"))
+                .ok_or_else(|| invalid("chunk has no sections"))?,
+            "Palette",
+            "BlockStates",
+        ),
+    };
+
+    for section in sections.as_list().unwrap_or_default() {
+        let Some(section_y) = section.get("Y").and_then(Tag::as_i64) else {
+            continue;
+        };
+        // 1.18 nests the palette and states in `block_states`
+        let states = section.get("block_states").unwrap_or(section);
+        let Some(palette) = states.get(palette_key).and_then(Tag::as_list) else {
+            continue;
+        };
+        let palette = palette
+            .iter()
+            .map(|entry| {
+                entry
+                    .get("Name")
+                    .and_then(Tag::as_str)
+                    .map_or(Block::Air, |name| names.get(name))
+            })
+            .collect::<Vec<_>>();
+        let data = states
+            .get(states_key)
+            .and_then(Tag::as_long_array)
+            .unwrap_or_default();
+
+        let bits = (usize::BITS - (palette.len().max(1) - 1).leading_zeros()).max(4) as usize;
+        for i in 0..4096 {
+            let entry = if palette.len() == 1 || data.is_empty() {
+                0
+            } else if data_version >= NON_SPANNING_DATA_VERSION {
+                let per_long = 64 / bits;
+                let long = *data
+                    .get(i / per_long)
+                    .ok_or_else(|| invalid("block states are truncated"))?;
+                (long as u64 >> ((i % per_long) * bits)) & ((1 << bits) - 1)
+            } else {
+                spanning_entry(data, i, bits)?
+            };
+            let block = *palette
+                .get(entry as usize)
+                .ok_or_else(|| invalid("block state outside of the palette"))?;
+
+            let (x, z, y) = (i % 16, (i / 16) % 16, i / 256);
+            let y = section_y * 16 + y as i64 + y_offset - MIN_Y;
+            if (0..CHUNK_HEIGHT as i64).contains(&y) {
+                blocks[local_index(x, y as usize, z)] = block;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Entries before 1.16 are packed back to back and may continue in the next long.
+fn spanning_entry(data: &[i64], i: usize, bits: usize) -> io::Result<u64> {
+    let bit = i * bits;
+    let (long, shift) = (bit / 64, bit % 64);
+    let mask = (1u64 << bits) - 1;
+    let low = *data
+        .get(long)
+        .ok_or_else(|| invalid("block states are truncated"))? as u64;
+    let mut entry = low >> shift;
+    if shift + bits > 64 {
+        let high = *data
+            .get(long + 1)
+            .ok_or_else(|| invalid("block states are truncated"))? as u64;
+        entry |= high << (64 - shift);
+    }
+    Ok(entry & mask)
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::CHUNK_SIZE;
+
+    /// Holds `r.0.0.mca` with chunk 0, 0 in the 1.18 layout and chunk 1, 0 in the layout from
+    /// before 1.16. Both have a section at Y 0 with a palette of [`PALETTE`] followed by an
+    /// unknown name, so entries take 5 bits and only cross longs in the older layout.
+    const FIXTURE: &str = "assets/anvil";
+
+    const PALETTE: [&str; 16] = [
+        "minecraft:air",
+        "minecraft:grass_block",
+        "minecraft:dirt",
+        "minecraft:stone",
+        "minecraft:cobblestone",
+        "minecraft:sand",
+        "minecraft:gravel",
+        "minecraft:oak_planks",
+        "minecraft:oak_log",
+        "minecraft:bricks",
+        "minecraft:bedrock",
+        "minecraft:glowstone",
+        "minecraft:torch",
+        "minecraft:lava",
+        "minecraft:water",
+        "minecraft:glass",
+    ];
+
+    /// Maps the palette of the fixture to every block in order, the unknown name at the end
+    /// falls back to the last block.
+    fn names() -> BlockNames {
+        let mut table = format!("* = {}\n", Block::Leaves.name());
+        for (name, block) in PALETTE.iter().zip(Block::ALL) {
+            table += &format!("{name} = {}\n", block.name());
+        }
+        BlockNames::parse(&table).unwrap()
+    }
+
+    /// Block the fixture stores at entry `i` of the sections at Y 0.
+    fn expected(i: usize) -> Block {
+        Block::ALL[(i * 7 + i / 256) % 17]
+    }
+
+    fn load(x: i64, z: i64) -> Option<Vec<Block>> {
+        let mut world = AnvilWorld::open(FIXTURE, names(), 0);
+        let mut blocks = vec![Block::Stone; CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE];
+        world
+            .load_chunk(x, z, &mut blocks)
+            .unwrap()
+            .then_some(blocks)
+    }
+
+    fn section_blocks(blocks: &[Block], section_y: i64) -> impl Iterator<Item = Block> + '_ {
+        (0..4096).map(move |i| {
+            let (x, z, y) = (i % 16, (i / 16) % 16, i / 256);
+            let y = section_y * 16 + y as i64 - MIN_Y;
+            blocks[local_index(x, y as usize, z)]
+        })
+    }
+
+    #[test]
+    fn reads_sections_without_spanning_entries() {
+        let blocks = load(0, 0).unwrap();
+        assert!(
+            section_blocks(&blocks, 0)
+                .enumerate()
+                .all(|(i, block)| block == expected(i))
+        );
+        // single entry palettes have no data
+        assert!(section_blocks(&blocks, 1).all(|block| block == Block::Stone));
+        assert!(section_blocks(&blocks, -4).all(|block| block == Block::Air));
+        // sections that are not in the file are air
+        assert!(section_blocks(&blocks, 2).all(|block| block == Block::Air));
+    }
+
+    #[test]
+    fn reads_level_sections_with_spanning_entries() {
+        let blocks = load(1, 0).unwrap();
+        assert!(
+            section_blocks(&blocks, 0)
+                .enumerate()
+                .all(|(i, block)| block == expected(i))
+        );
+        // the section without a palette only stores light
+        assert!(section_blocks(&blocks, -1).all(|block| block == Block::Air));
+    }
+
+    #[test]
+    fn missing_chunks_are_not_generated() {
+        assert!(load(0, 1).is_none());
+        // no region file
+        assert!(load(-1, 0).is_none());
+    }
+
+    #[test]
+    fn spanning_entry_joins_longs() {
+        // entry 12 of 5 bits starts at bit 60, its lowest 4 bits end the first long
+        let data = [0b1011 << 60, 0b1];
+        assert_eq!(spanning_entry(&data, 12, 5).unwrap(), 0b11011);
+        assert_eq!(spanning_entry(&data, 0, 5).unwrap(), 0);
+        assert!(spanning_entry(&data[..1], 12, 5).is_err());
+    }
+
+    #[test]
+    fn unknown_names_fall_back() {
+        let names = BlockNames::default();
+        assert_eq!(names.get("minecraft:grass_block"), Block::Grass);
+        assert_eq!(names.get("minecraft:not_a_block"), Block::Stone);
+
+        let names = BlockNames::parse("minecraft:sand = sand").unwrap();
+        assert_eq!(names.get("minecraft:sand"), Block::Sand);
+        assert_eq!(names.get("minecraft:not_a_block"), Block::Air);
+
+        assert!(BlockNames::parse("minecraft:sand = not_a_block").is_err());
+    }
+}
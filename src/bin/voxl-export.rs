@@ -0,0 +1,55 @@
+//! Writes generated terrain or a Minecraft save as an OBJ or glTF mesh without opening a window.
+//!
+//! voxl-export <output.obj|output.glb> [--radius N] [--center X,Z] [--world SAVE] [--y-offset N] [--pack DIR]
+
+use std::path::Path;
+
+const USAGE: &str = "usage: voxl-export <output.obj|output.glb> [--radius N] [--center X,Z] [--world SAVE] [--y-offset N] [--pack DIR]";
+
+fn main() {
+    if let Err(err) = run(std::env::args().skip(1)) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut output = None;
+    let mut radius = 4;
+    let mut center = (0, 0);
+    let mut world = None;
+    let mut y_offset = -64;
+    let mut pack = Path::new(voxl::RESOURCE_PACKS).join(voxl::DEFAULT_PACK);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} expects a value"));
+        match arg.as_str() {
+            "--radius" => radius = value()?.parse().map_err(|_| "invalid radius")?,
+            "--center" => {
+                let value = value()?;
+                center = value
+                    .split_once(',')
+                    .and_then(|(x, z)| Some((x.parse().ok()?, z.parse().ok()?)))
+                    .ok_or("invalid center, expected X,Z")?;
+            }
+            "--world" => world = Some(value()?),
+            "--y-offset" => y_offset = value()?.parse().map_err(|_| "invalid y offset")?,
+            "--pack" => pack = value()?.into(),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if output.is_none() && !arg.starts_with('-') => output = Some(arg),
+            _ => return Err(format!("unexpected argument {arg}\n{USAGE}")),
+        }
+    }
+    let output = output.ok_or(USAGE)?;
+
+    let chunks = voxl::export_generated(
+        Path::new(&output),
+        &pack,
+        center,
+        radius,
+        world.as_deref().map(|world| (Path::new(world), y_offset)),
+    )
+    .map_err(|err| format!("failed to export {output}: {err}"))?;
+    println!("exported {chunks} chunks to {output}");
+    Ok(())
+}
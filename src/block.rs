@@ -0,0 +1,211 @@
+use glam::{IVec3, Vec3};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Block {
+    #[default]
+    Air,
+    Grass,
+    Dirt,
+    Stone,
+    Cobblestone,
+    Sand,
+    Gravel,
+    Planks,
+    Log,
+    Bricks,
+    Bedrock,
+    Glowstone,
+    Torch,
+    Lava,
+    Water,
+    Glass,
+    Leaves,
+}
+
+impl Block {
+    pub const ALL: [Block; 17] = [
+        Block::Air,
+        Block::Grass,
+        Block::Dirt,
+        Block::Stone,
+        Block::Cobblestone,
+        Block::Sand,
+        Block::Gravel,
+        Block::Planks,
+        Block::Log,
+        Block::Bricks,
+        Block::Bedrock,
+        Block::Glowstone,
+        Block::Torch,
+        Block::Lava,
+        Block::Water,
+        Block::Glass,
+        Block::Leaves,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Block::Air => "air",
+            Block::Grass => "grass",
+            Block::Dirt => "dirt",
+            Block::Stone => "stone",
+            Block::Cobblestone => "cobblestone",
+            Block::Sand => "sand",
+            Block::Gravel => "gravel",
+            Block::Planks => "planks",
+            Block::Log => "log",
+            Block::Bricks => "bricks",
+            Block::Bedrock => "bedrock",
+            Block::Glowstone => "glowstone",
+            Block::Torch => "torch",
+            Block::Lava => "lava",
+            Block::Water => "water",
+            Block::Glass => "glass",
+            Block::Leaves => "leaves",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Block> {
+        Block::ALL.into_iter().find(|block| block.name() == name)
+    }
+
+    pub fn is_air(self) -> bool {
+        self == Block::Air
+    }
+
+    /// Stops sky and block light from passing through.
+    pub fn is_opaque(self) -> bool {
+        !self.is_air() && self.layer() == RenderLayer::Opaque
+    }
+
+    pub fn layer(self) -> RenderLayer {
+        match self {
+            Block::Torch | Block::Leaves => RenderLayer::Cutout,
+            Block::Water | Block::Glass => RenderLayer::Translucent,
+            _ => RenderLayer::Opaque,
+        }
+    }
+
+    /// Whether a face of this block towards `neighbour` is hidden by it. Translucent blocks hide
+    /// the faces between blocks of the same kind, so water and glass have no inner walls.
+    pub fn face_hidden_by(self, neighbour: Block) -> bool {
+        neighbour.is_opaque() || (self == neighbour && self.layer() == RenderLayer::Translucent)
+    }
+
+    /// Block light level given off by the block, up to [`MAX_LIGHT`](crate::light::MAX_LIGHT).
+    pub fn light_emission(self) -> u8 {
+        match self {
+            Block::Glowstone | Block::Lava => 15,
+            Block::Torch => 14,
+            _ => 0,
+        }
+    }
+
+    /// Representative colour, used when converting to and from palette based formats.
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            Block::Air => [0, 0, 0],
+            Block::Grass => [94, 157, 52],
+            Block::Dirt => [134, 96, 67],
+            Block::Stone => [125, 125, 125],
+            Block::Cobblestone => [100, 100, 100],
+            Block::Sand => [219, 207, 163],
+            Block::Gravel => [136, 126, 126],
+            Block::Planks => [162, 130, 78],
+            Block::Log => [102, 81, 50],
+            Block::Bricks => [150, 97, 83],
+            Block::Bedrock => [40, 40, 40],
+            Block::Glowstone => [196, 140, 58],
+            Block::Torch => [255, 216, 96],
+            Block::Lava => [207, 92, 20],
+            Block::Water => [47, 67, 244],
+            Block::Glass => [200, 220, 230],
+            Block::Leaves => [60, 120, 40],
+        }
+    }
+
+    /// Solid block with the closest [`Block::color`].
+    pub fn from_color(color: [u8; 3]) -> Block {
+        let distance = |block: &Block| {
+            let other = block.color();
+            (0..3)
+                .map(|i| (color[i] as i32 - other[i] as i32).pow(2))
+                .sum::<i32>()
+        };
+        Block::ALL
+            .into_iter()
+            .filter(|block| !block.is_air())
+            .min_by_key(distance)
+            .unwrap()
+    }
+
+    /// Texture array layer of each [`Face`], every block has six layers in the order of
+    /// [`Block::ALL`] and [`Face::ALL`] whatever resource pack is loaded.
+    pub fn texture_layers(self) -> [u32; 6] {
+        Face::ALL.map(|face| self as u32 * 6 + face as u32)
+    }
+
+    /// Colour multiplied with the texture, for greyscale grass and leaves textures.
+    pub fn tint(self, face: Face) -> Vec3 {
+        match (self, face) {
+            (Block::Grass, Face::Top) | (Block::Leaves, _) => Vec3::new(0.55, 0.8, 0.35),
+            _ => Vec3::ONE,
+        }
+    }
+}
+
+/// How the faces of a block are drawn, in the order of the passes in
+/// [`VoxelRenderer`](crate::voxel::VoxelRenderer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderLayer {
+    Opaque,
+    /// Pixels with low alpha are discarded, for leaves and torches.
+    Cutout,
+    /// Blended over everything else, sorted back to front.
+    Translucent,
+}
+
+/// Cube faces in the order of [`Block::texture_layers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    Back,
+    Front,
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::Back,
+        Face::Front,
+        Face::Left,
+        Face::Right,
+        Face::Bottom,
+        Face::Top,
+    ];
+
+    pub fn opposite(self) -> Face {
+        match self {
+            Face::Back => Face::Front,
+            Face::Front => Face::Back,
+            Face::Left => Face::Right,
+            Face::Right => Face::Left,
+            Face::Bottom => Face::Top,
+            Face::Top => Face::Bottom,
+        }
+    }
+
+    pub fn normal(self) -> IVec3 {
+        match self {
+            Face::Back => IVec3::NEG_Z,
+            Face::Front => IVec3::Z,
+            Face::Left => IVec3::NEG_X,
+            Face::Right => IVec3::X,
+            Face::Bottom => IVec3::NEG_Y,
+            Face::Top => IVec3::Y,
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    block::Block,
+    chunk::{CHUNK_HEIGHT, Chunks, MIN_Y},
+    history::History,
+};
+use glam::{IVec2, IVec3, Vec2};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushKind {
+    Raise,
+    Lower,
+    Smooth,
+    Flatten,
+}
+
+impl BrushKind {
+    pub const ALL: [BrushKind; 4] = [
+        BrushKind::Raise,
+        BrushKind::Lower,
+        BrushKind::Smooth,
+        BrushKind::Flatten,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BrushKind::Raise => "Raise",
+            BrushKind::Lower => "Lower",
+            BrushKind::Smooth => "Smooth",
+            BrushKind::Flatten => "Flatten",
+        }
+    }
+}
+
+/// Reshapes the terrain surface in a radius around the crosshair while the mouse is held.
+pub struct Brush {
+    pub kind: BrushKind,
+    pub radius: f32,
+    /// Blocks per second at the center of the brush.
+    pub strength: f32,
+    stroke: Option<Stroke>,
+}
+
+/// State of a single press of the mouse button.
+struct Stroke {
+    /// Surface height of the first click, the target of [`BrushKind::Flatten`].
+    flatten_height: f32,
+    /// Fractional surface heights, so that weak brushes still move the terrain over time.
+    heights: HashMap<IVec2, f32>,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            kind: BrushKind::Raise,
+            radius: 6.0,
+            strength: 8.0,
+            stroke: None,
+        }
+    }
+}
+
+impl Brush {
+    pub fn begin(&mut self, history: &mut History, target: IVec3) {
+        // a release that never arrived leaves the last stroke open
+        self.end(history);
+        history.begin(format!("{} brush", self.kind.name()));
+        self.stroke = Some(Stroke {
+            flatten_height: target.y as f32,
+            heights: HashMap::new(),
+        });
+    }
+
+    /// Whether a stroke is open, its edits go into the transaction it began.
+    pub fn is_active(&self) -> bool {
+        self.stroke.is_some()
+    }
+
+    pub fn end(&mut self, history: &mut History) {
+        if self.stroke.take().is_some() {
+            history.commit();
+        }
+    }
+
+    pub fn apply(&mut self, chunks: &mut Chunks, history: &mut History, target: IVec3, delta: f32) {
+        let Some(stroke) = &mut self.stroke else {
+            return;
+        };
+
+        let center = IVec2::new(target.x, target.z);
+        let radius = self.radius.ceil() as i32;
+        let mut columns = Vec::new();
+        for z in -radius..=radius {
+            for x in -radius..=radius {
+                let column = center + IVec2::new(x, z);
+                let distance = Vec2::new(x as f32, z as f32).length();
+                if distance > self.radius {
+                    continue;
+                }
+                let Some(surface) = chunks.surface_height(column.x, column.y) else {
+                    continue;
+                };
+                let height = *stroke.heights.entry(column).or_insert(surface as f32);
+                columns.push((column, height, falloff(distance / self.radius)));
+            }
+        }
+
+        let rate = (self.strength * delta).min(1.0);
+        let targets = columns
+            .iter()
+            .map(|&(column, height, falloff)| {
+                let height = match self.kind {
+                    BrushKind::Raise => height + self.strength * delta * falloff,
+                    BrushKind::Lower => height - self.strength * delta * falloff,
+                    BrushKind::Smooth => {
+                        let average = neighbourhood_average(chunks, &stroke.heights, column);
+                        height + (average - height) * (rate * falloff).min(1.0)
+                    }
+                    BrushKind::Flatten => {
+                        height + (stroke.flatten_height - height) * (rate * falloff).min(1.0)
+                    }
+                };
+                (column, height)
+            })
+            .collect::<Vec<_>>();
+
+        for (column, height) in targets {
+            stroke.heights.insert(column, height);
+            set_surface_height(chunks, history, column, height.round() as i32);
+        }
+    }
+}
+
+/// Smooth falloff from 1 at the center to 0 at the edge of the brush.
+fn falloff(t: f32) -> f32 {
+    let t = 1.0 - t.clamp(0.0, 1.0) * t.clamp(0.0, 1.0);
+    t * t
+}
+
+fn neighbourhood_average(chunks: &Chunks, heights: &HashMap<IVec2, f32>, column: IVec2) -> f32 {
+    let mut sum = 0.0;
+    let mut count = 0.0;
+    for z in -1..=1 {
+        for x in -1..=1 {
+            let neighbour = column + IVec2::new(x, z);
+            let height = heights.get(&neighbour).copied().or_else(|| {
+                chunks
+                    .surface_height(neighbour.x, neighbour.y)
+                    .map(|height| height as f32)
+            });
+            if let Some(height) = height {
+                sum += height;
+                count += 1.0;
+            }
+        }
+    }
+    sum / count
+}
+
+/// Grows or shrinks a column, keeping the surface block on top.
+fn set_surface_height(chunks: &mut Chunks, history: &mut History, column: IVec2, height: i32) {
+    let Some(surface) = chunks.surface_height(column.x, column.y) else {
+        return;
+    };
+    // keep the bedrock layer
+    let height = height.clamp(MIN_Y as i32 + 1, MIN_Y as i32 + CHUNK_HEIGHT as i32 - 1);
+    if surface == height {
+        return;
+    }
+
+    let at = |y: i32| IVec3::new(column.x, y, column.y);
+    let top = chunks.block(at(surface));
+    let below = match chunks.block(at(surface - 1)) {
+        Block::Air => top,
+        block => block,
+    };
+
+    if height > surface {
+        history.set_block(chunks, at(surface), below);
+        for y in surface + 1..height {
+            history.set_block(chunks, at(y), below);
+        }
+    } else {
+        for y in height + 1..=surface {
+            history.set_block(chunks, at(y), Block::Air);
+        }
+    }
+    history.set_block(chunks, at(height), top);
+}
+
+pub fn ui(ui: &mut egui::Ui, brush: &mut Brush) {
+    ui.horizontal(|ui| {
+        for kind in BrushKind::ALL {
+            ui.selectable_value(&mut brush.kind, kind, kind.name());
+        }
+    });
+    ui.add(egui::Slider::new(&mut brush.radius, 1.0..=32.0).text("Brush Size"));
+    ui.add(egui::Slider::new(&mut brush.strength, 0.5..=32.0).text("Brush Strength"));
+}
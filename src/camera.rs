@@ -37,6 +37,15 @@ impl Camera {
         Mat4::from_quat(Quat::look_to_rh(self.look_at, Vec3::Y))
             * Mat4::from_translation(self.translation)
     }
+
+    /// World space position, the view matrix translates by the negated position.
+    pub fn position(&self) -> Vec3 {
+        -self.translation
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.look_at
+    }
 }
 
 pub fn handle_input(input: &glazer::Input, camera: &mut Camera) {
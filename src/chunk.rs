@@ -1,46 +1,522 @@
 use crate::{
     World,
+    anvil::{AnvilWorld, BlockNames},
+    block::Block,
     camera::Camera,
-    voxel::{Lighting, VoxelInstanceBuffer, VoxelRenderer},
+    deferred::{self, RenderPath},
+    frustum::Frustum,
+    history::History,
+    light,
+    lod::LodSettings,
+    mesh::{self, SortedFace},
+    occlusion::{self, Connectivity, SECTIONS},
+    render_graph::{Format, Pass},
+    voxel::{self, ChunkMesh, Lighting, VoxelRenderer},
 };
-use glam::{FloatExt, Vec2, Vec3};
+use glam::{FloatExt, IVec3, Vec2, Vec3};
 use glazer::glow;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 pub const CHUNK_SIZE: usize = 16;
+pub const CHUNK_HEIGHT: usize = 256;
+/// World height of the lowest block in a chunk.
+pub const MIN_Y: i64 = -128;
+/// `(uv scale, weight)` of the noise layers summed into the terrain height.
+pub const DEFAULT_NOISE_LAYERS: [(f32, f32); 3] = [(1.5, 80.0), (3.0, 40.0), (8.0, 30.0)];
+/// Furthest view distance in chunks. Only chunks within the [`LodSettings::distance`] of at most
+/// 32 chunks keep every block and its light, 128KiB each, the rest store downsampled cells.
+pub const MAX_VIEW_DISTANCE: usize = 64;
+/// Furthest view distance without levels of detail, where the 65² loaded chunks take about 550MB.
+pub const MAX_FULL_VIEW_DISTANCE: usize = 32;
 
 #[derive(Default)]
 pub struct Chunks {
     loaded_chunks: HashMap<(i64, i64), Chunk>,
     unloaded_chunks: Vec<Chunk>,
+    /// Block data of edited chunks that were unloaded, so edits survive leaving the area.
+    edited_chunks: HashMap<(i64, i64), Vec<Block>>,
     noise_layers: Vec<(f32, f32)>,
+    /// Streams chunks from a Minecraft save instead of generating them from noise.
+    anvil: Option<AnvilWorld>,
+    anvil_path: String,
+    anvil_names_path: String,
+    anvil_y_offset: i64,
+    pub lod: LodSettings,
 }
 
 impl Chunks {
     pub fn from_noise(noise: Vec<(f32, f32)>) -> Self {
         Self {
             noise_layers: noise,
+            anvil_y_offset: -64,
             ..Default::default()
         }
     }
 
+    /// Fills `blocks` with the chunk at `x`, `z` as it is before any edits.
+    fn generate(&mut self, x: i64, z: i64, blocks: &mut [Block]) {
+        match &mut self.anvil {
+            Some(anvil) => {
+                if let Err(err) = anvil.load_chunk(x, z, blocks) {
+                    glazer::log!("[ERROR] failed to load chunk {x}, {z}: {err}");
+                }
+            }
+            None => generate(&self.noise_layers, x, z, blocks),
+        }
+    }
+
+    /// Switches where chunks come from, dropping every loaded chunk. Edits were made to the
+    /// blocks of the old source, so they are dropped as well.
+    pub fn set_anvil(&mut self, anvil: Option<AnvilWorld>) {
+        self.anvil = anvil;
+        self.clear();
+        self.edited_chunks.clear();
+    }
+
+    pub fn loaded(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.loaded_chunks.keys().copied()
+    }
+
+    /// Blocks of a chunk loaded at full detail, indexed with [`local_index`].
+    pub fn chunk_blocks(&self, key: (i64, i64)) -> Option<&[Block]> {
+        self.loaded_chunks
+            .get(&key)
+            .filter(|chunk| chunk.lod == 0)
+            .map(|chunk| chunk.blocks.as_slice())
+    }
+
+    /// Blocks of a loaded chunk at the level of detail it is stored at.
+    pub fn chunk_cells(&self, key: (i64, i64)) -> Option<Cells<'_>> {
+        self.loaded_chunks.get(&key).map(|chunk| Cells {
+            lod: chunk.lod,
+            blocks: &chunk.blocks,
+        })
+    }
+
+    /// Section connectivity of a loaded chunk as of its last mesh, see [`occlusion`].
+    pub fn connectivity(&self, key: (i64, i64)) -> Option<&[Connectivity; SECTIONS]> {
+        self.loaded_chunks
+            .get(&key)
+            .map(|chunk| &chunk.connectivity)
+    }
+
+    /// Loads every chunk within `view_distance` of `center` and unloads the rest.
+    pub fn load_around(&mut self, center: (i64, i64), view_distance: usize) {
+        self.stream_around(center, view_distance, usize::MAX);
+    }
+
+    /// Like [`Chunks::load_around`], but loads at most `budget` chunks, nearest first, so large
+    /// view distances fill in over several frames.
+    ///
+    /// Chunks are stored at the level of detail [`Chunks::lod`] gives them around `center`.
+    /// Chunks that need less detail than they are stored at are downsampled, and those that need
+    /// more are loaded again.
+    pub fn stream_around(&mut self, center: (i64, i64), view_distance: usize, budget: usize) {
+        let view_distance = view_distance as i64;
+        let zrange = center.1 - view_distance..=center.1 + view_distance;
+        let xrange = center.0 - view_distance..=center.0 + view_distance;
+
+        let unloaded = self
+            .loaded_chunks
+            .extract_if(|(x, z), _| !xrange.contains(x) || !zrange.contains(z));
+        for (key, chunk) in unloaded {
+            if chunk.edited {
+                self.edited_chunks.insert(key, chunk.blocks.clone());
+            }
+            self.unloaded_chunks.push(chunk);
+        }
+
+        let coarser = self
+            .loaded_chunks
+            .iter()
+            .map(|(key, chunk)| (*key, chunk.lod, self.lod.level(*key, center)))
+            .filter(|(_, stored, lod)| lod > stored)
+            .collect::<Vec<_>>();
+        for (key, _, lod) in coarser {
+            self.downsample(key, lod);
+        }
+
+        let mut missing = zrange
+            .flat_map(|z| xrange.clone().map(move |x| (x, z)))
+            .filter(|key| {
+                self.loaded_chunks
+                    .get(key)
+                    .is_none_or(|chunk| chunk.lod > self.lod.level(*key, center))
+            })
+            .collect::<Vec<_>>();
+        missing.sort_by_key(|(x, z)| (x - center.0).pow(2) + (z - center.1).pow(2));
+        for (x, z) in missing.into_iter().take(budget) {
+            // downsampled chunks are never edited, their edits are kept in `edited_chunks`
+            if let Some(chunk) = self.loaded_chunks.remove(&(x, z)) {
+                self.unloaded_chunks.push(chunk);
+            }
+            let lod = self.lod.level((x, z), center);
+            load_chunk(self, x, z, lod);
+        }
+    }
+
+    /// Stores a loaded chunk in cells of `2^lod` blocks, see [`mesh::downsample`]. Its blocks
+    /// are kept in `edited_chunks` if they were edited, and its connectivity stays as it was at
+    /// full detail.
+    fn downsample(&mut self, key: (i64, i64), lod: u32) {
+        let Some(chunk) = self.loaded_chunks.get_mut(&key) else {
+            return;
+        };
+        if chunk.lod >= lod {
+            return;
+        }
+        if chunk.lod == 0 {
+            chunk.connectivity = occlusion::chunk_connectivity(&chunk.blocks);
+        }
+
+        let cells = Cells {
+            lod: chunk.lod,
+            blocks: &chunk.blocks,
+        };
+        let (width, height) = (CHUNK_SIZE >> lod, CHUNK_HEIGHT >> lod);
+        let positions = (0..height)
+            .flat_map(|y| (0..width).flat_map(move |z| (0..width).map(move |x| (x, y, z))))
+            .collect::<Vec<_>>();
+        let blocks = positions
+            .iter()
+            .map(|&(x, y, z)| mesh::downsample(cells, lod, x, y, z))
+            .collect();
+        let light = positions
+            .iter()
+            .map(|&(x, y, z)| light::downsample(&chunk.light, chunk.lod, lod, x, y, z))
+            .collect();
+
+        let blocks = core::mem::replace(&mut chunk.blocks, blocks);
+        if chunk.edited {
+            self.edited_chunks.insert(key, blocks);
+            chunk.edited = false;
+        }
+        chunk.light = light;
+        chunk.lod = lod;
+        chunk.dirty = true;
+
+        // border faces of the neighbours were hidden by the blocks that were there
+        for neighbour in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            if let Some(chunk) = self
+                .loaded_chunks
+                .get_mut(&(key.0 + neighbour.0, key.1 + neighbour.1))
+            {
+                chunk.dirty = true;
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
-        self.unloaded_chunks
-            .extend(self.loaded_chunks.drain().map(|(_, v)| v));
+        for (key, chunk) in self.loaded_chunks.drain() {
+            if chunk.edited {
+                self.edited_chunks.insert(key, chunk.blocks.clone());
+            }
+            self.unloaded_chunks.push(chunk);
+        }
+    }
+
+    /// Packed sky and block light, `None` if the chunk is not loaded at full detail or `position`
+    /// is outside the world height. Light does not spread through downsampled chunks.
+    pub fn light(&self, position: IVec3) -> Option<u8> {
+        let (key, index) = chunk_index(position);
+        Some(self.full_chunk(key)?.light[index?])
+    }
+
+    /// Packed light of a loaded block, or of the cell holding it in downsampled chunks.
+    pub fn sample_light(&self, position: IVec3) -> Option<u8> {
+        let (key, local) = chunk_local(position);
+        let chunk = self.loaded_chunks.get(&key)?;
+        Some(chunk.light[chunk.index(local?)])
+    }
+
+    /// Sets the packed light of a block loaded at full detail, marking meshes that sample it as
+    /// dirty.
+    pub fn set_light(&mut self, position: IVec3, light: u8) {
+        let (key, Some(index)) = chunk_index(position) else {
+            return;
+        };
+        if let Some(chunk) = self.loaded_chunks.get_mut(&key)
+            && chunk.lod == 0
+        {
+            chunk.light[index] = light;
+            self.mark_dirty(position);
+        }
+    }
+
+    /// Blocks and packed light of a chunk loaded at full detail, indexed with [`local_index`].
+    pub fn chunk_light_mut(&mut self, key: (i64, i64)) -> Option<(&[Block], &mut [u8])> {
+        self.loaded_chunks
+            .get_mut(&key)
+            .filter(|chunk| chunk.lod == 0)
+            .map(|chunk| (chunk.blocks.as_slice(), chunk.light.as_mut_slice()))
+    }
+
+    /// Block at `position`, or the cell holding it in downsampled chunks.
+    pub fn block(&self, position: IVec3) -> Block {
+        let (key, local) = chunk_local(position);
+        match (self.loaded_chunks.get(&key), local) {
+            (Some(chunk), Some(local)) => chunk.blocks[chunk.index(local)],
+            _ => Block::Air,
+        }
+    }
+
+    fn full_chunk(&self, key: (i64, i64)) -> Option<&Chunk> {
+        self.loaded_chunks.get(&key).filter(|chunk| chunk.lod == 0)
+    }
+
+    /// Height of the highest solid block in a column, `None` if the chunk is not loaded at full
+    /// detail.
+    pub fn surface_height(&self, x: i32, z: i32) -> Option<i32> {
+        let (key, Some(index)) = chunk_index(IVec3::new(x, MIN_Y as i32, z)) else {
+            return None;
+        };
+        let chunk = self.full_chunk(key)?;
+        let layer = CHUNK_SIZE * CHUNK_SIZE;
+        (0..CHUNK_HEIGHT)
+            .rev()
+            .find(|y| !chunk.blocks[index + y * layer].is_air())
+            .map(|y| y as i32 + MIN_Y as i32)
+    }
+
+    /// Replaces the block at `position`, returning the previous block.
+    ///
+    /// Chunks that are not loaded are generated and kept in `edited_chunks`. Downsampled chunks
+    /// are unloaded, so they are streamed in again with the edit.
+    pub fn set_block(&mut self, position: IVec3, block: Block) -> Block {
+        let (key, Some(index)) = chunk_index(position) else {
+            return Block::Air;
+        };
+
+        if self
+            .loaded_chunks
+            .get(&key)
+            .is_some_and(|chunk| chunk.lod > 0)
+        {
+            let chunk = self.loaded_chunks.remove(&key).unwrap();
+            self.unloaded_chunks.push(chunk);
+        }
+        let previous = match self.loaded_chunks.get_mut(&key) {
+            Some(chunk) => {
+                chunk.edited = true;
+                core::mem::replace(&mut chunk.blocks[index], block)
+            }
+            None => {
+                if !self.edited_chunks.contains_key(&key) {
+                    let mut blocks = vec![Block::Air; CHUNK_VOLUME];
+                    self.generate(key.0, key.1, &mut blocks);
+                    self.edited_chunks.insert(key, blocks);
+                }
+                let blocks = self.edited_chunks.get_mut(&key).unwrap();
+                core::mem::replace(&mut blocks[index], block)
+            }
+        };
+
+        if self.loaded_chunks.contains_key(&key) {
+            self.mark_dirty(position);
+            light::block_changed(self, position, previous, block);
+        }
+
+        previous
+    }
+
+    /// Marks the chunk of `position` as dirty, along with the neighbours whose faces or lighting
+    /// can depend on it.
+    fn mark_dirty(&mut self, position: IVec3) {
+        let (key, _) = chunk_index(position);
+        let local = position.rem_euclid(IVec3::splat(CHUNK_SIZE as i32));
+        let offsets = |local: i32| -> &[i64] {
+            match local {
+                0 => &[0, -1],
+                local if local == CHUNK_SIZE as i32 - 1 => &[0, 1],
+                _ => &[0],
+            }
+        };
+        for &dx in offsets(local.x) {
+            for &dz in offsets(local.z) {
+                if let Some(chunk) = self.loaded_chunks.get_mut(&(key.0 + dx, key.1 + dz)) {
+                    chunk.dirty = true;
+                }
+            }
+        }
+    }
+
+    /// Walks the voxel grid along a ray, returning the first solid block and the normal of the
+    /// face that was hit.
+    ///
+    /// http://www.cse.yorku.ca/~amana/research/grid.pdf
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> Option<(IVec3, IVec3)> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return None;
+        }
+
+        // blocks are centered on integer coordinates
+        let origin = origin + 0.5;
+        let mut voxel = origin.floor().as_ivec3();
+        let step = direction.signum().as_ivec3();
+        let delta = direction.recip().abs();
+        let next_boundary = |o: f32, d: f32| {
+            if d > 0.0 {
+                o.floor() + 1.0 - o
+            } else {
+                o - o.floor()
+            }
+        };
+        let mut t_max = Vec3::new(
+            next_boundary(origin.x, direction.x),
+            next_boundary(origin.y, direction.y),
+            next_boundary(origin.z, direction.z),
+        ) * delta;
+        let mut normal = IVec3::ZERO;
+
+        loop {
+            if !self.block(voxel).is_air() {
+                return Some((voxel, normal));
+            }
+
+            let t = t_max.min_element();
+            if t > max_distance {
+                return None;
+            }
+            if t_max.x == t {
+                voxel.x += step.x;
+                t_max.x += delta.x;
+                normal = IVec3::new(-step.x, 0, 0);
+            } else if t_max.y == t {
+                voxel.y += step.y;
+                t_max.y += delta.y;
+                normal = IVec3::new(0, -step.y, 0);
+            } else {
+                voxel.z += step.z;
+                t_max.z += delta.z;
+                normal = IVec3::new(0, 0, -step.z);
+            }
+        }
+    }
+}
+
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE;
+
+/// Chunk key and position in the chunk of a world position, `None` if it is outside the world
+/// height.
+fn chunk_local(position: IVec3) -> ((i64, i64), Option<[usize; 3]>) {
+    let size = CHUNK_SIZE as i64;
+    let x = position.x as i64;
+    let z = position.z as i64;
+    let y = position.y as i64 - MIN_Y;
+    let key = (x.div_euclid(size), z.div_euclid(size));
+    let local = (0..CHUNK_HEIGHT as i64).contains(&y).then(|| {
+        [
+            x.rem_euclid(size) as usize,
+            y as usize,
+            z.rem_euclid(size) as usize,
+        ]
+    });
+    (key, local)
+}
+
+/// Chunk key and block index of a world position, `None` if it is outside the world height.
+fn chunk_index(position: IVec3) -> ((i64, i64), Option<usize>) {
+    let (key, local) = chunk_local(position);
+    (key, local.map(|[x, y, z]| local_index(x, y, z)))
+}
+
+pub fn local_index(x: usize, y: usize, z: usize) -> usize {
+    cell_index(0, x, y, z)
+}
+
+/// Index of a cell of `2^lod` blocks in a chunk stored at that level of detail.
+pub fn cell_index(lod: u32, x: usize, y: usize, z: usize) -> usize {
+    let width = CHUNK_SIZE >> lod;
+    (y * width + z) * width + x
+}
+
+/// Indices of the cells of a chunk stored at level `from` that make up cell `x`, `y`, `z` at
+/// the coarser level `lod`.
+pub fn cells_within(
+    from: u32,
+    lod: u32,
+    x: usize,
+    y: usize,
+    z: usize,
+) -> impl Iterator<Item = usize> {
+    let size = 1 << (lod - from);
+    let (x, y, z) = (x * size, y * size, z * size);
+    (0..size).flat_map(move |dy| {
+        (0..size)
+            .flat_map(move |dz| (0..size).map(move |dx| cell_index(from, x + dx, y + dy, z + dz)))
+    })
+}
+
+/// Blocks of a loaded chunk stored in cells of `2^lod` blocks, indexed with [`cell_index`].
+#[derive(Clone, Copy)]
+pub struct Cells<'a> {
+    pub lod: u32,
+    pub blocks: &'a [Block],
+}
+
+impl Cells<'_> {
+    /// Block at a position in the chunk, or the cell holding it.
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Block {
+        self.blocks[cell_index(self.lod, x >> self.lod, y >> self.lod, z >> self.lod)]
     }
 }
 
-#[derive(Default)]
 struct Chunk {
-    buffers: Option<(VoxelInstanceBuffer, Vec<[Vec2; 6]>)>,
+    /// Blocks at full detail, or cells of `2^lod` blocks for chunks far from the camera.
+    blocks: Vec<Block>,
+    /// Sky and block light, see [`light`], of each of `blocks`.
+    light: Vec<u8>,
+    buffers: Option<ChunkMesh>,
+    /// Translucent faces of `buffers`, sorted back to front when the camera moves.
+    translucent: Vec<SortedFace>,
+    /// Camera position `translucent` was last sorted for.
+    sorted_from: Option<Vec3>,
+    /// Level of detail `blocks` are stored and meshed at, see [`lod`](crate::lod).
+    lod: u32,
+    /// Which faces of each section see each other, updated with the mesh while the chunk is at
+    /// full detail.
+    connectivity: [Connectivity; SECTIONS],
+    /// Blocks changed since the last mesh.
+    dirty: bool,
+    /// Blocks differ from what the noise generates.
+    edited: bool,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self {
+            blocks: vec![Block::Air; CHUNK_VOLUME],
+            light: vec![0; CHUNK_VOLUME],
+            buffers: None,
+            translucent: Vec::new(),
+            sorted_from: None,
+            lod: 0,
+            connectivity: [Connectivity::default(); SECTIONS],
+            dirty: true,
+            edited: false,
+        }
+    }
 }
 
 impl Chunk {
-    fn with_capacity(capacity: usize) -> Self {
-        Self { buffers: None }
+    /// Index of the block at a position in the chunk, or of the cell holding it.
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        cell_index(self.lod, x >> self.lod, y >> self.lod, z >> self.lod)
     }
 }
 
+/// Chunks generated and lit per frame, more make moving around stutter.
+const MAX_LOADS_PER_FRAME: usize = 16;
+/// Dirty chunks meshed per frame, nearest first, so crossing a level of detail boundary is spread
+/// over several frames.
+const MAX_MESHES_PER_FRAME: usize = 16;
+
 pub fn update(
     gl: &glow::Context,
     voxel_renderer: &VoxelRenderer,
@@ -48,26 +524,47 @@ pub fn update(
     view_distance: usize,
     camera: &Camera,
 ) {
-    let view_distance = view_distance as i64;
     let current_chunk = (-camera.translation / CHUNK_SIZE as f32).as_i64vec3();
-    let zrange = current_chunk.z - view_distance..=current_chunk.z + view_distance;
-    let xrange = current_chunk.x - view_distance..=current_chunk.x + view_distance;
+    let center = (current_chunk.x, current_chunk.z);
+    chunks.stream_around(center, view_distance, MAX_LOADS_PER_FRAME);
 
-    chunks.loaded_chunks.retain(|(x, z), buffer| {
-        if !xrange.contains(x) || !zrange.contains(z) {
-            chunks.unloaded_chunks.push(core::mem::take(buffer));
-            false
-        } else {
-            true
+    let mut dirty = chunks
+        .loaded_chunks
+        .iter()
+        .filter(|(_, chunk)| chunk.dirty)
+        .map(|(key, _)| *key)
+        .collect::<Vec<_>>();
+    dirty.sort_by_key(|(x, z)| (x - center.0).pow(2) + (z - center.1).pow(2));
+    for key in dirty.into_iter().take(MAX_MESHES_PER_FRAME) {
+        let mesh = mesh::build_lod(chunks, key);
+        let chunk = chunks.loaded_chunks.get_mut(&key).unwrap();
+        if let Some(buffers) = chunk.buffers.take() {
+            voxel_renderer.delete_mesh(gl, buffers);
         }
-    });
+        chunk.buffers = (!mesh.is_empty()).then(|| voxel_renderer.upload_mesh(gl, &mesh));
+        chunk.translucent = mesh.translucent_faces();
+        chunk.sorted_from = None;
+        if chunk.lod == 0 {
+            chunk.connectivity = occlusion::chunk_connectivity(&chunk.blocks);
+        }
+        chunk.dirty = false;
+    }
 
-    for z in zrange {
-        for x in xrange.clone() {
-            if !chunks.loaded_chunks.contains_key(&(x, z)) {
-                load_chunk(gl, voxel_renderer, chunks, x, z);
-            }
+    let eye = camera.position();
+    for chunk in chunks.loaded_chunks.values_mut() {
+        let Some(buffers) = chunk.buffers else {
+            continue;
+        };
+        if chunk.translucent.is_empty()
+            || chunk
+                .sorted_from
+                .is_some_and(|from| from.distance_squared(eye) < 0.25)
+        {
+            continue;
         }
+        let indices = mesh::sort_back_to_front(&mut chunk.translucent, eye);
+        voxel_renderer.update_translucent(gl, buffers, &indices);
+        chunk.sorted_from = Some(eye);
     }
 }
 
@@ -76,6 +573,7 @@ pub fn ui(
     gl: &glow::Context,
     voxel_renderer: &VoxelRenderer,
     chunks: &mut Chunks,
+    history: &mut History,
     view_distance: usize,
     camera: &Camera,
 ) {
@@ -102,144 +600,317 @@ pub fn ui(
         });
     }
 
+    ui.label("Minecraft World");
+    ui.horizontal(|ui| {
+        ui.label("Save");
+        ui.text_edit_singleline(&mut chunks.anvil_path);
+    });
+    ui.horizontal(|ui| {
+        ui.label("Block Names");
+        ui.text_edit_singleline(&mut chunks.anvil_names_path)
+            .on_hover_text("Leave empty for the built-in table");
+    });
+    ui.add(egui::DragValue::new(&mut chunks.anvil_y_offset).prefix("Y Offset: "));
+    ui.horizontal(|ui| {
+        if ui.button("Load").clicked() {
+            let names = if chunks.anvil_names_path.is_empty() {
+                Ok(BlockNames::default())
+            } else {
+                BlockNames::load(&chunks.anvil_names_path)
+            };
+            match names {
+                Ok(names) => {
+                    let anvil = AnvilWorld::open(&chunks.anvil_path, names, chunks.anvil_y_offset);
+                    chunks.set_anvil(Some(anvil));
+                    history.clear();
+                    changed_chunk_generation = true;
+                }
+                Err(err) => glazer::log!(
+                    "[ERROR] failed to load block names {}: {err}",
+                    chunks.anvil_names_path
+                ),
+            }
+        }
+        if chunks.anvil.is_some() && ui.button("Use Noise").clicked() {
+            chunks.set_anvil(None);
+            history.clear();
+            changed_chunk_generation = true;
+        }
+    });
+
     if changed_chunk_generation {
         chunks.clear();
         update(gl, voxel_renderer, chunks, view_distance, camera);
     }
 }
 
-pub fn render(world: &mut World, gl: &glow::Context, width: usize, height: usize) {
+pub fn render(world: &mut World, gl: &glow::Context, width: usize, height: usize, delta: f32) {
+    world.post.begin(gl, width, height);
+    let point_lights = world
+        .point_lights
+        .visible(&world.camera, (world.view_distance * CHUNK_SIZE) as f32);
+    let sky = world.clock.sky(&world.atmosphere);
     let lighting = Lighting {
-        light_source: Vec3::new(0.0, 50.0, -120.0),
-        light_color: Vec3::ONE,
-        ambient_brightness: 0.4,
+        light_direction: sky.light_direction,
+        light_color: sky.light_color,
+        ambient_brightness: sky.ambient_brightness,
+        block_light_color: Vec3::new(1.0, 0.85, 0.6),
+        ao_strength: if world.ambient_occlusion {
+            world.ambient_occlusion_strength
+        } else {
+            0.0
+        },
+        point_lights: &point_lights,
     };
     let view = world.camera.view_matrix();
+    let proj = voxel::projection(width, height);
+    let jittered_proj = world.post.jitter() * proj;
+    let view_distance = (world.view_distance * CHUNK_SIZE) as f32;
 
-    let (fog_near, fog_far) = if world.fog {
-        let near = ((world.view_distance - 2) * CHUNK_SIZE) as f32;
-        let far = (world.view_distance * CHUNK_SIZE) as f32;
-        (near, far)
-    } else {
-        (
-            (world.view_distance * CHUNK_SIZE * 10) as f32,
-            (world.view_distance * CHUNK_SIZE * 10) as f32,
-        )
-    };
+    let renderer = &world.voxel_renderer;
+    let visible = renderer
+        .occlusion_culling
+        .then(|| {
+            let frustum = Frustum::from_matrix(proj * view);
+            occlusion::visible_chunks(
+                &world.chunks,
+                world.camera.position(),
+                renderer.frustum_culling.then_some(&frustum),
+            )
+        })
+        .flatten();
+    world.voxel_renderer.set_projection(gl, jittered_proj);
 
-    let translations_for_shadow_pass = world
-        .chunks
-        .loaded_chunks
-        .values()
-        .flat_map(|chunk| chunk.buffers.as_ref().map(|(instances, _)| *instances));
-    let translations_and_atlas_uvs = world.chunks.loaded_chunks.values().flat_map(|chunk| {
-        chunk
-            .buffers
-            .as_ref()
-            .map(|(instances, uvs)| (*instances, uvs.as_slice()))
-    });
+    // passes are given the world, so the graph is taken out of it while they run
+    let mut graph = std::mem::take(&mut world.render_graph);
+    let shadow_maps = graph.import("Shadow Maps");
+    let screen = graph.import("Screen");
+    let scene = graph.target(gl, "Scene", Format::Rgba16F);
+    let depth = graph.target(gl, "Depth", Format::Depth24);
+    let (scene_texture, depth_texture) = (graph.texture(scene), graph.texture(depth));
 
-    world.voxel_renderer.render_pass(
-        gl,
-        width,
-        height,
-        lighting,
-        view,
-        fog_near,
-        fog_far,
-        translations_for_shadow_pass,
-        translations_and_atlas_uvs,
-    );
-
-    world.sprite_renderer.render(
-        gl,
-        Vec3::new(950.0, 400.0, 0.0),
-        Vec2::ONE * 0.5,
-        glam::Quat::default(),
-        world.voxel_renderer.shadow_map,
-        1024,
-        1024,
-    );
+    let mut passes = vec![Pass::new(
+        "Shadows",
+        &[],
+        &[shadow_maps],
+        move |gl, world: &mut World| {
+            let meshes = world
+                .chunks
+                .loaded_chunks
+                .values()
+                .filter_map(|chunk| chunk.buffers);
+            world.voxel_renderer.shadow_pass(
+                gl,
+                width,
+                height,
+                view,
+                lighting.light_direction,
+                meshes,
+                world.cloud_renderer.shadow_caster(),
+            );
+        },
+    )];
+    let voxel_pass = move |gl: &glow::Context, world: &mut World| {
+        let meshes = world
+            .chunks
+            .loaded_chunks
+            .iter()
+            .filter_map(|(key, chunk)| {
+                let occluded = visible
+                    .as_ref()
+                    .is_some_and(|visible| !visible.contains(key));
+                chunk.buffers.map(|mesh| (mesh, occluded))
+            });
+        world.voxel_renderer.voxel_pass(
+            gl,
+            width,
+            height,
+            lighting,
+            view,
+            world.time,
+            &world.fog,
+            view_distance,
+            sky.horizon_color,
+            meshes,
+        );
+    };
+    match world.voxel_renderer.render_path {
+        RenderPath::Forward => passes.push(Pass::new(
+            "Voxels",
+            &[shadow_maps],
+            &[scene, depth],
+            move |gl, world: &mut World| {
+                world.post.bind_scene(gl);
+                voxel_pass(gl, world);
+            },
+        )),
+        RenderPath::Deferred => {
+            let gbuffer = deferred::gbuffer(gl, &mut graph);
+            let textures = gbuffer.map(|id| graph.texture(id));
+            // the cascades are set on the lighting shader along with the other uniforms
+            passes.push(Pass::new("G-Buffer", &[shadow_maps], &gbuffer, voxel_pass));
+            let mut reads = gbuffer.to_vec();
+            reads.push(shadow_maps);
+            passes.push(Pass::new(
+                "Lighting",
+                &reads,
+                &[scene, depth],
+                move |gl, world: &mut World| {
+                    world.post.bind_scene(gl);
+                    world
+                        .voxel_renderer
+                        .lighting_pass(gl, view, sky.horizon_color, textures);
+                },
+            ));
+        }
+    }
+    passes.push(Pass::new(
+        "Sky",
+        &[],
+        &[scene, depth],
+        move |gl, world: &mut World| {
+            world.post.bind_scene(gl);
+            world.sky_renderer.render(gl, jittered_proj, view, &sky);
+        },
+    ));
+    passes.push(Pass::new(
+        "Clouds",
+        &[],
+        &[scene, depth],
+        move |gl, world: &mut World| {
+            world.post.bind_scene(gl);
+            world
+                .cloud_renderer
+                .render(gl, jittered_proj, view, &lighting, sky.horizon_color);
+        },
+    ));
+    passes.push(Pass::new(
+        "Translucent",
+        &[shadow_maps],
+        &[scene, depth],
+        |gl, world: &mut World| {
+            world.post.bind_scene(gl);
+            world.voxel_renderer.translucent_pass(gl);
+        },
+    ));
+    if world.post.multisampled() {
+        passes.push(Pass::new(
+            "Resolve",
+            &[],
+            &[scene, depth],
+            |gl, world: &mut World| {
+                world.post.resolve(gl);
+            },
+        ));
+    }
+    passes.push(Pass::new(
+        "Post Processing",
+        &[scene, depth],
+        &[screen],
+        move |gl, world: &mut World| {
+            world
+                .post
+                .finish(gl, delta, scene_texture, depth_texture, proj, view);
+        },
+    ));
+    if world.voxel_renderer.shadow_settings.show_maps {
+        passes.push(Pass::new(
+            "Shadow Map Overlay",
+            &[shadow_maps],
+            &[screen],
+            move |gl, world: &mut World| {
+                // cascades side by side in the top right corner
+                let settings = world.voxel_renderer.shadow_settings;
+                let size = 256.0;
+                for layer in 0..settings.cascades {
+                    world.sprite_renderer.render_layer(
+                        gl,
+                        Vec3::new(
+                            width as f32 / 2.0 - size * (settings.cascades - layer) as f32
+                                + size / 2.0,
+                            height as f32 / 2.0 - size / 2.0,
+                            0.0,
+                        ),
+                        Vec2::ONE,
+                        world.voxel_renderer.shadow_map,
+                        layer,
+                        size as usize,
+                        size as usize,
+                    );
+                }
+            },
+        ));
+    }
+    graph.execute(gl, world, passes);
+    world.render_graph = graph;
 }
 
-// TODO: this shit is trash
-fn load_chunk(
-    gl: &glow::Context,
-    voxel_renderer: &VoxelRenderer,
-    chunks: &mut Chunks,
-    x: i64,
-    z: i64,
-) {
-    let perlin_scale = 200;
+/// Loads and lights a chunk at full detail, then downsamples it to `lod`.
+fn load_chunk(chunks: &mut Chunks, x: i64, z: i64, lod: u32) {
+    let mut chunk = chunks.unloaded_chunks.pop().unwrap_or_default();
+    match chunks.edited_chunks.remove(&(x, z)) {
+        Some(blocks) => {
+            chunk.blocks = blocks;
+            chunk.edited = true;
+        }
+        None => {
+            chunk.blocks.resize(CHUNK_VOLUME, Block::Air);
+            chunks.generate(x, z, &mut chunk.blocks);
+            chunk.edited = false;
+        }
+    }
+    chunk.light.clear();
+    chunk.light.resize(CHUNK_VOLUME, 0);
+    chunk.lod = 0;
+    chunk.dirty = true;
 
-    let mut chunk = chunks
-        .unloaded_chunks
-        .pop()
-        .unwrap_or_else(|| Chunk::with_capacity(CHUNK_SIZE * CHUNK_SIZE));
+    // neighbours treated the missing chunk as solid, so their border faces need a new mesh
+    for neighbour in [(x - 1, z), (x + 1, z), (x, z - 1), (x, z + 1)] {
+        if let Some(chunk) = chunks.loaded_chunks.get_mut(&neighbour) {
+            chunk.dirty = true;
+        }
+    }
 
-    let mut translations = Vec::new();
-    let mut uvs = Vec::new();
+    assert!(chunks.loaded_chunks.insert((x, z), chunk).is_none());
+    light::light_chunk(chunks, (x, z));
+    chunks.downsample((x, z), lod);
+}
+
+fn generate(noise_layers: &[(f32, f32)], x: i64, z: i64, blocks: &mut [Block]) {
+    let perlin_scale = 200.0;
 
+    blocks.fill(Block::Air);
     let zoffset = z as f32 * CHUNK_SIZE as f32;
     let xoffset = x as f32 * CHUNK_SIZE as f32;
-    for z in 0..CHUNK_SIZE {
-        for x in 0..CHUNK_SIZE {
-            let z = z as f32 + zoffset;
-            let x = x as f32 + xoffset;
-            let uv = Vec2::new(x / perlin_scale as f32, z / perlin_scale as f32);
+    for lz in 0..CHUNK_SIZE {
+        for lx in 0..CHUNK_SIZE {
+            let z = lz as f32 + zoffset;
+            let x = lx as f32 + xoffset;
+            let uv = Vec2::new(x / perlin_scale, z / perlin_scale);
 
             let mut surface = 0.0;
-            for (uv_scale, weight) in chunks.noise_layers.iter() {
+            for (uv_scale, weight) in noise_layers.iter() {
                 surface += (perlin(uv * *uv_scale) * 0.5 + 0.5) * weight;
             }
 
-            translations.push(Vec3::new(x, surface.round() - 80.0, z));
-            uvs.push([Vec2::new(0.0, 1.0); 6]);
-            // for y in 0..surface.round() as usize {
-            //     translations.push(Vec3::new(x, y as f32 - 80.0, z));
-            //     uvs.push([Vec2::new(0.0, 1.0); 6]);
-            // }
-        }
-    }
-
-    let mut hash = HashSet::<(i64, i64, i64)>::from_iter(
-        translations
-            .iter()
-            .map(|t| (t.x as i64, t.y as i64, t.z as i64)),
-    );
-    let mut remove = Vec::new();
-    'outer: for (i, translation) in translations.iter().enumerate() {
-        for z in -1..=1 {
-            for y in -1..=1 {
-                for x in -1..=1 {
-                    if hash.insert((
-                        translation.x as i64 + x,
-                        translation.y as i64 + y,
-                        translation.z as i64 + z,
-                    )) {
-                        continue 'outer;
-                    }
-                }
+            let height = (surface.round() as i64 - 80 - MIN_Y).clamp(0, CHUNK_HEIGHT as i64 - 1);
+            for y in 0..=height {
+                blocks[local_index(lx, y as usize, lz)] = if y == 0 {
+                    Block::Bedrock
+                } else if y == height {
+                    Block::Grass
+                } else if y > height - 4 {
+                    Block::Dirt
+                } else {
+                    Block::Stone
+                };
             }
         }
-        remove.push(i);
     }
-
-    for index in remove.into_iter().rev() {
-        translations.swap_remove(index);
-        uvs.swap_remove(index);
-    }
-
-    chunk.buffers = Some((
-        voxel_renderer.generate_translation_buffer(gl, &translations),
-        uvs,
-    ));
-
-    assert!(chunks.loaded_chunks.insert((x, z), chunk).is_none());
 }
 
 // https://thebookofshaders.com/edit.php#11/2d-gnoise.frag
-fn perlin(st: Vec2) -> f32 {
+pub fn perlin(st: Vec2) -> f32 {
     fn random2(st: Vec2) -> Vec2 {
         fn random(v: f32) -> f32 {
             -1.0 + 2.0 * (v.sin() * 43758.547).fract()
@@ -264,3 +935,60 @@ fn perlin(st: Vec2) -> f32 {
         .lerp(random2(i + Vec2::ONE).dot(f - Vec2::ONE), u.x);
     left.lerp(right, u.y)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chunks with a level of detail per doubling of the distance from the first chunk out, so
+    /// chunks 2 away are at level 2.
+    fn chunks() -> Chunks {
+        let mut chunks = Chunks::from_noise(DEFAULT_NOISE_LAYERS.to_vec());
+        chunks.lod.distance = 1;
+        chunks.load_around((0, 0), 2);
+        chunks
+    }
+
+    /// Air block above the terrain of the middle column of the chunk at `key`.
+    fn above_surface(chunks: &Chunks, key: (i64, i64)) -> IVec3 {
+        let (x, z) = (key.0 as i32 * 16 + 8, key.1 as i32 * 16 + 8);
+        IVec3::new(x, chunks.surface_height(x, z).unwrap() + 1, z)
+    }
+
+    #[test]
+    fn distant_chunks_only_store_cells() {
+        let mut chunks = chunks();
+        assert!(chunks.chunk_blocks((0, 0)).is_some());
+        assert_eq!(chunks.chunk_cells((1, 0)).unwrap().lod, 1);
+        let cells = chunks.chunk_cells((2, 0)).unwrap();
+        assert_eq!(cells.lod, 2);
+        assert_eq!(cells.blocks.len(), CHUNK_VOLUME / 64);
+        assert!(chunks.chunk_blocks((2, 0)).is_none());
+        assert!(chunks.light(IVec3::new(32, 0, 0)).is_none());
+        assert!(chunks.sample_light(IVec3::new(32, 0, 0)).is_some());
+
+        // loaded again at full detail once the camera is close, and downsampled once it leaves
+        chunks.load_around((2, 0), 2);
+        assert!(chunks.chunk_blocks((2, 0)).is_some());
+        assert_eq!(chunks.chunk_cells((0, 0)).unwrap().lod, 2);
+    }
+
+    #[test]
+    fn edits_survive_downsampling() {
+        let mut chunks = chunks();
+        let near = above_surface(&chunks, (0, 0));
+        chunks.set_block(near, Block::Glass);
+
+        // the far chunk is downsampled, so the edit unloads it until it is streamed in again
+        chunks.load_around((2, 0), 2);
+        assert!(chunks.chunk_blocks((0, 0)).is_none());
+        let far = near + IVec3::Y;
+        chunks.set_block(far, Block::Glowstone);
+        assert!(chunks.chunk_cells((0, 0)).is_none());
+
+        chunks.load_around((0, 0), 2);
+        assert_eq!(chunks.block(near), Block::Glass);
+        assert_eq!(chunks.block(far), Block::Glowstone);
+        assert_eq!(chunks.light(far).map(light::block), Some(15));
+    }
+}
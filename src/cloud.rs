@@ -0,0 +1,231 @@
+//! A layer of blocky clouds drifting with the wind, lit by the sun and casting shadows.
+//!
+//! Clouds are boxes on a grid of cells picked by noise. The grid is fixed in cloud space, which
+//! the wind moves over the world, so the mesh only changes when the camera crosses into another
+//! cell and the drift is added when drawing.
+
+use crate::block::Face;
+use crate::chunk::perlin;
+use crate::mesh::Mesh;
+use crate::shader::uniform;
+use crate::voxel::{ChunkMesh, Lighting, VoxelRenderer};
+use glam::{IVec2, Mat4, Vec2, Vec3};
+use glazer::glow::{self, HasContext};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CloudSettings {
+    pub enabled: bool,
+    /// Height of the bottom of the clouds.
+    pub altitude: f32,
+    pub thickness: f32,
+    /// Width of a cell of the cloud grid in blocks.
+    pub cell_size: f32,
+    /// Roughly the fraction of the sky covered by clouds.
+    pub coverage: f32,
+    /// Cells drawn in every direction from the camera.
+    pub radius: i32,
+    /// Blocks per second the clouds drift along X and Z.
+    pub wind: Vec2,
+    pub opacity: f32,
+    /// Draws the clouds into the shadow maps.
+    pub shadows: bool,
+}
+
+impl Default for CloudSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            altitude: 192.0,
+            thickness: 4.0,
+            cell_size: 12.0,
+            coverage: 0.4,
+            radius: 24,
+            wind: Vec2::new(2.0, 0.5),
+            opacity: 0.85,
+            shadows: true,
+        }
+    }
+}
+
+impl CloudSettings {
+    fn covered(&self, cell: IVec2) -> bool {
+        let position = cell.as_vec2();
+        let noise = perlin(position * 0.09) + perlin(position * 0.31) * 0.5;
+        noise * 0.5 + 0.5 > 1.0 - self.coverage
+    }
+
+    /// Cell of the cloud grid above `position` after the clouds drifted by `drift`.
+    fn cell(&self, position: Vec3, drift: Vec2) -> IVec2 {
+        ((Vec2::new(position.x, position.z) - drift) / self.cell_size)
+            .floor()
+            .as_ivec2()
+    }
+}
+
+/// Boxes of the covered cells within [`CloudSettings::radius`] of `center`, in cloud space.
+fn build(settings: &CloudSettings, center: IVec2) -> Mesh {
+    let mut mesh = Mesh::default();
+    let size = Vec3::new(settings.cell_size, settings.thickness, settings.cell_size);
+    for z in -settings.radius..=settings.radius {
+        for x in -settings.radius..=settings.radius {
+            let cell = center + IVec2::new(x, z);
+            if !settings.covered(cell) {
+                continue;
+            }
+            let middle = Vec3::new(
+                (cell.x as f32 + 0.5) * settings.cell_size,
+                settings.altitude + settings.thickness / 2.0,
+                (cell.y as f32 + 0.5) * settings.cell_size,
+            );
+            for face in Face::ALL {
+                let normal = face.normal();
+                // neighbouring clouds join into one
+                if normal.y == 0 && settings.covered(cell + IVec2::new(normal.x, normal.z)) {
+                    continue;
+                }
+                mesh.push_box_face(face, middle, size);
+            }
+        }
+    }
+    mesh
+}
+
+pub fn ui(ui: &mut egui::Ui, settings: &mut CloudSettings) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.enabled, "Clouds");
+        ui.add_enabled(
+            settings.enabled,
+            egui::Checkbox::new(&mut settings.shadows, "Cast Shadows"),
+        );
+    });
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        ui.add(egui::Slider::new(&mut settings.altitude, 0.0..=256.0).text("Altitude"));
+        ui.add(egui::Slider::new(&mut settings.thickness, 1.0..=16.0).text("Thickness"));
+        ui.add(egui::Slider::new(&mut settings.cell_size, 4.0..=32.0).text("Cell Size"));
+        ui.add(egui::Slider::new(&mut settings.coverage, 0.0..=1.0).text("Coverage"));
+        ui.add(egui::Slider::new(&mut settings.radius, 4..=64).text("Radius"));
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut settings.wind.x).speed(0.1));
+            ui.add(egui::DragValue::new(&mut settings.wind.y).speed(0.1));
+            ui.label("Wind");
+        });
+        ui.add(egui::Slider::new(&mut settings.opacity, 0.0..=1.0).text("Opacity"));
+    });
+}
+
+pub struct CloudRenderer {
+    pub settings: CloudSettings,
+    shader: glow::Program,
+    mesh: Option<ChunkMesh>,
+    /// Settings and camera cell `mesh` was built for.
+    built: Option<(CloudSettings, IVec2)>,
+    /// Blocks the wind moved the clouds by.
+    drift: Vec2,
+}
+
+impl CloudRenderer {
+    pub fn new(gl: &glow::Context) -> Self {
+        Self {
+            settings: CloudSettings::default(),
+            shader: crate::compile_shader!(gl, "shaders/cloud.vert", "shaders/cloud.frag"),
+            mesh: None,
+            built: None,
+            drift: Vec2::ZERO,
+        }
+    }
+
+    /// Moves the clouds with the wind and rebuilds the mesh after the camera entered another cell
+    /// or the settings changed.
+    pub fn update(
+        &mut self,
+        gl: &glow::Context,
+        voxel_renderer: &VoxelRenderer,
+        camera_position: Vec3,
+        delta: f32,
+    ) {
+        self.drift += self.settings.wind * delta;
+        let center = self.settings.cell(camera_position, self.drift);
+        if self.built == Some((self.settings, center)) {
+            return;
+        }
+        if let Some(mesh) = self.mesh.take() {
+            voxel_renderer.delete_mesh(gl, mesh);
+        }
+        let mesh = build(&self.settings, center);
+        if !mesh.is_empty() {
+            self.mesh = Some(voxel_renderer.upload_mesh(gl, &mesh));
+        }
+        self.built = Some((self.settings, center));
+    }
+
+    /// Offset from cloud space to the world.
+    fn offset(&self) -> Vec3 {
+        Vec3::new(self.drift.x, 0.0, self.drift.y)
+    }
+
+    /// Mesh and offset to draw into the shadow maps, if clouds cast shadows.
+    pub fn shadow_caster(&self) -> Option<(ChunkMesh, Vec3)> {
+        let settings = &self.settings;
+        self.mesh
+            .filter(|_| settings.enabled && settings.shadows)
+            .map(|mesh| (mesh, self.offset()))
+    }
+
+    /// Draws the clouds over the sky and terrain, fading into `fog_color` towards the edge.
+    pub fn render(
+        &self,
+        gl: &glow::Context,
+        proj: Mat4,
+        view: Mat4,
+        lighting: &Lighting,
+        fog_color: Vec3,
+    ) {
+        let Some(mesh) = self.mesh.filter(|_| self.settings.enabled) else {
+            return;
+        };
+        let camera_position = view.inverse().w_axis.truncate();
+        unsafe {
+            gl.use_program(Some(self.shader));
+            for (name, matrix) in [("proj", proj), ("view", view)] {
+                uniform(gl, self.shader, name, |location| {
+                    gl.uniform_matrix_4_f32_slice(location, false, &matrix.to_cols_array());
+                });
+            }
+            for (name, value) in [
+                ("offset", self.offset()),
+                ("light_direction", lighting.light_direction),
+                ("light_color", lighting.light_color),
+                ("fog_color", fog_color),
+                ("camera_position", camera_position),
+            ] {
+                uniform(gl, self.shader, name, |location| {
+                    gl.uniform_3_f32(location, value.x, value.y, value.z);
+                });
+            }
+            for (name, value) in [
+                ("ambient_brightness", lighting.ambient_brightness),
+                (
+                    "fade_distance",
+                    self.settings.radius as f32 * self.settings.cell_size,
+                ),
+                ("opacity", self.settings.opacity),
+            ] {
+                uniform(gl, self.shader, name, |location| {
+                    gl.uniform_1_f32(location, value);
+                });
+            }
+
+            gl.enable(glow::DEPTH_TEST);
+            gl.depth_func(glow::LESS);
+            gl.enable(glow::CULL_FACE);
+            gl.cull_face(glow::BACK);
+            gl.enable(glow::BLEND);
+            gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+            mesh.draw_layers(gl, 0..3);
+            gl.disable(glow::BLEND);
+            gl.disable(glow::CULL_FACE);
+            gl.bind_vertex_array(None);
+            gl.use_program(None);
+        }
+    }
+}
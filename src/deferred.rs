@@ -0,0 +1,53 @@
+//! Deferred shading, the voxel pass writes what lighting needs into a G-buffer and a fullscreen
+//! pass shades every pixel once from it.
+//!
+//! Translucent faces are blended over what is behind them, so they are still shaded forward.
+
+use crate::render_graph::{Format, RenderGraph, TargetId};
+use glazer::glow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Shades every face as it is drawn.
+    Forward,
+    /// Shades every pixel once after all opaque faces are drawn.
+    Deferred,
+}
+
+impl RenderPath {
+    pub const ALL: [RenderPath; 2] = [RenderPath::Forward, RenderPath::Deferred];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderPath::Forward => "Forward",
+            RenderPath::Deferred => "Deferred",
+        }
+    }
+}
+
+/// Targets written by the voxel shader compiled with `DEFERRED`, in the order of its outputs
+/// and then the samplers of `deferred.frag`.
+pub const GBUFFER: [(&str, Format); 4] = [
+    // linear colour of the surface, with its emission in alpha
+    ("Albedo Emission", Format::Rgba16F),
+    // normal scaled into `0..1`
+    ("Normal", Format::Rgba8),
+    // sky light, block light and ambient occlusion
+    ("Light AO", Format::Rgba8),
+    ("G-Buffer Depth", Format::Depth24),
+];
+
+/// Declares the G-buffer in `graph`.
+pub fn gbuffer(gl: &glow::Context, graph: &mut RenderGraph) -> [TargetId; 4] {
+    GBUFFER.map(|(name, format)| graph.target(gl, name, format))
+}
+
+pub fn ui(ui: &mut egui::Ui, render_path: &mut RenderPath) {
+    egui::ComboBox::from_label("Render Path")
+        .selected_text(render_path.name())
+        .show_ui(ui, |ui| {
+            for path in RenderPath::ALL {
+                ui.selectable_value(render_path, path, path.name());
+            }
+        });
+}
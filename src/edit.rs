@@ -0,0 +1,198 @@
+use crate::{
+    block::Block,
+    brush::{self, Brush},
+    camera::Camera,
+    chunk::Chunks,
+    history::History,
+    vox::{self, VoxModel},
+};
+use glam::IVec3;
+use glazer::winit::event::{ElementState, MouseButton};
+
+/// Reach of the crosshair when breaking and placing blocks.
+const REACH: f32 = 64.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// Break blocks with the left and place them with the right mouse button.
+    Block,
+    Brush,
+}
+
+pub struct Editor {
+    pub tool: Tool,
+    pub block: Block,
+    pub brush: Brush,
+    region: [IVec3; 2],
+    vox_path: String,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            tool: Tool::Block,
+            block: Block::Stone,
+            brush: Brush::default(),
+            region: [IVec3::ZERO; 2],
+            vox_path: String::from("assets/model.vox"),
+        }
+    }
+}
+
+pub fn handle_mouse(
+    editor: &mut Editor,
+    button: MouseButton,
+    state: ElementState,
+    camera: &Camera,
+    chunks: &mut Chunks,
+    history: &mut History,
+) {
+    if !state.is_pressed() {
+        if button == MouseButton::Left {
+            end_stroke(editor, history);
+        }
+        return;
+    }
+
+    let Some((target, normal)) = chunks.raycast(camera.position(), camera.forward(), REACH) else {
+        return;
+    };
+
+    match (editor.tool, button) {
+        (Tool::Block, MouseButton::Left) => history.set_block(chunks, target, Block::Air),
+        (Tool::Block, MouseButton::Right) if normal != IVec3::ZERO => {
+            history.set_block(chunks, target + normal, editor.block)
+        }
+        (Tool::Brush, MouseButton::Left) => editor.brush.begin(history, target),
+        _ => {}
+    }
+}
+
+/// Ends the brush stroke, whatever the tool is now, so later edits are not merged into its
+/// transaction.
+pub fn end_stroke(editor: &mut Editor, history: &mut History) {
+    editor.brush.end(history);
+}
+
+/// Applies the brush under the crosshair while the mouse button is held.
+pub fn update(
+    editor: &mut Editor,
+    camera: &Camera,
+    chunks: &mut Chunks,
+    history: &mut History,
+    delta: f32,
+) {
+    if editor.tool != Tool::Brush {
+        return;
+    }
+    if let Some((target, _)) = chunks.raycast(camera.position(), camera.forward(), REACH) {
+        editor.brush.apply(chunks, history, target, delta);
+    }
+}
+
+pub fn ui(
+    ui: &mut egui::Ui,
+    editor: &mut Editor,
+    camera: &Camera,
+    chunks: &mut Chunks,
+    history: &mut History,
+) {
+    ui.label("Editing");
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut editor.tool, Tool::Block, "Block");
+        ui.selectable_value(&mut editor.tool, Tool::Brush, "Brush");
+    });
+    if editor.tool != Tool::Brush {
+        end_stroke(editor, history);
+    }
+    match editor.tool {
+        Tool::Block => block_combo_box(ui, "Block", &mut editor.block),
+        Tool::Brush => brush::ui(ui, &mut editor.brush),
+    }
+
+    let target = chunks
+        .raycast(camera.position(), camera.forward(), REACH)
+        .map(|(target, _)| target);
+    for (corner, name) in editor.region.iter_mut().zip(["A", "B"]) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Corner {name}"));
+            ui.add(egui::DragValue::new(&mut corner.x).prefix("x: "));
+            ui.add(egui::DragValue::new(&mut corner.y).prefix("y: "));
+            ui.add(egui::DragValue::new(&mut corner.z).prefix("z: "));
+            if let Some(target) = target
+                && ui.button("Target").clicked()
+            {
+                *corner = target;
+            }
+        });
+    }
+    ui.horizontal(|ui| {
+        let [a, b] = editor.region;
+        if ui.button("Fill").clicked() {
+            end_stroke(editor, history);
+            history.fill(chunks, a, b, editor.block);
+        }
+        if ui.button("Clear").clicked() {
+            end_stroke(editor, history);
+            history.fill(chunks, a, b, Block::Air);
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("MagicaVoxel");
+        ui.text_edit_singleline(&mut editor.vox_path);
+    });
+    ui.horizontal(|ui| {
+        let [a, b] = editor.region;
+        if ui.button("Import at A").clicked() {
+            match vox::load(&editor.vox_path) {
+                Ok(model) => {
+                    end_stroke(editor, history);
+                    history.begin(format!("Import {}", editor.vox_path));
+                    for (position, block) in model.blocks {
+                        history.set_block(chunks, a + position, block);
+                    }
+                    history.commit();
+                }
+                Err(err) => glazer::log!("[ERROR] failed to import {}: {err}", editor.vox_path),
+            }
+        }
+        if ui.button("Export A..B").clicked() {
+            let min = a.min(b);
+            let max = a.max(b);
+            let size = max - min + 1;
+            // checked before collecting, the region could be far larger than any model
+            if size.cmpgt(IVec3::splat(vox::MAX_SIZE)).any() {
+                glazer::log!(
+                    "[ERROR] failed to export {}: the region is larger than {} blocks along an axis",
+                    editor.vox_path,
+                    vox::MAX_SIZE
+                );
+                return;
+            }
+            let mut blocks = Vec::new();
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    for x in min.x..=max.x {
+                        let position = IVec3::new(x, y, z);
+                        blocks.push((position - min, chunks.block(position)));
+                    }
+                }
+            }
+            let model = VoxModel { size, blocks };
+            if let Err(err) = vox::save(&editor.vox_path, &model) {
+                glazer::log!("[ERROR] failed to export {}: {err}", editor.vox_path);
+            }
+        }
+    });
+}
+
+pub fn block_combo_box(ui: &mut egui::Ui, label: &str, block: &mut Block) {
+    egui::ComboBox::from_label(label)
+        .selected_text(block.name())
+        .show_ui(ui, |ui| {
+            for option in Block::ALL.into_iter().filter(|block| !block.is_air()) {
+                ui.selectable_value(block, option, option.name());
+            }
+        });
+}
@@ -0,0 +1,335 @@
+//! Chunk meshes written as Wavefront OBJ or binary glTF.
+//!
+//! https://paulbourke.net/dataformats/obj/
+//! https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html
+//!
+//! Both formats reference an atlas of the resource pack's textures and only contain the faces
+//! built by [`mesh::build_lod`], so no GL context is needed.
+
+use crate::{
+    anvil::{AnvilWorld, BlockNames},
+    chunk::{self, Chunks},
+    mesh::{self, Mesh},
+    resource_pack::{ATLAS_COLUMNS, ResourcePack},
+};
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// Writes the loaded chunks at their level of detail to `path`, choosing the format from its
+/// extension.
+///
+/// The textures of `pack` are written as a single [`ResourcePack::atlas`] PNG, next to OBJ files
+/// and embedded in glTF ones.
+pub fn export(chunks: &Chunks, path: &Path, pack: &ResourcePack) -> io::Result<()> {
+    let mut mesh = Mesh::default();
+    for key in chunks.loaded() {
+        mesh.append(&mesh::build_lod(chunks, key));
+    }
+    if mesh.is_empty() {
+        return Err(invalid("there are no faces to export"));
+    }
+
+    let atlas = pack.atlas();
+    let rows = (atlas.height() / pack.tile_size) as f32;
+    let mut png = io::Cursor::new(Vec::new());
+    atlas
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(io::Error::other)?;
+    let png = png.into_inner();
+    // texture coordinates into the whole atlas, with `v` pointing down
+    let uvs = mesh
+        .vertices
+        .iter()
+        .map(|v| {
+            let layer = v.texture_layer as u32;
+            let tile = [layer % ATLAS_COLUMNS, layer / ATLAS_COLUMNS];
+            [
+                (tile[0] as f32 + v.uv[0]) / ATLAS_COLUMNS as f32,
+                (tile[1] as f32 + v.uv[1]) / rows,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("obj") => write_obj(&mesh, &uvs, path, &png),
+        Some("glb") => write_glb(&mesh, &uvs, path, png),
+        _ => Err(invalid("expected a .obj or .glb file")),
+    }
+}
+
+/// Generates the chunks within `radius` of `center` and writes them with [`export`].
+///
+/// Chunks come from the Minecraft save at `anvil` if given, shifted by `y_offset`, otherwise
+/// from the default noise layers. Textures come from the resource pack directory `pack`.
+/// Returns the number of exported chunks.
+pub fn export_generated(
+    path: &Path,
+    pack: &Path,
+    center: (i64, i64),
+    radius: usize,
+    anvil: Option<(&Path, i64)>,
+) -> io::Result<usize> {
+    let mut chunks = Chunks::from_noise(chunk::DEFAULT_NOISE_LAYERS.to_vec());
+    if let Some((save, y_offset)) = anvil {
+        if !save.is_dir() {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                "Minecraft save does not exist",
+            ));
+        }
+        chunks.set_anvil(Some(AnvilWorld::open(
+            save,
+            BlockNames::default(),
+            y_offset,
+        )));
+    }
+    let pack = ResourcePack::load(pack)?;
+    // every chunk at full detail
+    chunks.lod.enabled = false;
+    chunks.load_around(center, radius);
+    export(&chunks, path, &pack)?;
+    Ok(chunks.loaded().count())
+}
+
+pub fn ui(ui: &mut egui::Ui, export_path: &mut String, chunks: &Chunks, pack: &ResourcePack) {
+    ui.horizontal(|ui| {
+        ui.label("Mesh");
+        ui.text_edit_singleline(export_path)
+            .on_hover_text("Wavefront .obj or binary glTF .glb");
+    });
+    if ui.button("Export Loaded Chunks").clicked()
+        && let Err(err) = export(chunks, Path::new(export_path), pack)
+    {
+        glazer::log!("[ERROR] failed to export {export_path}: {err}");
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Writes `path` with a material library and atlas of the same name, vertex colours hold the
+/// tint.
+fn write_obj(mesh: &Mesh, uvs: &[[f32; 2]], path: &Path, png: &[u8]) -> io::Result<()> {
+    let png_path = path.with_extension("png");
+    std::fs::write(&png_path, png)?;
+    let texture = file_name(&png_path);
+    let mtl = path.with_extension("mtl");
+    std::fs::write(
+        &mtl,
+        format!("newmtl terrain\nKa 1 1 1\nKd 1 1 1\nKs 0 0 0\nd 1\nillum 1\nmap_Kd {texture}\n"),
+    )?;
+    std::fs::write(path, obj(mesh, uvs, &file_name(&mtl)))
+}
+
+/// OBJ text of `mesh` using the `terrain` material from the library `mtllib`.
+fn obj(mesh: &Mesh, uvs: &[[f32; 2]], mtllib: &str) -> String {
+    let mut obj = String::new();
+    let _ = writeln!(obj, "mtllib {mtllib}");
+    let _ = writeln!(obj, "o voxl");
+    for vertex in &mesh.vertices {
+        let [x, y, z] = vertex.position;
+        let [r, g, b] = vertex.tint;
+        let _ = writeln!(obj, "v {x} {y} {z} {r} {g} {b}");
+    }
+    // OBJ texture coordinates point up
+    for [u, v] in uvs {
+        let _ = writeln!(obj, "vt {u} {}", 1.0 - v);
+    }
+    for vertex in &mesh.vertices {
+        let [x, y, z] = vertex.normal;
+        let _ = writeln!(obj, "vn {x} {y} {z}");
+    }
+    let _ = writeln!(obj, "usemtl terrain");
+    for triangle in mesh.all_indices().chunks_exact(3) {
+        let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+        let _ = writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}");
+    }
+    obj
+}
+
+/// Writes a single `.glb` file with the atlas embedded as a PNG.
+fn write_glb(mesh: &Mesh, uvs: &[[f32; 2]], path: &Path, png: Vec<u8>) -> io::Result<()> {
+    std::fs::write(path, glb(mesh, uvs, png))
+}
+
+/// Binary glTF of `mesh`, with the `png` atlas in the same buffer.
+fn glb(mesh: &Mesh, uvs: &[[f32; 2]], png: Vec<u8>) -> Vec<u8> {
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+    const NEAREST: u32 = 9728;
+
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for vertex in &mesh.vertices {
+        for i in 0..3 {
+            min[i] = min[i].min(vertex.position[i]);
+            max[i] = max[i].max(vertex.position[i]);
+        }
+    }
+
+    let indices = mesh.all_indices();
+    let floats = |values: &mut dyn Iterator<Item = f32>| {
+        values.flat_map(f32::to_le_bytes).collect::<Vec<_>>()
+    };
+    let views = [
+        (
+            floats(&mut mesh.vertices.iter().flat_map(|v| v.position)),
+            Some(ARRAY_BUFFER),
+        ),
+        (
+            floats(&mut mesh.vertices.iter().flat_map(|v| v.normal)),
+            Some(ARRAY_BUFFER),
+        ),
+        (
+            floats(&mut uvs.iter().flatten().copied()),
+            Some(ARRAY_BUFFER),
+        ),
+        (
+            floats(&mut mesh.vertices.iter().flat_map(|v| v.tint)),
+            Some(ARRAY_BUFFER),
+        ),
+        (
+            indices.iter().flat_map(|i| i.to_le_bytes()).collect(),
+            Some(ELEMENT_ARRAY_BUFFER),
+        ),
+        (png, None),
+    ];
+
+    let mut bin = Vec::new();
+    let mut buffer_views = Vec::new();
+    for (data, target) in &views {
+        let target = target.map_or(String::new(), |target| format!(r#","target":{target}"#));
+        buffer_views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{}{target}}}"#,
+            bin.len(),
+            data.len()
+        ));
+        bin.extend_from_slice(data);
+        bin.resize(bin.len().next_multiple_of(4), 0);
+    }
+
+    let vertices = mesh.vertices.len();
+    let accessors = [
+        format!(
+            r#"{{"bufferView":0,"componentType":{FLOAT},"count":{vertices},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+            min[0], min[1], min[2], max[0], max[1], max[2]
+        ),
+        format!(r#"{{"bufferView":1,"componentType":{FLOAT},"count":{vertices},"type":"VEC3"}}"#),
+        format!(r#"{{"bufferView":2,"componentType":{FLOAT},"count":{vertices},"type":"VEC2"}}"#),
+        format!(r#"{{"bufferView":3,"componentType":{FLOAT},"count":{vertices},"type":"VEC3"}}"#),
+        format!(
+            r#"{{"bufferView":4,"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#,
+            indices.len()
+        ),
+    ];
+
+    let mut json = format!(
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"voxl"}},"scene":0,"scenes":[{{"nodes":[0]}}],"#,
+            r#""nodes":[{{"mesh":0,"name":"voxl"}}],"#,
+            r#""meshes":[{{"primitives":[{{"attributes":{{"POSITION":0,"NORMAL":1,"TEXCOORD_0":2,"COLOR_0":3}},"indices":4,"material":0}}]}}],"#,
+            r#""materials":[{{"name":"terrain","pbrMetallicRoughness":{{"baseColorTexture":{{"index":0}},"metallicFactor":0,"roughnessFactor":1}},"alphaMode":"MASK"}}],"#,
+            r#""textures":[{{"sampler":0,"source":0}}],"#,
+            r#""samplers":[{{"magFilter":{NEAREST},"minFilter":{NEAREST}}}],"#,
+            r#""images":[{{"bufferView":5,"mimeType":"image/png"}}],"#,
+            r#""buffers":[{{"byteLength":{len}}}],"bufferViews":[{views}],"accessors":[{accessors}]}}"#,
+        ),
+        NEAREST = NEAREST,
+        len = bin.len(),
+        views = buffer_views.join(","),
+        accessors = accessors.join(","),
+    )
+    .into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+
+    let mut glb = Vec::with_capacity(28 + json.len() + bin.len());
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&((28 + json.len() + bin.len()) as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&bin);
+    glb
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Face;
+    use glam::Vec3;
+
+    /// The six faces of a single block, 4 vertices and 2 triangles each.
+    fn block_mesh() -> (Mesh, Vec<[f32; 2]>) {
+        let mut mesh = Mesh::default();
+        for face in Face::ALL {
+            mesh.push_box_face(face, Vec3::ZERO, Vec3::ONE);
+        }
+        let uvs = vec![[0.5; 2]; mesh.vertices.len()];
+        (mesh, uvs)
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn obj_has_a_line_per_vertex_and_triangle() {
+        let (mesh, uvs) = block_mesh();
+        let obj = obj(&mesh, &uvs, "voxl.mtl");
+        let count = |prefix: &str| obj.lines().filter(|line| line.starts_with(prefix)).count();
+        assert_eq!(count("v "), 24);
+        assert_eq!(count("vt "), 24);
+        assert_eq!(count("vn "), 24);
+        assert_eq!(count("f "), 12);
+        // indices start at 1
+        assert!(obj.contains("f 1/1/1 "));
+        assert!(!obj.contains(" 25/"));
+    }
+
+    #[test]
+    fn glb_chunks_are_aligned_and_fill_the_file() {
+        let (mesh, uvs) = block_mesh();
+        // a length that needs padding
+        let glb = glb(&mesh, &uvs, vec![0x89; 5]);
+
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(u32_at(&glb, 4), 2);
+        assert_eq!(u32_at(&glb, 8), glb.len());
+
+        let json_len = u32_at(&glb, 12);
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(json_len % 4, 0);
+        let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+
+        let bin = 20 + json_len;
+        let bin_len = u32_at(&glb, bin);
+        assert_eq!(&glb[bin + 4..bin + 8], b"BIN\0");
+        assert_eq!(bin_len % 4, 0);
+        assert_eq!(bin + 8 + bin_len, glb.len());
+        assert!(json.contains(&format!(r#""buffers":[{{"byteLength":{bin_len}}}]"#)));
+
+        // positions, normals, uvs and tints of 24 vertices, 36 indices and the padded png
+        let views = [24 * 12, 24 * 12, 24 * 8, 24 * 12, 36 * 4, 8];
+        assert_eq!(views.iter().sum::<usize>(), bin_len);
+        let mut offset = 0;
+        for (length, unpadded) in views.into_iter().zip([288, 288, 192, 288, 144, 5]) {
+            assert!(json.contains(&format!(
+                r#"{{"buffer":0,"byteOffset":{offset},"byteLength":{unpadded}"#
+            )));
+            offset += length;
+        }
+    }
+}
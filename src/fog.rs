@@ -0,0 +1,106 @@
+//! Fog that blends distant terrain into the horizon colour of the sky, applied in `voxel.frag`.
+//!
+//! Distance fog thickens with the distance to the camera, height fog with the amount of low
+//! lying air the view passes through, which fills valleys.
+//!
+//! https://iquilezles.org/articles/fog/
+
+/// How distance fog grows, in the order of the `FOG_*` constants in `voxel.frag`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FogMode {
+    Off,
+    /// Fades in between [`FogSettings::start`] and [`FogSettings::end`].
+    Linear,
+    Exponential,
+    /// Stays clear for longer than exponential fog, then closes in quickly.
+    ExponentialSquared,
+}
+
+impl FogMode {
+    pub const ALL: [FogMode; 4] = [
+        FogMode::Off,
+        FogMode::Linear,
+        FogMode::Exponential,
+        FogMode::ExponentialSquared,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FogMode::Off => "Off",
+            FogMode::Linear => "Linear",
+            FogMode::Exponential => "Exponential",
+            FogMode::ExponentialSquared => "Exponential Squared",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FogSettings {
+    pub mode: FogMode,
+    /// Where linear fog starts, as a fraction of the view distance.
+    pub start: f32,
+    /// Where linear fog hides everything, as a fraction of the view distance.
+    pub end: f32,
+    /// Fog per block for the exponential modes.
+    pub density: f32,
+    pub height_fog: bool,
+    /// Fog per block at [`FogSettings::height`].
+    pub height_density: f32,
+    /// Height fog thins out above this height and thickens below it.
+    pub height: f32,
+    /// How quickly height fog thins out per block of height.
+    pub height_falloff: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            mode: FogMode::Linear,
+            start: 0.75,
+            end: 1.0,
+            density: 0.01,
+            height_fog: false,
+            height_density: 0.02,
+            height: 0.0,
+            height_falloff: 0.1,
+        }
+    }
+}
+
+pub fn ui(ui: &mut egui::Ui, settings: &mut FogSettings) {
+    ui.label("Fog");
+    egui::ComboBox::from_label("Fog Mode")
+        .selected_text(settings.mode.name())
+        .show_ui(ui, |ui| {
+            for mode in FogMode::ALL {
+                ui.selectable_value(&mut settings.mode, mode, mode.name());
+            }
+        });
+    ui.add_enabled_ui(settings.mode == FogMode::Linear, |ui| {
+        ui.add(egui::Slider::new(&mut settings.start, 0.0..=1.0).text("Start"));
+        ui.add(egui::Slider::new(&mut settings.end, settings.start..=1.0).text("End"));
+    });
+    ui.add_enabled(
+        matches!(
+            settings.mode,
+            FogMode::Exponential | FogMode::ExponentialSquared
+        ),
+        egui::Slider::new(&mut settings.density, 0.0..=0.1)
+            .logarithmic(true)
+            .text("Density"),
+    );
+    ui.checkbox(&mut settings.height_fog, "Height Fog");
+    ui.add_enabled_ui(settings.height_fog, |ui| {
+        ui.add(
+            egui::Slider::new(&mut settings.height_density, 0.0..=0.5)
+                .logarithmic(true)
+                .text("Height Density"),
+        );
+        ui.add(egui::Slider::new(&mut settings.height, -128.0..=128.0).text("Height"));
+        ui.add(
+            egui::Slider::new(&mut settings.height_falloff, 0.01..=1.0)
+                .logarithmic(true)
+                .text("Falloff"),
+        );
+    });
+}
@@ -0,0 +1,80 @@
+//! Culling of chunk meshes outside the camera and light frustums.
+
+use glam::{Mat4, Vec3, Vec4};
+
+/// Axis aligned bounding box in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Smallest box around `points`, `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        points.into_iter().fold(None, |aabb, point| {
+            Some(match aabb {
+                Some(Aabb { min, max }) => Aabb {
+                    min: min.min(point),
+                    max: max.max(point),
+                },
+                None => Aabb {
+                    min: point,
+                    max: point,
+                },
+            })
+        })
+    }
+}
+
+/// Planes of a view frustum with normals pointing inwards.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a projection * view matrix.
+    ///
+    /// https://www.gamedevs.org/uploads/fast-extraction-viewing-frustum-planes-from-world-view-projection-matrix.pdf
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (x, y, z, w) = (matrix.row(0), matrix.row(1), matrix.row(2), matrix.row(3));
+        Self {
+            planes: [w + x, w - x, w + y, w - y, w + z, w - z],
+        }
+    }
+
+    /// False only if `aabb` is certainly outside, boxes near a corner may be kept.
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // corner furthest along the plane normal
+            let normal = plane.truncate();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+/// Chunk meshes drawn and skipped in the last frame.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+    /// Inside the view but hidden behind terrain, see [`occlusion`](crate::occlusion).
+    pub occluded: usize,
+    /// Summed over every shadow cascade.
+    pub shadow_drawn: usize,
+    pub shadow_culled: usize,
+}
+
+pub fn ui(ui: &mut egui::Ui, enabled: &mut bool, stats: &CullStats) {
+    ui.checkbox(enabled, "Frustum Culling");
+    ui.label(format!(
+        "Chunks drawn {}, culled {}, occluded {}",
+        stats.drawn, stats.culled, stats.occluded
+    ));
+    ui.label(format!(
+        "Shadow chunks drawn {}, culled {}",
+        stats.shadow_drawn, stats.shadow_culled
+    ));
+}
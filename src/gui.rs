@@ -30,6 +30,11 @@ impl Egui {
         self.egui_glow.run(&self.window, ui);
     }
 
+    /// Shows `texture` in egui images, egui deletes it when it is destroyed.
+    pub fn register_texture(&mut self, texture: glazer::glow::Texture) -> egui::TextureId {
+        self.egui_glow.painter.register_native_texture(texture)
+    }
+
     pub fn paint(&mut self) {
         self.egui_glow.paint(&self.window);
     }
@@ -0,0 +1,309 @@
+use crate::{block::Block, chunk::Chunks};
+use glam::IVec3;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Clone, Copy)]
+pub struct BlockChange {
+    pub position: IVec3,
+    pub before: Block,
+    pub after: Block,
+}
+
+/// A group of block changes that are undone and redone together.
+pub struct Transaction {
+    pub label: String,
+    changes: Vec<BlockChange>,
+}
+
+impl Transaction {
+    fn bytes(&self) -> usize {
+        core::mem::size_of::<Self>()
+            + self.label.len()
+            + self.changes.len() * core::mem::size_of::<BlockChange>()
+    }
+}
+
+/// Reversible record of world edits.
+///
+/// Every edit goes through [`History::set_block`]. Edits made between [`History::begin`] and
+/// [`History::commit`] form a single transaction, otherwise each edit is its own transaction.
+/// Only one transaction can be open, and it has to be committed by whoever began it before
+/// another begins or the history is undone, redone or cleared.
+pub struct History {
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    pending: Option<(Transaction, HashMap<IVec3, usize>)>,
+    undo_bytes: usize,
+    redo_bytes: usize,
+    /// Redo transactions and then the oldest undo transactions are dropped once the history
+    /// grows past this many bytes.
+    pub memory_budget: usize,
+}
+
+impl History {
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            pending: None,
+            undo_bytes: 0,
+            redo_bytes: 0,
+            memory_budget,
+        }
+    }
+
+    pub fn begin(&mut self, label: impl Into<String>) {
+        self.assert_closed("begin a transaction");
+        self.pending = Some((
+            Transaction {
+                label: label.into(),
+                changes: Vec::new(),
+            },
+            HashMap::new(),
+        ));
+    }
+
+    pub fn commit(&mut self) {
+        let (transaction, _) = self
+            .pending
+            .take()
+            .expect("committed a transaction that was not begun");
+        if transaction.changes.is_empty() {
+            return;
+        }
+
+        self.redo.clear();
+        self.redo_bytes = 0;
+        self.undo_bytes += transaction.bytes();
+        self.undo.push_back(transaction);
+        self.enforce_budget();
+    }
+
+    pub fn set_block(&mut self, chunks: &mut Chunks, position: IVec3, block: Block) {
+        let before = chunks.set_block(position, block);
+        if before == block {
+            return;
+        }
+
+        let single = self.pending.is_none();
+        if single {
+            self.begin(format!("Set {} at {position}", block.name()));
+        }
+        let (transaction, index) = self.pending.as_mut().unwrap();
+        match index.get(&position) {
+            // keep the original `before` so undo restores the state prior to the transaction
+            Some(&i) => transaction.changes[i].after = block,
+            None => {
+                index.insert(position, transaction.changes.len());
+                transaction.changes.push(BlockChange {
+                    position,
+                    before,
+                    after: block,
+                });
+            }
+        }
+        if single {
+            self.commit();
+        }
+    }
+
+    /// Sets every block in the inclusive box between `a` and `b` as one transaction.
+    pub fn fill(&mut self, chunks: &mut Chunks, a: IVec3, b: IVec3, block: Block) {
+        let min = a.min(b);
+        let max = a.max(b);
+        self.begin(format!("Fill {min}..{max} with {}", block.name()));
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    self.set_block(chunks, IVec3::new(x, y, z), block);
+                }
+            }
+        }
+        self.commit();
+    }
+
+    pub fn undo(&mut self, chunks: &mut Chunks) {
+        self.assert_closed("undo");
+        let Some(transaction) = self.undo.pop_back() else {
+            return;
+        };
+        for change in transaction.changes.iter().rev() {
+            chunks.set_block(change.position, change.before);
+        }
+        self.undo_bytes -= transaction.bytes();
+        self.redo_bytes += transaction.bytes();
+        self.redo.push(transaction);
+    }
+
+    pub fn redo(&mut self, chunks: &mut Chunks) {
+        self.assert_closed("redo");
+        let Some(transaction) = self.redo.pop() else {
+            return;
+        };
+        for change in transaction.changes.iter() {
+            chunks.set_block(change.position, change.after);
+        }
+        self.redo_bytes -= transaction.bytes();
+        self.undo_bytes += transaction.bytes();
+        self.undo.push_back(transaction);
+    }
+
+    /// Drops every transaction, for when the blocks they changed are gone.
+    pub fn clear(&mut self) {
+        self.assert_closed("clear the history");
+        self.undo.clear();
+        self.redo.clear();
+        self.undo_bytes = 0;
+        self.redo_bytes = 0;
+    }
+
+    /// Undoes or redoes until `applied` transactions are applied.
+    pub fn jump_to(&mut self, chunks: &mut Chunks, applied: usize) {
+        while self.undo.len() > applied {
+            self.undo(chunks);
+        }
+        while self.undo.len() < applied && !self.redo.is_empty() {
+            self.redo(chunks);
+        }
+    }
+
+    /// Panics if a transaction is open, its owner would otherwise keep editing into a
+    /// transaction that is already closed.
+    fn assert_closed(&self, action: &str) {
+        if let Some((transaction, _)) = &self.pending {
+            panic!("cannot {action} while {} is open", transaction.label);
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.undo_bytes + self.redo_bytes
+    }
+
+    /// Drops redo transactions, furthest from the present first, then the oldest undo
+    /// transactions. The newest undo transaction is always kept so the last edit can be undone,
+    /// however large it is.
+    fn enforce_budget(&mut self) {
+        while self.bytes() > self.memory_budget && !self.redo.is_empty() {
+            // the next transaction to redo is at the end
+            let transaction = self.redo.remove(0);
+            self.redo_bytes -= transaction.bytes();
+        }
+        while self.bytes() > self.memory_budget && self.undo.len() > 1 {
+            let transaction = self.undo.pop_front().unwrap();
+            self.undo_bytes -= transaction.bytes();
+        }
+    }
+}
+
+pub fn ui(ui: &mut egui::Ui, history: &mut History, chunks: &mut Chunks) {
+    ui.label("History");
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(!history.undo.is_empty(), egui::Button::new("Undo"))
+            .clicked()
+        {
+            history.undo(chunks);
+        }
+        if ui
+            .add_enabled(!history.redo.is_empty(), egui::Button::new("Redo"))
+            .clicked()
+        {
+            history.redo(chunks);
+        }
+    });
+
+    let mut budget = history.memory_budget / (1024 * 1024);
+    if ui
+        .add(egui::Slider::new(&mut budget, 1..=512).text("Memory Budget (MiB)"))
+        .changed()
+    {
+        history.memory_budget = budget * 1024 * 1024;
+        history.enforce_budget();
+    }
+    ui.label(format!(
+        "{:.2} MiB used",
+        history.bytes() as f32 / (1024.0 * 1024.0)
+    ));
+
+    let mut jump = None;
+    egui::ScrollArea::vertical()
+        .id_salt("history")
+        .max_height(200.0)
+        .show(ui, |ui| {
+            if ui
+                .selectable_label(history.undo.is_empty(), "Start")
+                .clicked()
+            {
+                jump = Some(0);
+            }
+            let applied = history.undo.len();
+            let redo = history.redo.iter().rev();
+            for (i, transaction) in history.undo.iter().chain(redo).enumerate() {
+                let text = format!(
+                    "{} ({} blocks)",
+                    transaction.label,
+                    transaction.changes.len()
+                );
+                let text = if i < applied {
+                    egui::RichText::new(text)
+                } else {
+                    egui::RichText::new(text).weak()
+                };
+                if ui.selectable_label(i + 1 == applied, text).clicked() {
+                    jump = Some(i + 1);
+                }
+            }
+        });
+    if let Some(applied) = jump {
+        history.jump_to(chunks, applied);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::DEFAULT_NOISE_LAYERS;
+
+    fn chunks() -> Chunks {
+        let mut chunks = Chunks::from_noise(DEFAULT_NOISE_LAYERS.to_vec());
+        chunks.load_around((0, 0), 0);
+        chunks
+    }
+
+    #[test]
+    fn begin_and_commit_group_edits() {
+        let mut chunks = chunks();
+        let mut history = History::new(usize::MAX);
+        let a = IVec3::new(1, chunks.surface_height(1, 1).unwrap() + 1, 1);
+        let b = a + IVec3::Y;
+        history.begin("pair");
+        history.set_block(&mut chunks, a, Block::Stone);
+        history.set_block(&mut chunks, b, Block::Stone);
+        history.commit();
+        assert_eq!(history.undo.len(), 1);
+
+        history.undo(&mut chunks);
+        assert_eq!((chunks.block(a), chunks.block(b)), (Block::Air, Block::Air));
+        history.redo(&mut chunks);
+        assert_eq!(
+            (chunks.block(a), chunks.block(b)),
+            (Block::Stone, Block::Stone)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot begin a transaction while first is open")]
+    fn begin_rejects_a_second_transaction() {
+        let mut history = History::new(usize::MAX);
+        history.begin("first");
+        history.begin("second");
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot undo while stroke is open")]
+    fn undo_rejects_an_open_transaction() {
+        let mut history = History::new(usize::MAX);
+        history.begin("stroke");
+        history.undo(&mut chunks());
+    }
+}
@@ -2,20 +2,48 @@
 
 use crate::camera::Camera;
 use crate::chunk::Chunks;
+use crate::history::History;
 use crate::sprite::SpriteRenderer;
 use crate::voxel::VoxelRenderer;
 use glam::Vec3;
 use glazer::glow::{self, HasContext};
-use glazer::winit::event::{KeyEvent, WindowEvent};
-use glazer::winit::keyboard::{KeyCode, PhysicalKey};
+use glazer::winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
+use glazer::winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
 
+mod antialias;
+mod anvil;
+mod block;
+mod brush;
 mod camera;
 mod chunk;
+mod cloud;
+mod deferred;
+mod edit;
+mod export;
+mod fog;
+mod frustum;
 mod gui;
+mod history;
+mod light;
+mod lod;
+mod mesh;
+mod nbt;
+mod occlusion;
+mod point_light;
+mod post;
+mod render_graph;
+mod resource_pack;
 mod shader;
+mod shadow;
+mod sky;
 mod sprite;
+mod ssao;
+mod vox;
 mod voxel;
 
+pub use export::export_generated;
+pub use resource_pack::{DEFAULT_PACK, RESOURCE_PACKS};
+
 #[derive(Default)]
 pub struct Memory {
     world: Option<World>,
@@ -25,11 +53,27 @@ struct World {
     gui: gui::Egui,
     voxel_renderer: VoxelRenderer,
     sprite_renderer: SpriteRenderer,
+    sky_renderer: sky::SkyRenderer,
+    cloud_renderer: cloud::CloudRenderer,
+    post: post::PostProcess,
+    render_graph: render_graph::RenderGraph,
+    resource_pack: resource_pack::ResourcePack,
+    clock: sky::Clock,
+    atmosphere: sky::Atmosphere,
+    /// Seconds since the world was created, for animated textures.
+    time: f32,
     wireframes: bool,
-    fog: bool,
+    fog: fog::FogSettings,
+    ambient_occlusion: bool,
+    ambient_occlusion_strength: f32,
     view_distance: usize,
     camera: Camera,
     chunks: Chunks,
+    history: History,
+    point_lights: point_light::PointLights,
+    editor: edit::Editor,
+    export_path: String,
+    modifiers: ModifiersState,
 }
 
 #[unsafe(no_mangle)]
@@ -48,6 +92,15 @@ pub fn handle_input(
 
     if let glazer::Input::Window(event) = &input {
         if world.gui.handle_input(window, event) {
+            // a stroke started over the world still ends when released over the ui
+            if let WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } = event
+            {
+                edit::end_stroke(&mut world.editor, &mut world.history);
+            }
             return;
         }
         match event {
@@ -56,6 +109,20 @@ pub fn handle_input(
                 let h = size.height as usize;
                 world.voxel_renderer.resize(gl, w, h);
                 world.sprite_renderer.resize(gl, w, h);
+                world.render_graph.resize(gl, w, h);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                world.modifiers = modifiers.state();
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                edit::handle_mouse(
+                    &mut world.editor,
+                    *button,
+                    *state,
+                    &world.camera,
+                    &mut world.chunks,
+                    &mut world.history,
+                );
             }
             WindowEvent::KeyboardInput {
                 event:
@@ -70,12 +137,21 @@ pub fn handle_input(
                 KeyCode::Escape => {
                     std::process::exit(0);
                 }
-                KeyCode::KeyF if state.is_pressed() => {
-                    world.fog = !world.fog;
-                }
                 KeyCode::KeyV if state.is_pressed() => {
                     world.wireframes = !world.wireframes;
                 }
+                KeyCode::KeyZ if state.is_pressed() && world.modifiers.control_key() => {
+                    edit::end_stroke(&mut world.editor, &mut world.history);
+                    if world.modifiers.shift_key() {
+                        world.history.redo(&mut world.chunks);
+                    } else {
+                        world.history.undo(&mut world.chunks);
+                    }
+                }
+                KeyCode::KeyY if state.is_pressed() && world.modifiers.control_key() => {
+                    edit::end_stroke(&mut world.editor, &mut world.history);
+                    world.history.redo(&mut world.chunks);
+                }
                 _ => {}
             },
             _ => {}
@@ -103,18 +179,48 @@ pub fn update_and_render(
     window.set_title(&format!("Voxl - {:.2}", 1.0 / delta));
 
     let view_distance = 12;
-    let world = memory.world.get_or_insert_with(|| World {
-        gui: gui::Egui::new(event_loop, window, gl),
-        voxel_renderer: VoxelRenderer::new(gl, width, height, "assets/terrain.png"),
-        sprite_renderer: SpriteRenderer::new(gl, width, height),
-        wireframes: false,
-        fog: false,
-        view_distance,
-        camera: Camera::new(100.0, Vec3::ZERO, 0.0, 0.0),
-        chunks: Chunks::from_noise(vec![(1.5, 80.0), (3.0, 40.0), (8.0, 30.0)]),
+    let world = memory.world.get_or_insert_with(|| {
+        let resource_pack = resource_pack::ResourcePack::load(
+            std::path::Path::new(RESOURCE_PACKS).join(DEFAULT_PACK),
+        )
+        .expect("the default resource pack is missing");
+        World {
+            gui: gui::Egui::new(event_loop, window, gl),
+            voxel_renderer: VoxelRenderer::new(gl, width, height, &resource_pack),
+            sprite_renderer: SpriteRenderer::new(gl, width, height),
+            sky_renderer: sky::SkyRenderer::new(gl),
+            cloud_renderer: cloud::CloudRenderer::new(gl),
+            post: post::PostProcess::new(gl, width, height),
+            render_graph: render_graph::RenderGraph::new(width, height),
+            resource_pack,
+            clock: sky::Clock::default(),
+            atmosphere: sky::Atmosphere::default(),
+            time: 0.0,
+            wireframes: false,
+            fog: fog::FogSettings::default(),
+            ambient_occlusion: true,
+            ambient_occlusion_strength: 0.6,
+            view_distance,
+            camera: Camera::new(100.0, Vec3::ZERO, 0.0, 0.0),
+            chunks: Chunks::from_noise(chunk::DEFAULT_NOISE_LAYERS.to_vec()),
+            history: History::new(64 * 1024 * 1024),
+            point_lights: point_light::PointLights::default(),
+            editor: edit::Editor::default(),
+            export_path: "voxl.glb".to_string(),
+            modifiers: ModifiersState::empty(),
+        }
     });
 
     camera::update(&mut world.camera, delta);
+    world.clock.update(delta);
+    world.time += delta;
+    edit::update(
+        &mut world.editor,
+        &world.camera,
+        &mut world.chunks,
+        &mut world.history,
+        delta,
+    );
     chunk::update(
         gl,
         &world.voxel_renderer,
@@ -122,6 +228,9 @@ pub fn update_and_render(
         world.view_distance,
         &world.camera,
     );
+    world
+        .cloud_renderer
+        .update(gl, &world.voxel_renderer, world.camera.position(), delta);
 
     unsafe {
         if world.wireframes {
@@ -130,21 +239,86 @@ pub fn update_and_render(
             gl.polygon_mode(glow::FRONT_AND_BACK, glow::FILL);
         }
 
-        chunk::render(world, gl, width, height);
+        chunk::render(world, gl, width, height, delta);
         gl.polygon_mode(glow::FRONT_AND_BACK, glow::FILL);
     }
+    world.render_graph.register_preview(&mut world.gui);
 
     world.gui.show(|ui| {
         egui::Window::new("Voxl").show(ui, |ui| {
             egui::ScrollArea::both().show(ui, |ui| {
-                ui.add(egui::Slider::new(&mut world.view_distance, 1..=32).text("View Distance"));
-                chunk::ui(
+                // every chunk is stored in full without levels of detail
+                let max_view_distance = if world.chunks.lod.enabled {
+                    chunk::MAX_VIEW_DISTANCE
+                } else {
+                    chunk::MAX_FULL_VIEW_DISTANCE
+                };
+                world.view_distance = world.view_distance.min(max_view_distance);
+                ui.add(
+                    egui::Slider::new(&mut world.view_distance, 1..=max_view_distance)
+                        .text("View Distance"),
+                );
+                lod::ui(ui, &mut world.chunks.lod);
+                resource_pack::ui(ui, gl, &mut world.resource_pack, &mut world.voxel_renderer);
+                voxel::texture_ui(ui, &mut world.voxel_renderer);
+                frustum::ui(
                     ui,
-                    gl,
-                    &world.voxel_renderer,
-                    &mut world.chunks,
-                    world.view_distance,
+                    &mut world.voxel_renderer.frustum_culling,
+                    &world.voxel_renderer.cull_stats,
+                );
+                occlusion::ui(
+                    ui,
+                    &mut world.voxel_renderer.occlusion_culling,
+                    &mut world.voxel_renderer.show_occluded,
+                );
+                deferred::ui(ui, &mut world.voxel_renderer.render_path);
+                sky::ui(ui, &mut world.clock, &mut world.atmosphere);
+                fog::ui(ui, &mut world.fog);
+                cloud::ui(ui, &mut world.cloud_renderer.settings);
+                post::ui(ui, &mut world.post);
+                render_graph::ui(ui, &mut world.render_graph);
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut world.ambient_occlusion, "Ambient Occlusion");
+                    ui.add_enabled(
+                        world.ambient_occlusion,
+                        egui::Slider::new(&mut world.ambient_occlusion_strength, 0.0..=1.0)
+                            .text("Strength"),
+                    );
+                });
+                shadow::ui(ui, &mut world.voxel_renderer.shadow_settings);
+                // both can drop or undo transactions, so not while a brush stroke holds one open
+                let stroking = world.editor.brush.is_active();
+                ui.add_enabled_ui(!stroking, |ui| {
+                    chunk::ui(
+                        ui,
+                        gl,
+                        &world.voxel_renderer,
+                        &mut world.chunks,
+                        &mut world.history,
+                        world.view_distance,
+                        &world.camera,
+                    );
+                });
+                ui.separator();
+                point_light::ui(ui, &mut world.point_lights, &world.camera, &world.chunks);
+                ui.separator();
+                edit::ui(
+                    ui,
+                    &mut world.editor,
                     &world.camera,
+                    &mut world.chunks,
+                    &mut world.history,
+                );
+                ui.separator();
+                ui.add_enabled_ui(!stroking, |ui| {
+                    history::ui(ui, &mut world.history, &mut world.chunks);
+                });
+                ui.separator();
+                export::ui(
+                    ui,
+                    &mut world.export_path,
+                    &world.chunks,
+                    &world.resource_pack,
                 );
             })
         });
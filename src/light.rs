@@ -0,0 +1,337 @@
+//! Sky and block light flood filled through the loaded chunks.
+//!
+//! Every block stores a packed byte with the sky light level in the high nibble and the block
+//! light level in the low nibble. Sky light enters from the top of the world and travels
+//! straight down without falling off, block light is given off by [`Block::light_emission`].
+//! Both lose one level per block otherwise.
+//!
+//! https://web.archive.org/web/2021/https://www.seedofandromeda.com/blogs/29-fast-flood-fill-lighting-in-a-blocky-voxel-game-pt-1
+
+use crate::{
+    block::{Block, Face},
+    chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunks, MIN_Y, cells_within, local_index},
+};
+use glam::IVec3;
+use std::collections::VecDeque;
+
+pub const MAX_LIGHT: u8 = 15;
+
+/// World height of the highest block in a chunk, sky light enters from above it.
+const TOP_Y: i32 = (MIN_Y + CHUNK_HEIGHT as i64 - 1) as i32;
+
+pub fn sky(light: u8) -> u8 {
+    light >> 4
+}
+
+pub fn block(light: u8) -> u8 {
+    light & 0xf
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    Sky,
+    Block,
+}
+
+impl Channel {
+    const ALL: [Channel; 2] = [Channel::Sky, Channel::Block];
+
+    fn get(self, light: u8) -> u8 {
+        match self {
+            Channel::Sky => sky(light),
+            Channel::Block => block(light),
+        }
+    }
+
+    fn with(self, light: u8, level: u8) -> u8 {
+        match self {
+            Channel::Sky => (light & 0xf) | (level << 4),
+            Channel::Block => (light & 0xf0) | level,
+        }
+    }
+
+    /// Level that `block` at `position` has regardless of its neighbours.
+    fn source(self, position: IVec3, block: Block) -> u8 {
+        match self {
+            Channel::Sky if position.y == TOP_Y && !block.is_opaque() => MAX_LIGHT,
+            Channel::Sky => 0,
+            Channel::Block => block.light_emission(),
+        }
+    }
+
+    /// Level that spreads from a block with `level` towards `face`.
+    fn spread(self, level: u8, face: Face) -> u8 {
+        if self == Channel::Sky && face == Face::Bottom && level == MAX_LIGHT {
+            MAX_LIGHT
+        } else {
+            level.saturating_sub(1)
+        }
+    }
+}
+
+/// Light of a block that lets light through, `None` for opaque blocks and unloaded chunks.
+///
+/// Everything above the world is in full sky light.
+pub fn sample(chunks: &Chunks, position: IVec3) -> Option<u8> {
+    if position.y > TOP_Y {
+        return Some(MAX_LIGHT << 4);
+    }
+    chunks
+        .sample_light(position)
+        .filter(|_| !chunks.block(position).is_opaque())
+}
+
+/// Light of cell `x`, `y`, `z` at level of detail `lod` of a chunk whose `light` is stored at
+/// level `from`, the brightest sky and block light of the cells it covers.
+pub fn downsample(light: &[u8], from: u32, lod: u32, x: usize, y: usize, z: usize) -> u8 {
+    cells_within(from, lod, x, y, z)
+        .map(|index| light[index])
+        .fold(0, |max, light| {
+            Channel::ALL.into_iter().fold(max, |max, channel| {
+                channel.with(max, channel.get(max).max(channel.get(light)))
+            })
+        })
+}
+
+/// Lights a chunk that was just loaded, pulling in light from loaded neighbours and spreading
+/// its own light into them.
+pub fn light_chunk(chunks: &mut Chunks, key: (i64, i64)) {
+    let Some((blocks, light)) = chunks.chunk_light_mut(key) else {
+        return;
+    };
+
+    // height of the lowest block of each column that sees the sky
+    let mut heights = [[0; CHUNK_SIZE]; CHUNK_SIZE];
+    let mut block_queue = VecDeque::new();
+    let origin = IVec3::new(
+        (key.0 * CHUNK_SIZE as i64) as i32,
+        MIN_Y as i32,
+        (key.1 * CHUNK_SIZE as i64) as i32,
+    );
+    for z in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            let mut height = CHUNK_HEIGHT;
+            while height > 0 && !blocks[local_index(x, height - 1, z)].is_opaque() {
+                height -= 1;
+                light[local_index(x, height, z)] = MAX_LIGHT << 4;
+            }
+            heights[z][x] = height;
+
+            for y in 0..CHUNK_HEIGHT {
+                let index = local_index(x, y, z);
+                let emission = blocks[index].light_emission();
+                if emission > 0 {
+                    light[index] = Channel::Block.with(light[index], emission);
+                    block_queue.push_back(origin + IVec3::new(x as i32, y as i32, z as i32));
+                }
+            }
+        }
+    }
+
+    // sky light only spreads sideways where a neighbouring column is lower, columns on the
+    // border can spread into the next chunk at any height
+    let mut sky_queue = VecDeque::new();
+    for z in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            let mut top = heights[z][x];
+            for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (nx, nz) = (x as i64 + dx, z as i64 + dz);
+                top = top.max(
+                    if (0..CHUNK_SIZE as i64).contains(&nx) && (0..CHUNK_SIZE as i64).contains(&nz)
+                    {
+                        heights[nz as usize][nx as usize]
+                    } else {
+                        CHUNK_HEIGHT
+                    },
+                );
+            }
+            for y in heights[z][x]..top.min(CHUNK_HEIGHT) {
+                sky_queue.push_back(origin + IVec3::new(x as i32, y as i32, z as i32));
+            }
+        }
+    }
+
+    // light of neighbours, which treated this chunk as a wall until now
+    let size = CHUNK_SIZE as i32;
+    for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        for i in 0..size {
+            for y in 0..CHUNK_HEIGHT as i32 {
+                let (x, z) = match (dx, dz) {
+                    (-1, _) => (-1, i),
+                    (1, _) => (size, i),
+                    (_, -1) => (i, -1),
+                    _ => (i, size),
+                };
+                let position = origin + IVec3::new(x, y, z);
+                let Some(light) = chunks.light(position) else {
+                    continue;
+                };
+                if sky(light) > 1 {
+                    sky_queue.push_back(position);
+                }
+                if block(light) > 1 {
+                    block_queue.push_back(position);
+                }
+            }
+        }
+    }
+
+    propagate(chunks, Channel::Sky, sky_queue);
+    propagate(chunks, Channel::Block, block_queue);
+}
+
+/// Updates the light around `position` after its block changed from `before` to `after`.
+pub fn block_changed(chunks: &mut Chunks, position: IVec3, before: Block, after: Block) {
+    if before.is_opaque() == after.is_opaque() && before.light_emission() == after.light_emission()
+    {
+        return;
+    }
+
+    for channel in Channel::ALL {
+        let Some(light) = chunks.light(position) else {
+            return;
+        };
+
+        // remove the light that may have passed through or come from the block, then fill the
+        // gap from the light that is left around it
+        let level = channel.get(light);
+        chunks.set_light(position, channel.with(light, 0));
+        let mut refill = remove(chunks, channel, VecDeque::from([(position, level)]));
+
+        let source = channel.source(position, after);
+        if source > 0 {
+            let light = chunks.light(position).unwrap();
+            chunks.set_light(position, channel.with(light, source));
+        }
+        refill.push_back(position);
+        propagate(chunks, channel, refill);
+    }
+}
+
+/// Spreads light from every queued position into the blocks around it.
+fn propagate(chunks: &mut Chunks, channel: Channel, mut queue: VecDeque<IVec3>) {
+    while let Some(position) = queue.pop_front() {
+        let Some(light) = chunks.light(position) else {
+            continue;
+        };
+        let level = channel.get(light);
+        for face in Face::ALL {
+            let spread = channel.spread(level, face);
+            if spread == 0 {
+                continue;
+            }
+            let neighbour = position + face.normal();
+            let Some(light) = chunks.light(neighbour) else {
+                continue;
+            };
+            if channel.get(light) < spread && !chunks.block(neighbour).is_opaque() {
+                chunks.set_light(neighbour, channel.with(light, spread));
+                queue.push_back(neighbour);
+            }
+        }
+    }
+}
+
+/// Darkens the blocks that were lit by the queued `(position, previous level)` pairs, returning
+/// the lit blocks around the darkened area that need to spread their light back into it.
+fn remove(
+    chunks: &mut Chunks,
+    channel: Channel,
+    mut queue: VecDeque<(IVec3, u8)>,
+) -> VecDeque<IVec3> {
+    let mut refill = VecDeque::new();
+    while let Some((position, level)) = queue.pop_front() {
+        for face in Face::ALL {
+            let neighbour = position + face.normal();
+            let Some(light) = chunks.light(neighbour) else {
+                continue;
+            };
+            let neighbour_level = channel.get(light);
+            if neighbour_level == 0 {
+                continue;
+            }
+            if neighbour_level <= channel.spread(level, face) {
+                chunks.set_light(neighbour, channel.with(light, 0));
+                queue.push_back((neighbour, neighbour_level));
+
+                let source = channel.source(neighbour, chunks.block(neighbour));
+                if source > 0 {
+                    chunks.set_light(neighbour, channel.with(light, source));
+                    refill.push_back(neighbour);
+                }
+            } else {
+                refill.push_back(neighbour);
+            }
+        }
+    }
+    refill
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::DEFAULT_NOISE_LAYERS;
+
+    fn chunks() -> Chunks {
+        let mut chunks = Chunks::from_noise(DEFAULT_NOISE_LAYERS.to_vec());
+        chunks.load_around((0, 0), 1);
+        chunks
+    }
+
+    /// Air block `height` above the terrain of the column at `x`, `z`.
+    fn above_surface(chunks: &Chunks, x: i32, z: i32, height: i32) -> IVec3 {
+        let position = IVec3::new(x, chunks.surface_height(x, z).unwrap() + height, z);
+        assert!(position.y < TOP_Y);
+        position
+    }
+
+    #[test]
+    fn sky_light_comes_back_when_a_block_is_removed() {
+        let mut chunks = chunks();
+        let position = above_surface(&chunks, 8, 8, 8);
+        let below = position - IVec3::Y * 3;
+        assert_eq!(chunks.light(below).map(sky), Some(MAX_LIGHT));
+
+        chunks.set_block(position, Block::Stone);
+        // only what spreads in from the columns around it
+        assert_eq!(chunks.light(below).map(sky), Some(MAX_LIGHT - 1));
+
+        chunks.set_block(position, Block::Air);
+        assert_eq!(chunks.light(below).map(sky), Some(MAX_LIGHT));
+        assert_eq!(chunks.light(position).map(sky), Some(MAX_LIGHT));
+    }
+
+    /// Block light of the blocks from `position` six blocks towards `direction`.
+    fn block_levels(chunks: &Chunks, position: IVec3, direction: IVec3) -> Vec<u8> {
+        (0..=6)
+            .map(|distance| block(chunks.light(position + direction * distance).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn block_light_falls_off_by_one_per_block() {
+        let mut chunks = chunks();
+        // next to the chunk border, so the light also spreads into the neighbour
+        let position = above_surface(&chunks, 14, 8, 20);
+        chunks.set_block(position, Block::Glowstone);
+        let falloff = [15, 14, 13, 12, 11, 10, 9];
+        assert_eq!(block_levels(&chunks, position, IVec3::X), falloff);
+        assert_eq!(block_levels(&chunks, position, IVec3::Y), falloff);
+
+        chunks.set_block(position, Block::Air);
+        assert_eq!(block_levels(&chunks, position, IVec3::X), [0; 7]);
+    }
+
+    #[test]
+    fn loading_a_chunk_pulls_in_neighbouring_light() {
+        let mut chunks = chunks();
+        let position = above_surface(&chunks, 30, 8, 20);
+        chunks.set_block(position, Block::Glowstone);
+
+        // chunk 2, 0 sees the light of chunk 1, 0 once it is loaded
+        chunks.load_around((1, 0), 1);
+        let neighbour = position + IVec3::X * 3;
+        assert_eq!(chunks.light(neighbour).map(block), Some(MAX_LIGHT - 3));
+        assert_eq!(chunks.light(neighbour).map(sky), Some(MAX_LIGHT));
+    }
+}
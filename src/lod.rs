@@ -0,0 +1,51 @@
+//! Level of detail for chunks far from the camera, see [`mesh::build_lod`](crate::mesh::build_lod).
+//!
+//! Chunks past the first level only store their downsampled cells and light, see
+//! [`Chunks::stream_around`](crate::chunk::Chunks::stream_around).
+
+/// Coarsest level, which meshes cells of 8³ blocks.
+pub const MAX_LEVEL: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodSettings {
+    pub enabled: bool,
+    /// Chunks from the camera at which the first level starts, each next level starts twice as
+    /// far away.
+    pub distance: usize,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            distance: 8,
+        }
+    }
+}
+
+impl LodSettings {
+    /// Level to mesh the chunk at `key` with while the camera is in the chunk at `center`, 0 is
+    /// full detail and each level halves the resolution.
+    pub fn level(&self, key: (i64, i64), center: (i64, i64)) -> u32 {
+        if !self.enabled {
+            return 0;
+        }
+        let (dx, dz) = ((key.0 - center.0) as f32, (key.1 - center.1) as f32);
+        let distance = (dx * dx + dz * dz).sqrt() / self.distance.max(1) as f32;
+        if distance < 1.0 {
+            0
+        } else {
+            (distance.log2().floor() as u32 + 1).min(MAX_LEVEL)
+        }
+    }
+}
+
+pub fn ui(ui: &mut egui::Ui, settings: &mut LodSettings) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.enabled, "Level of Detail");
+        ui.add_enabled(
+            settings.enabled,
+            egui::Slider::new(&mut settings.distance, 1..=32).text("LOD Distance"),
+        );
+    });
+}
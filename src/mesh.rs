@@ -0,0 +1,460 @@
+//! Chunk meshes built from block data, independent of any GL context.
+
+use crate::{
+    block::{Block, Face, RenderLayer},
+    chunk::{
+        CHUNK_HEIGHT, CHUNK_SIZE, Cells, Chunks, MIN_Y, cell_index, cells_within, local_index,
+    },
+    frustum::Aabb,
+    light::{self, MAX_LIGHT},
+};
+use glam::{IVec3, Vec3};
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// Position within the tile, with `v` pointing down the atlas image. Goes past 1 on faces
+    /// larger than a block, which repeat the texture.
+    pub uv: [f32; 2],
+    /// Layer of the texture array, see [`Block::texture_layers`].
+    pub texture_layer: f32,
+    pub tint: [f32; 3],
+    /// Sky and block light between 0 and 1.
+    pub light: [f32; 2],
+    /// Ambient occlusion, from 0 for a corner enclosed by three blocks to 1 for an open corner.
+    pub ao: f32,
+    /// How much the texture glows on its own, from [`Block::light_emission`].
+    pub emission: f32,
+}
+
+#[derive(Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    /// Triangles of each [`RenderLayer`], indexed by the layer.
+    pub indices: [Vec<u32>; 3],
+}
+
+impl Mesh {
+    pub fn is_empty(&self) -> bool {
+        self.indices.iter().all(Vec::is_empty)
+    }
+
+    /// Triangles of every layer, for formats that do not tell them apart.
+    pub fn all_indices(&self) -> Vec<u32> {
+        self.indices.concat()
+    }
+
+    /// Middle and indices of every translucent quad, to sort them with [`sort_back_to_front`].
+    pub fn translucent_faces(&self) -> Vec<SortedFace> {
+        self.indices[RenderLayer::Translucent as usize]
+            .chunks_exact(6)
+            .map(|quad| {
+                let center = quad
+                    .iter()
+                    .map(|index| Vec3::from_array(self.vertices[*index as usize].position))
+                    .sum::<Vec3>()
+                    / 6.0;
+                SortedFace {
+                    center,
+                    indices: quad.try_into().unwrap(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(
+            self.vertices
+                .iter()
+                .map(|vertex| Vec3::from_array(vertex.position)),
+        )
+    }
+
+    /// Appends the faces of `other`.
+    pub fn append(&mut self, other: &Mesh) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        for (indices, other) in self.indices.iter_mut().zip(&other.indices) {
+            indices.extend(other.iter().map(|index| base + index));
+        }
+    }
+
+    /// Pushes an untextured, fully lit face of a box of `size` centred on `center`, for meshes
+    /// that are not made of blocks.
+    pub fn push_box_face(&mut self, face: Face, center: Vec3, size: Vec3) {
+        let base = self.vertices.len() as u32;
+        let normal = face.normal().as_vec3().to_array();
+        for position in face_corners(face) {
+            self.vertices.push(Vertex {
+                position: (center + position * size).to_array(),
+                normal,
+                tint: [1.0; 3],
+                light: [1.0; 2],
+                ao: 1.0,
+                ..Default::default()
+            });
+        }
+        self.indices[RenderLayer::Opaque as usize].extend([
+            base,
+            base + 1,
+            base + 2,
+            base,
+            base + 2,
+            base + 3,
+        ]);
+    }
+
+    /// Pushes the face of a cube `scale` blocks wide centered on `translation`.
+    fn push_face(
+        &mut self,
+        block: Block,
+        face: Face,
+        translation: Vec3,
+        scale: f32,
+        corners: [Corner; 4],
+    ) {
+        let base = self.vertices.len() as u32;
+        let normal = face.normal().as_vec3().to_array();
+        let texture_layer = block.texture_layers()[face as usize] as f32;
+        let tint = block.tint(face).to_array();
+        let emission = block.light_emission() as f32 / MAX_LIGHT as f32;
+        for ((position, uv), corner) in face_corners(face).into_iter().zip(CORNER_UVS).zip(corners)
+        {
+            self.vertices.push(Vertex {
+                position: (translation + position * scale).to_array(),
+                normal,
+                uv: [uv[0] * scale, (1.0 - uv[1]) * scale],
+                texture_layer,
+                tint,
+                light: corner.light,
+                ao: corner.ao,
+                emission,
+            });
+        }
+
+        // split the quad along the diagonal that keeps occlusion from bleeding across the face
+        // https://0fps.net/2013/07/03/ambient-occlusion-for-minecraft-like-worlds/
+        let ao = corners.map(|corner| corner.ao);
+        let indices = &mut self.indices[block.layer() as usize];
+        if ao[0] + ao[2] >= ao[1] + ao[3] {
+            indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+        } else {
+            indices.extend([base + 1, base + 2, base + 3, base + 1, base + 3, base]);
+        }
+    }
+}
+
+/// A translucent quad of a [`Mesh`], kept after upload so it can be sorted again as the camera
+/// moves.
+#[derive(Debug, Clone, Copy)]
+pub struct SortedFace {
+    pub center: Vec3,
+    pub indices: [u32; 6],
+}
+
+/// Sorts `faces` from furthest to nearest to `eye` and returns their indices in that order.
+pub fn sort_back_to_front(faces: &mut [SortedFace], eye: Vec3) -> Vec<u32> {
+    faces.sort_by(|a, b| {
+        b.center
+            .distance_squared(eye)
+            .total_cmp(&a.center.distance_squared(eye))
+    });
+    faces.iter().flat_map(|face| face.indices).collect()
+}
+
+/// Builds the faces of a loaded chunk that are not hidden by their neighbour, see
+/// [`Block::face_hidden_by`].
+///
+/// Blocks in neighbouring chunks that are not loaded count as opaque.
+pub fn build(chunks: &Chunks, key: (i64, i64)) -> Mesh {
+    let mut mesh = Mesh::default();
+    let Some(blocks) = chunks.chunk_blocks(key) else {
+        return mesh;
+    };
+    let neighbours = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .map(|(dx, dz)| chunks.chunk_cells((key.0 + dx, key.1 + dz)));
+    let cells = Cells { lod: 0, blocks };
+    // stone stands in for anything opaque outside the loaded blocks
+    let block_at = |x: i64, y: i64, z: i64| -> Block {
+        if y < 0 {
+            return Block::Stone;
+        }
+        if y >= CHUNK_HEIGHT as i64 {
+            return Block::Air;
+        }
+        let size = CHUNK_SIZE as i64;
+        let neighbour = match (x, z) {
+            (-1, _) => neighbours[0],
+            (x, _) if x == size => neighbours[1],
+            (_, -1) => neighbours[2],
+            (_, z) if z == size => neighbours[3],
+            _ => Some(cells),
+        };
+        neighbour.map_or(Block::Stone, |neighbour| {
+            neighbour.get(
+                x.rem_euclid(size) as usize,
+                y as usize,
+                z.rem_euclid(size) as usize,
+            )
+        })
+    };
+
+    let origin = Vec3::new(
+        (key.0 * CHUNK_SIZE as i64) as f32,
+        MIN_Y as f32,
+        (key.1 * CHUNK_SIZE as i64) as f32,
+    );
+    for y in 0..CHUNK_HEIGHT {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let block = blocks[local_index(x, y, z)];
+                if block.is_air() {
+                    continue;
+                }
+
+                let translation = origin + Vec3::new(x as f32, y as f32, z as f32);
+                let position = translation.as_ivec3();
+                for face in Face::ALL {
+                    let n = face.normal();
+                    let neighbour = block_at(
+                        x as i64 + n.x as i64,
+                        y as i64 + n.y as i64,
+                        z as i64 + n.z as i64,
+                    );
+                    if !block.face_hidden_by(neighbour) {
+                        let corners = face_corner_shading(chunks, position, face);
+                        mesh.push_face(block, face, translation, 1.0, corners);
+                    }
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// Mesh of a loaded chunk at the level of detail it is stored at, in cells of `2^level` blocks
+/// for chunks far from the camera. Level 0 is the same as [`build`].
+///
+/// A cell is filled when at least half of its blocks are not air and looks like the most common of
+/// them, so water, glass and leaves keep their own [`RenderLayer`]. Neighbouring chunks can be
+/// meshed at other levels, so the top two filled cells of every column on the chunk border always
+/// get their outward faces as skirts that cover the gaps.
+pub fn build_lod(chunks: &Chunks, key: (i64, i64)) -> Mesh {
+    let mut mesh = Mesh::default();
+    let Some(cells) = chunks.chunk_cells(key) else {
+        return mesh;
+    };
+    let level = cells.lod;
+    if level == 0 {
+        return build(chunks, key);
+    }
+    let size = 1 << level;
+    let (width, height) = ((CHUNK_SIZE / size) as i64, (CHUNK_HEIGHT / size) as i64);
+    let cell = |x: i64, y: i64, z: i64| {
+        cells.blocks[cell_index(level, x as usize, y as usize, z as usize)]
+    };
+
+    let neighbours = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .map(|(dx, dz)| chunks.chunk_cells((key.0 + dx, key.1 + dz)));
+    // stone stands in for anything opaque outside the loaded blocks
+    let cell_at = |x: i64, y: i64, z: i64| -> Block {
+        if y < 0 {
+            return Block::Stone;
+        }
+        if y >= height {
+            return Block::Air;
+        }
+        let neighbour = match (x, z) {
+            (-1, _) => neighbours[0],
+            (x, _) if x == width => neighbours[1],
+            (_, -1) => neighbours[2],
+            (_, z) if z == width => neighbours[3],
+            _ => return cell(x, y, z),
+        };
+        neighbour.map_or(Block::Stone, |neighbour| {
+            downsample(
+                neighbour,
+                level,
+                x.rem_euclid(width) as usize,
+                y as usize,
+                z.rem_euclid(width) as usize,
+            )
+        })
+    };
+
+    let origin = Vec3::new(
+        (key.0 * CHUNK_SIZE as i64) as f32,
+        MIN_Y as f32,
+        (key.1 * CHUNK_SIZE as i64) as f32,
+    );
+    let scale = size as f32;
+    for y in 0..height {
+        for z in 0..width {
+            for x in 0..width {
+                let block = cell(x, y, z);
+                if block.is_air() {
+                    continue;
+                }
+
+                let first = Vec3::new(x as f32, y as f32, z as f32) * scale;
+                let translation = origin + first + (scale - 1.0) / 2.0;
+                let surface = (y + 1..=y + 2).any(|y| !cell_at(x, y, z).is_opaque());
+                for face in Face::ALL {
+                    let n = face.normal();
+                    let (nx, nz) = (x + n.x as i64, z + n.z as i64);
+                    let border = !(0..width).contains(&nx) || !(0..width).contains(&nz);
+                    let neighbour = cell_at(nx, y + n.y as i64, nz);
+                    if (border && surface) || !block.face_hidden_by(neighbour) {
+                        let light = cell_light(chunks, translation.as_ivec3(), face, size as i32);
+                        let corners = [Corner { light, ao: 1.0 }; 4];
+                        mesh.push_face(block, face, translation, scale, corners);
+                    }
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// Cell `x`, `y`, `z` of `2^level` blocks of a chunk, the most common block other than air of
+/// the cells it covers, or air if more than half of them are air. Chunks stored at a coarser
+/// level give the cell that holds it.
+pub fn downsample(cells: Cells, level: u32, x: usize, y: usize, z: usize) -> Block {
+    if cells.lod >= level {
+        let shift = cells.lod - level;
+        return cells.blocks[cell_index(cells.lod, x >> shift, y >> shift, z >> shift)];
+    }
+    let mut counts = [0; Block::ALL.len()];
+    let mut total = 0;
+    for index in cells_within(cells.lod, level, x, y, z) {
+        let block = cells.blocks[index];
+        if !block.is_air() {
+            counts[block as usize] += 1;
+        }
+        total += 1;
+    }
+    let (most_common, count) = counts
+        .into_iter()
+        .enumerate()
+        .max_by_key(|(_, count)| *count)
+        .unwrap();
+    if count > 0 && counts.iter().sum::<usize>() * 2 >= total {
+        Block::ALL[most_common]
+    } else {
+        Block::Air
+    }
+}
+
+/// Light in front of the middle of a cell face, or above the cell when that is opaque.
+fn cell_light(chunks: &Chunks, center: IVec3, face: Face, size: i32) -> [f32; 2] {
+    let light = light::sample(chunks, center + face.normal() * (size / 2 + 1))
+        .or_else(|| light::sample(chunks, center + IVec3::Y * (size / 2 + 1)))
+        .unwrap_or(MAX_LIGHT << 4);
+    [
+        light::sky(light) as f32 / MAX_LIGHT as f32,
+        light::block(light) as f32 / MAX_LIGHT as f32,
+    ]
+}
+
+#[derive(Clone, Copy)]
+struct Corner {
+    light: [f32; 2],
+    ao: f32,
+}
+
+/// Smooth light and ambient occlusion of each corner of a face, from the blocks in front of the
+/// face that touch the corner.
+fn face_corner_shading(chunks: &Chunks, position: IVec3, face: Face) -> [Corner; 4] {
+    let normal = face.normal();
+    let front = position + normal;
+    face_corners(face).map(|corner| {
+        // steps from the block in front of the face towards the corner
+        let offset = (corner * 2.0).as_ivec3() * (IVec3::ONE - normal.abs());
+        let mut tangents = [IVec3::X, IVec3::Y, IVec3::Z]
+            .map(|axis| axis * offset)
+            .into_iter()
+            .filter(|tangent| *tangent != IVec3::ZERO);
+        let (a, b) = (tangents.next().unwrap(), tangents.next().unwrap());
+
+        let opaque = |position: IVec3| chunks.block(position).is_opaque() as u8;
+        let (side_a, side_b) = (opaque(front + a), opaque(front + b));
+        let ao = if side_a + side_b == 2 {
+            0
+        } else {
+            3 - side_a - side_b - opaque(front + a + b)
+        };
+
+        let side_a = light::sample(chunks, front + a);
+        let side_b = light::sample(chunks, front + b);
+        // light cannot reach the diagonal through two opaque sides
+        let diagonal = (side_a.is_some() || side_b.is_some())
+            .then(|| light::sample(chunks, front + a + b))
+            .flatten();
+
+        let samples = [light::sample(chunks, front), side_a, side_b, diagonal];
+        let (mut sky, mut block, mut count) = (0.0, 0.0, 0.0);
+        for light in samples.into_iter().flatten() {
+            sky += light::sky(light) as f32;
+            block += light::block(light) as f32;
+            count += 1.0;
+        }
+        let max = MAX_LIGHT as f32 * count;
+        Corner {
+            light: if count == 0.0 {
+                [0.0; 2]
+            } else {
+                [sky / max, block / max]
+            },
+            ao: ao as f32 / 3.0,
+        }
+    })
+}
+
+/// Texture coordinates of the corners returned by [`face_corners`], with `v` pointing up.
+const CORNER_UVS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+/// Corners of a unit cube face centered on the origin, counter-clockwise when viewed from outside,
+/// starting at the bottom left of the texture.
+fn face_corners(face: Face) -> [Vec3; 4] {
+    let corners = match face {
+        Face::Back => [
+            [0.5, -0.5, -0.5],
+            [-0.5, -0.5, -0.5],
+            [-0.5, 0.5, -0.5],
+            [0.5, 0.5, -0.5],
+        ],
+        Face::Front => [
+            [-0.5, -0.5, 0.5],
+            [0.5, -0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [-0.5, 0.5, 0.5],
+        ],
+        Face::Left => [
+            [-0.5, -0.5, -0.5],
+            [-0.5, -0.5, 0.5],
+            [-0.5, 0.5, 0.5],
+            [-0.5, 0.5, -0.5],
+        ],
+        Face::Right => [
+            [0.5, -0.5, 0.5],
+            [0.5, -0.5, -0.5],
+            [0.5, 0.5, -0.5],
+            [0.5, 0.5, 0.5],
+        ],
+        Face::Bottom => [
+            [-0.5, -0.5, -0.5],
+            [0.5, -0.5, -0.5],
+            [0.5, -0.5, 0.5],
+            [-0.5, -0.5, 0.5],
+        ],
+        Face::Top => [
+            [-0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [0.5, 0.5, -0.5],
+            [-0.5, 0.5, -0.5],
+        ],
+    };
+    corners.map(Vec3::from_array)
+}
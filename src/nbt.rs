@@ -0,0 +1,223 @@
+//! Minecraft's Named Binary Tag format.
+//!
+//! https://minecraft.wiki/w/NBT_format
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+
+// every tag is parsed so files can be walked, even if voxl does not read all of them
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum Tag {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(compound) => compound.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(v) => Some(v as i64),
+            Tag::Short(v) => Some(v as i64),
+            Tag::Int(v) => Some(v as i64),
+            Tag::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Parses an uncompressed file, returning the root compound.
+pub fn parse(bytes: &[u8]) -> io::Result<Tag> {
+    let mut reader = Reader { bytes, offset: 0 };
+    let id = reader.u8()?;
+    if id != 10 {
+        return Err(invalid("root tag is not a compound"));
+    }
+    let _name = reader.string()?;
+    reader.payload(id, 0)
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> io::Result<i64> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        usize::try_from(self.i32()?).map_err(|_| invalid("negative length"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.i16()? as u16 as usize;
+        // modified UTF-8 only differs for null and supplementary characters
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> io::Result<Tag> {
+        if depth > 512 {
+            return Err(invalid("tags are nested too deeply"));
+        }
+        Ok(match id {
+            0 => Tag::End,
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => Tag::Float(f32::from_bits(self.i32()? as u32)),
+            6 => Tag::Double(f64::from_bits(self.i64()? as u64)),
+            7 => {
+                let len = self.len()?;
+                Tag::ByteArray(self.take(len)?.iter().map(|v| *v as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let id = self.u8()?;
+                let len = self.len()?;
+                // every other tag takes at least a byte, so longer lists cannot fit
+                if id == 0 && len > 0 {
+                    return Err(invalid("list of end tags"));
+                }
+                if len > self.remaining() {
+                    return Err(invalid("list is longer than the data"));
+                }
+                let mut list = Vec::with_capacity(len);
+                for _ in 0..len {
+                    list.push(self.payload(id, depth + 1)?);
+                }
+                Tag::List(list)
+            }
+            10 => {
+                let mut compound = HashMap::new();
+                loop {
+                    let id = self.u8()?;
+                    if id == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    compound.insert(name, self.payload(id, depth + 1)?);
+                }
+                Tag::Compound(compound)
+            }
+            11 => {
+                let len = self.len()?;
+                let mut array = Vec::with_capacity(len.min(self.bytes.len() / 4));
+                for _ in 0..len {
+                    array.push(self.i32()?);
+                }
+                Tag::IntArray(array)
+            }
+            12 => {
+                let len = self.len()?;
+                let mut array = Vec::with_capacity(len.min(self.bytes.len() / 8));
+                for _ in 0..len {
+                    array.push(self.i64()?);
+                }
+                Tag::LongArray(array)
+            }
+            _ => return Err(invalid("unknown tag")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Root compound holding a list named `l` of `len` tags with `id`, followed by `rest`.
+    fn list(id: u8, len: i32, rest: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![10, 0, 0, 9, 0, 1, b'l', id];
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes.extend_from_slice(rest);
+        bytes
+    }
+
+    #[test]
+    fn reads_lists() {
+        let root = parse(&list(1, 2, &[3, 4, 0])).unwrap();
+        let tags = root.get("l").and_then(Tag::as_list).unwrap();
+        assert_eq!(
+            tags.iter().filter_map(Tag::as_i64).collect::<Vec<_>>(),
+            [3, 4]
+        );
+        // empty lists are written with the end tag
+        assert!(parse(&list(0, 0, &[0])).is_ok());
+    }
+
+    #[test]
+    fn rejects_lists_that_cannot_fit() {
+        assert!(parse(&list(0, i32::MAX, &[0])).is_err());
+        assert!(parse(&list(1, i32::MAX, &[0])).is_err());
+        assert!(parse(&list(1, -1, &[0])).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Cave culling, skips chunks that cannot be seen through the air around the camera.
+//!
+//! Every 16³ section of a chunk records which of its faces are connected through blocks that
+//! let light through. Starting at the camera, the sections are walked away from it, only leaving
+//! a section through faces that connect to the one it was entered from. Chunks none of whose
+//! sections are reached are hidden behind terrain.
+//!
+//! https://tomcc.github.io/2014/08/31/visibility-1.html
+
+use crate::{
+    block::{Block, Face},
+    chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunks, MIN_Y, local_index},
+    frustum::{Aabb, Frustum},
+};
+use glam::{IVec3, Vec3};
+use std::collections::{HashSet, VecDeque};
+
+pub const SECTIONS: usize = CHUNK_HEIGHT / CHUNK_SIZE;
+
+/// Pairs of faces of a section that can see each other, one bit per pair.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Connectivity(u64);
+
+impl Connectivity {
+    pub fn connects(self, a: Face, b: Face) -> bool {
+        self.0 & (1 << (a as usize * 6 + b as usize)) != 0
+    }
+
+    fn connect(&mut self, a: Face, b: Face) {
+        self.0 |= 1 << (a as usize * 6 + b as usize);
+        self.0 |= 1 << (b as usize * 6 + a as usize);
+    }
+}
+
+/// Connectivity of every section of a chunk, from the bottom up.
+pub fn chunk_connectivity(blocks: &[Block]) -> [Connectivity; SECTIONS] {
+    std::array::from_fn(|section| section_connectivity(blocks, section))
+}
+
+/// Flood fills each pocket of air in a section and connects all the faces it touches.
+fn section_connectivity(blocks: &[Block], section: usize) -> Connectivity {
+    const SIZE: i32 = CHUNK_SIZE as i32;
+    let index = |p: IVec3| {
+        local_index(
+            p.x as usize,
+            section * CHUNK_SIZE + p.y as usize,
+            p.z as usize,
+        )
+    };
+    let local = |p: IVec3| ((p.y * SIZE + p.z) * SIZE + p.x) as usize;
+
+    let mut connectivity = Connectivity::default();
+    let mut visited = vec![false; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE];
+    let mut queue = VecDeque::new();
+    for y in 0..SIZE {
+        for z in 0..SIZE {
+            for x in 0..SIZE {
+                let start = IVec3::new(x, y, z);
+                if visited[local(start)] || blocks[index(start)].is_opaque() {
+                    continue;
+                }
+
+                let mut faces = Vec::new();
+                visited[local(start)] = true;
+                queue.push_back(start);
+                while let Some(position) = queue.pop_front() {
+                    for face in Face::ALL {
+                        let neighbour = position + face.normal();
+                        if neighbour.cmplt(IVec3::ZERO).any()
+                            || neighbour.cmpge(IVec3::splat(SIZE)).any()
+                        {
+                            if !faces.contains(&face) {
+                                faces.push(face);
+                            }
+                            continue;
+                        }
+                        if !visited[local(neighbour)] && !blocks[index(neighbour)].is_opaque() {
+                            visited[local(neighbour)] = true;
+                            queue.push_back(neighbour);
+                        }
+                    }
+                }
+
+                for a in &faces {
+                    for b in &faces {
+                        connectivity.connect(*a, *b);
+                    }
+                }
+            }
+        }
+    }
+    connectivity
+}
+
+/// Chunks with a section that can be seen from `position`, `None` if the camera is not in a
+/// loaded chunk and everything should be drawn.
+///
+/// Sections outside `frustum` are not walked through.
+pub fn visible_chunks(
+    chunks: &Chunks,
+    position: Vec3,
+    frustum: Option<&Frustum>,
+) -> Option<HashSet<(i64, i64)>> {
+    let size = CHUNK_SIZE as i32;
+    let block = (position + 0.5).floor().as_ivec3();
+    let start = IVec3::new(
+        block.x.div_euclid(size),
+        ((block.y - MIN_Y as i32).div_euclid(size)).clamp(0, SECTIONS as i32 - 1),
+        block.z.div_euclid(size),
+    );
+    chunks.connectivity((start.x as i64, start.z as i64))?;
+
+    let mut visible = HashSet::from([(start.x as i64, start.z as i64)]);
+    let mut visited = HashSet::from([start]);
+    // section, face it was entered through and every direction taken to reach it
+    let mut queue = VecDeque::from([(start, None::<Face>, 0u8)]);
+    while let Some((section, entered, directions)) = queue.pop_front() {
+        let Some(connectivity) = chunks.connectivity((section.x as i64, section.z as i64)) else {
+            continue;
+        };
+        let connectivity = connectivity[section.y as usize];
+        for face in Face::ALL {
+            // never turn back towards the camera
+            if directions & (1 << face.opposite() as usize) != 0 {
+                continue;
+            }
+            if entered.is_some_and(|entered| !connectivity.connects(entered, face)) {
+                continue;
+            }
+
+            let neighbour = section + face.normal();
+            if !(0..SECTIONS as i32).contains(&neighbour.y) || visited.contains(&neighbour) {
+                continue;
+            }
+            let key = (neighbour.x as i64, neighbour.z as i64);
+            if chunks.connectivity(key).is_none() {
+                continue;
+            }
+            if let Some(frustum) = frustum {
+                let min = (neighbour * size + IVec3::new(0, MIN_Y as i32, 0)).as_vec3() - 0.5;
+                let bounds = Aabb {
+                    min,
+                    max: min + size as f32,
+                };
+                if !frustum.intersects(&bounds) {
+                    continue;
+                }
+            }
+
+            visited.insert(neighbour);
+            visible.insert(key);
+            queue.push_back((
+                neighbour,
+                Some(face.opposite()),
+                directions | 1 << face as usize,
+            ));
+        }
+    }
+    Some(visible)
+}
+
+pub fn ui(ui: &mut egui::Ui, enabled: &mut bool, show_occluded: &mut bool) {
+    ui.horizontal(|ui| {
+        ui.checkbox(enabled, "Occlusion Culling");
+        ui.add_enabled(
+            *enabled,
+            egui::Checkbox::new(show_occluded, "Show Occluded"),
+        )
+        .on_hover_text("Draw hidden chunks in red instead of skipping them");
+    });
+}
@@ -0,0 +1,128 @@
+use crate::{camera::Camera, chunk::Chunks};
+use glam::{Vec2, Vec3};
+
+/// Point lights uploaded to the voxel shader each frame, keep in sync with `voxel.frag`.
+pub const MAX_POINT_LIGHTS: usize = 128;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    /// Distance at which the light fades out completely.
+    pub radius: f32,
+    /// Quadratic falloff, brightness is divided by `1 + attenuation * distance²`.
+    pub attenuation: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            color: Vec3::new(1.0, 0.8, 0.5),
+            radius: 12.0,
+            attenuation: 0.05,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PointLights {
+    pub lights: Vec<PointLight>,
+    /// Light that follows the camera.
+    pub carried: bool,
+    /// Settings of new and carried lights.
+    template: PointLight,
+    seed: u32,
+}
+
+impl PointLights {
+    /// Lights that reach within `view_distance` of the camera, nearest first.
+    pub fn visible(&self, camera: &Camera, view_distance: f32) -> Vec<PointLight> {
+        let position = camera.position();
+        let mut visible = self
+            .lights
+            .iter()
+            .copied()
+            .chain(self.carried.then_some(PointLight {
+                position,
+                ..self.template
+            }))
+            .filter(|light| light.position.distance(position) < view_distance + light.radius)
+            .collect::<Vec<_>>();
+        visible.sort_by(|a, b| {
+            a.position
+                .distance_squared(position)
+                .total_cmp(&b.position.distance_squared(position))
+        });
+        visible.truncate(MAX_POINT_LIGHTS);
+        visible
+    }
+
+    /// Places `count` lights of random colours just above the terrain around `center`.
+    fn scatter(&mut self, chunks: &Chunks, center: Vec3, count: usize, spread: f32) {
+        for _ in 0..count {
+            let offset = Vec2::new(self.random(), self.random()) * 2.0 - 1.0;
+            let x = (center.x + offset.x * spread).round() as i32;
+            let z = (center.z + offset.y * spread).round() as i32;
+            let Some(height) = chunks.surface_height(x, z) else {
+                continue;
+            };
+            // fully saturated colour from the hue
+            let hue = self.random() * 6.0;
+            let color = Vec3::new(
+                (hue - 3.0).abs() - 1.0,
+                2.0 - (hue - 2.0).abs(),
+                2.0 - (hue - 4.0).abs(),
+            )
+            .clamp(Vec3::ZERO, Vec3::ONE);
+            self.lights.push(PointLight {
+                position: Vec3::new(x as f32, height as f32 + 1.5, z as f32),
+                color,
+                ..self.template
+            });
+        }
+    }
+
+    /// xorshift, good enough to spread lights around.
+    fn random(&mut self) -> f32 {
+        if self.seed == 0 {
+            self.seed = 0x9e37_79b9;
+        }
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+pub fn ui(ui: &mut egui::Ui, point_lights: &mut PointLights, camera: &Camera, chunks: &Chunks) {
+    ui.label(format!("Point Lights ({})", point_lights.lights.len()));
+    ui.horizontal(|ui| {
+        ui.label("Color");
+        let mut color = point_lights.template.color.to_array();
+        if ui.color_edit_button_rgb(&mut color).changed() {
+            point_lights.template.color = Vec3::from_array(color);
+        }
+        ui.checkbox(&mut point_lights.carried, "Carry Light");
+    });
+    ui.add(egui::Slider::new(&mut point_lights.template.radius, 1.0..=64.0).text("Radius"));
+    ui.add(
+        egui::Slider::new(&mut point_lights.template.attenuation, 0.0..=1.0)
+            .logarithmic(true)
+            .text("Attenuation"),
+    );
+    ui.horizontal(|ui| {
+        if ui.button("Place").clicked() {
+            point_lights.lights.push(PointLight {
+                position: camera.position(),
+                ..point_lights.template
+            });
+        }
+        if ui.button("Scatter 64").clicked() {
+            point_lights.scatter(chunks, camera.position(), 64, 48.0);
+        }
+        if ui.button("Clear").clicked() {
+            point_lights.lights.clear();
+        }
+    });
+}
@@ -0,0 +1,818 @@
+//! HDR post processing between the scene and the screen.
+//!
+//! The scene is drawn into a floating point target of the
+//! [`RenderGraph`](crate::render_graph::RenderGraph) in linear colour, then every enabled
+//! [`Effect`] draws the output of the one before it into the next target and the last result
+//! is copied to the screen. Adding an effect only takes an implementation of [`Effect`] in
+//! [`PostProcess::new`].
+
+use crate::shader::uniform;
+use glam::{Mat4, Vec2};
+use glazer::glow::{self, HasContext};
+
+/// Colour texture with a framebuffer that draws into it.
+pub struct Target {
+    pub framebuffer: glow::Framebuffer,
+    pub texture: glow::Texture,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Target {
+    /// Allocates an uninitialised half float target, `format` is `RGBA16F` or `R16F`.
+    pub fn new(gl: &glow::Context, width: i32, height: i32, format: u32) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        unsafe {
+            let texture = gl.create_texture().unwrap();
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                format as i32,
+                width,
+                height,
+                0,
+                if format == glow::R16F {
+                    glow::RED
+                } else {
+                    glow::RGBA
+                },
+                glow::HALF_FLOAT,
+                glow::PixelUnpackData::Slice(None),
+            );
+            for (parameter, value) in [
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+            ] {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, parameter, value as i32);
+            }
+            gl.bind_texture(glow::TEXTURE_2D, None);
+
+            let framebuffer = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+
+            Self {
+                framebuffer,
+                texture,
+                width,
+                height,
+            }
+        }
+    }
+
+    /// Draws into the target from now on.
+    pub fn bind(&self, gl: &glow::Context) {
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+            gl.viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    pub fn delete(&self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_framebuffer(self.framebuffer);
+            gl.delete_texture(self.texture);
+        }
+    }
+}
+
+/// What every effect needs to know about the frame being processed.
+pub struct Frame {
+    pub width: i32,
+    pub height: i32,
+    /// Seconds since the last frame.
+    pub delta: f32,
+    /// Depth buffer of the scene.
+    pub depth: glow::Texture,
+    /// Matrices the scene was drawn with, without jitter.
+    pub proj: Mat4,
+    pub view: Mat4,
+    vao: glow::VertexArray,
+}
+
+impl Frame {
+    /// Runs the bound fragment shader over the whole bound target.
+    pub fn draw(&self, gl: &glow::Context) {
+        unsafe {
+            gl.bind_vertex_array(Some(self.vao));
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+        }
+    }
+
+    pub fn texel_size(&self) -> Vec2 {
+        1.0 / Vec2::new(self.width as f32, self.height as f32)
+    }
+}
+
+/// A step of the post processing chain.
+pub trait Effect {
+    fn name(&self) -> &'static str;
+
+    fn enabled(&self) -> bool;
+
+    /// Draws `input` with the effect applied into `output`, which is as large as the screen.
+    fn apply(&mut self, gl: &glow::Context, frame: &Frame, input: glow::Texture, output: &Target);
+
+    /// Settings of the effect, including whether it is enabled.
+    fn ui(&mut self, ui: &mut egui::Ui);
+
+    /// Offset in pixels to draw the next frame at, for effects that gather samples over frames.
+    fn jitter(&self) -> Vec2 {
+        Vec2::ZERO
+    }
+}
+
+/// Compiles a fragment shader for the fullscreen triangle of [`Frame::draw`] and points its
+/// samplers at consecutive texture units.
+macro_rules! post_shader {
+    ($gl:ident, $frag:literal, [$($sampler:literal),*]) => {{
+        let shader = crate::compile_shader!($gl, "shaders/post.vert", $frag);
+        unsafe {
+            $gl.use_program(Some(shader));
+        }
+        for (unit, sampler) in [$($sampler),*].into_iter().enumerate() {
+            crate::shader::uniform($gl, shader, sampler, |location| unsafe {
+                $gl.uniform_1_i32(location, unit as i32);
+            });
+        }
+        shader
+    }};
+}
+pub(crate) use post_shader;
+
+/// Binds `textures` to consecutive texture units, in the order of the samplers given to
+/// `post_shader!`.
+pub fn bind_textures(gl: &glow::Context, textures: &[glow::Texture]) {
+    unsafe {
+        for (unit, texture) in textures.iter().enumerate() {
+            gl.active_texture(glow::TEXTURE0 + unit as u32);
+            gl.bind_texture(glow::TEXTURE_2D, Some(*texture));
+        }
+        gl.active_texture(glow::TEXTURE0);
+    }
+}
+
+/// Multisampled buffers the scene is drawn into before being resolved.
+struct Multisample {
+    framebuffer: glow::Framebuffer,
+    renderbuffers: [glow::Renderbuffer; 2],
+    samples: i32,
+}
+
+impl Multisample {
+    fn new(gl: &glow::Context, width: i32, height: i32, samples: i32) -> Self {
+        unsafe {
+            let framebuffer = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            let renderbuffers = [
+                (glow::RGBA16F, glow::COLOR_ATTACHMENT0),
+                (glow::DEPTH_COMPONENT24, glow::DEPTH_ATTACHMENT),
+            ]
+            .map(|(format, attachment)| {
+                let renderbuffer = gl.create_renderbuffer().unwrap();
+                gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+                gl.renderbuffer_storage_multisample(
+                    glow::RENDERBUFFER,
+                    samples,
+                    format,
+                    width,
+                    height,
+                );
+                gl.framebuffer_renderbuffer(
+                    glow::FRAMEBUFFER,
+                    attachment,
+                    glow::RENDERBUFFER,
+                    Some(renderbuffer),
+                );
+                renderbuffer
+            });
+            gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            Self {
+                framebuffer,
+                renderbuffers,
+                samples,
+            }
+        }
+    }
+
+    fn delete(&self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_framebuffer(self.framebuffer);
+            for renderbuffer in self.renderbuffers {
+                gl.delete_renderbuffer(renderbuffer);
+            }
+        }
+    }
+}
+
+pub struct PostProcess {
+    width: i32,
+    height: i32,
+    /// Samples per pixel of the scene, 1 turns MSAA off.
+    pub samples: i32,
+    max_samples: i32,
+    /// Where the scene is drawn with MSAA before being resolved into the scene target.
+    multisample: Option<Multisample>,
+    /// Effects draw into these in turn.
+    targets: [Target; 2],
+    copy_shader: glow::Program,
+    vao: glow::VertexArray,
+    pub effects: Vec<Box<dyn Effect>>,
+}
+
+impl PostProcess {
+    pub fn new(gl: &glow::Context, width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(1) as i32, height.max(1) as i32);
+        Self {
+            width,
+            height,
+            samples: 1,
+            max_samples: unsafe { gl.get_parameter_i32(glow::MAX_SAMPLES) },
+            multisample: None,
+            targets: [
+                Target::new(gl, width, height, glow::RGBA16F),
+                Target::new(gl, width, height, glow::RGBA16F),
+            ],
+            copy_shader: post_shader!(gl, "shaders/copy.frag", ["source"]),
+            // the fullscreen triangle is generated from `gl_VertexID`
+            vao: unsafe { gl.create_vertex_array().unwrap() },
+            effects: vec![
+                Box::new(crate::ssao::Ssao::new(gl)),
+                Box::new(crate::antialias::Taa::new(gl)),
+                Box::new(Bloom::new(gl)),
+                Box::new(Exposure::new(gl)),
+                Box::new(ToneMap::new(gl)),
+                Box::new(Gamma::new(gl)),
+                // finds edges by contrast, which needs the colour as it is shown
+                Box::new(crate::antialias::Fxaa::new(gl)),
+            ],
+        }
+    }
+
+    /// Translation to apply after the projection so the scene is drawn at the jitter of the
+    /// enabled effects.
+    pub fn jitter(&self) -> Mat4 {
+        let jitter = self
+            .effects
+            .iter()
+            .filter(|effect| effect.enabled())
+            .map(|effect| effect.jitter())
+            .sum::<Vec2>();
+        // pixels to normalized device coordinates, which span 2
+        let offset = jitter * 2.0 / Vec2::new(self.width as f32, self.height as f32);
+        Mat4::from_translation(offset.extend(0.0))
+    }
+
+    /// Reallocates the targets if the screen changed size or MSAA was changed.
+    pub fn begin(&mut self, gl: &glow::Context, width: usize, height: usize) {
+        let (width, height) = (width.max(1) as i32, height.max(1) as i32);
+        let resized = (self.width, self.height) != (width, height);
+        if resized {
+            (self.width, self.height) = (width, height);
+            for target in &mut self.targets {
+                target.delete(gl);
+                *target = Target::new(gl, width, height, glow::RGBA16F);
+            }
+        }
+
+        let samples = self.samples.clamp(1, self.max_samples.max(1));
+        let applied = self
+            .multisample
+            .as_ref()
+            .map_or(1, |multisample| multisample.samples);
+        if resized || applied != samples {
+            if let Some(multisample) = self.multisample.take() {
+                multisample.delete(gl);
+            }
+            self.multisample = (samples > 1).then(|| Multisample::new(gl, width, height, samples));
+        }
+    }
+
+    pub fn multisampled(&self) -> bool {
+        self.multisample.is_some()
+    }
+
+    /// Draws the scene multisampled with MSAA, instead of into the bound framebuffer.
+    pub fn bind_scene(&self, gl: &glow::Context) {
+        if let Some(multisample) = &self.multisample {
+            unsafe {
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(multisample.framebuffer));
+            }
+        }
+    }
+
+    /// Resolves the multisampled scene into the colour and depth of the bound framebuffer.
+    pub fn resolve(&self, gl: &glow::Context) {
+        let Some(multisample) = &self.multisample else {
+            return;
+        };
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(multisample.framebuffer));
+            gl.blit_framebuffer(
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                self.width,
+                self.height,
+                glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT,
+                glow::NEAREST,
+            );
+        }
+    }
+
+    /// Runs the enabled effects over the scene and copies the result to the screen.
+    pub fn finish(
+        &mut self,
+        gl: &glow::Context,
+        delta: f32,
+        scene: glow::Texture,
+        depth: glow::Texture,
+        proj: Mat4,
+        view: Mat4,
+    ) {
+        let frame = Frame {
+            width: self.width,
+            height: self.height,
+            delta,
+            depth,
+            proj,
+            view,
+            vao: self.vao,
+        };
+        unsafe {
+            // wireframes only apply to the scene
+            gl.polygon_mode(glow::FRONT_AND_BACK, glow::FILL);
+            gl.disable(glow::DEPTH_TEST);
+            gl.disable(glow::BLEND);
+        }
+
+        let mut input = scene;
+        let mut next = 0;
+        for effect in self.effects.iter_mut().filter(|effect| effect.enabled()) {
+            let output = &self.targets[next];
+            effect.apply(gl, &frame, input, output);
+            input = output.texture;
+            next = 1 - next;
+        }
+
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            gl.viewport(0, 0, frame.width, frame.height);
+            gl.clear(glow::DEPTH_BUFFER_BIT);
+            gl.use_program(Some(self.copy_shader));
+            bind_textures(gl, &[input]);
+            frame.draw(gl);
+            gl.enable(glow::DEPTH_TEST);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+            gl.bind_vertex_array(None);
+            gl.use_program(None);
+        }
+    }
+}
+
+pub fn ui(ui: &mut egui::Ui, post: &mut PostProcess) {
+    ui.label("Post Processing");
+    egui::ComboBox::from_label("MSAA")
+        .selected_text(msaa_name(post.samples))
+        .show_ui(ui, |ui| {
+            for samples in [1, 2, 4, 8]
+                .into_iter()
+                .filter(|&samples| samples <= post.max_samples.max(1))
+            {
+                ui.selectable_value(&mut post.samples, samples, msaa_name(samples));
+            }
+        });
+    for effect in &mut post.effects {
+        effect.ui(ui);
+    }
+}
+
+fn msaa_name(samples: i32) -> String {
+    if samples > 1 {
+        format!("{samples}x")
+    } else {
+        "Off".to_string()
+    }
+}
+
+/// Light above a threshold blurred over a chain of smaller and smaller targets and added back.
+///
+/// https://learnopengl.com/Guest-Articles/2022/Phys.-Based-Bloom
+pub struct Bloom {
+    pub enabled: bool,
+    /// Brightness above which light blooms.
+    pub threshold: f32,
+    /// Range below the threshold over which bloom fades in.
+    pub knee: f32,
+    pub intensity: f32,
+    /// Spread of each upsample in texels.
+    pub radius: f32,
+    /// Number of halvings of the screen the light is blurred over.
+    pub levels: usize,
+    downsample_shader: glow::Program,
+    upsample_shader: glow::Program,
+    composite_shader: glow::Program,
+    /// Half the screen size, then halved again for each level.
+    mips: Vec<Target>,
+}
+
+impl Bloom {
+    const MAX_LEVELS: usize = 8;
+
+    fn new(gl: &glow::Context) -> Self {
+        Self {
+            enabled: true,
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.05,
+            radius: 1.0,
+            levels: 6,
+            downsample_shader: post_shader!(gl, "shaders/bloom_downsample.frag", ["source"]),
+            upsample_shader: post_shader!(gl, "shaders/bloom_upsample.frag", ["source"]),
+            composite_shader: post_shader!(gl, "shaders/bloom_composite.frag", ["source", "bloom"]),
+            mips: Vec::new(),
+        }
+    }
+}
+
+impl Effect for Bloom {
+    fn name(&self) -> &'static str {
+        "Bloom"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(&mut self, gl: &glow::Context, frame: &Frame, input: glow::Texture, output: &Target) {
+        let levels = self.levels.clamp(1, Self::MAX_LEVELS);
+        let first = ((frame.width / 2).max(1), (frame.height / 2).max(1));
+        if self.mips.len() != levels
+            || self.mips.first().map(|mip| (mip.width, mip.height)) != Some(first)
+        {
+            for mip in self.mips.drain(..) {
+                mip.delete(gl);
+            }
+            self.mips = (0..levels)
+                .map(|level| Target::new(gl, first.0 >> level, first.1 >> level, glow::RGBA16F))
+                .collect();
+        }
+
+        unsafe {
+            gl.use_program(Some(self.downsample_shader));
+            let mut source = (input, frame.width, frame.height);
+            for (level, mip) in self.mips.iter().enumerate() {
+                mip.bind(gl);
+                uniform(gl, self.downsample_shader, "texel_size", |location| {
+                    gl.uniform_2_f32(location, 1.0 / source.1 as f32, 1.0 / source.2 as f32);
+                });
+                uniform(gl, self.downsample_shader, "prefilter", |location| {
+                    gl.uniform_1_i32(location, (level == 0) as i32);
+                });
+                uniform(gl, self.downsample_shader, "threshold", |location| {
+                    gl.uniform_1_f32(location, self.threshold);
+                });
+                uniform(gl, self.downsample_shader, "knee", |location| {
+                    gl.uniform_1_f32(location, self.knee);
+                });
+                bind_textures(gl, &[source.0]);
+                frame.draw(gl);
+                source = (mip.texture, mip.width, mip.height);
+            }
+
+            // each level is blurred and added onto the next larger one
+            gl.use_program(Some(self.upsample_shader));
+            uniform(gl, self.upsample_shader, "radius", |location| {
+                gl.uniform_1_f32(location, self.radius);
+            });
+            gl.enable(glow::BLEND);
+            gl.blend_func(glow::ONE, glow::ONE);
+            for pair in self.mips.windows(2).rev() {
+                let (larger, smaller) = (&pair[0], &pair[1]);
+                larger.bind(gl);
+                uniform(gl, self.upsample_shader, "texel_size", |location| {
+                    gl.uniform_2_f32(
+                        location,
+                        1.0 / smaller.width as f32,
+                        1.0 / smaller.height as f32,
+                    );
+                });
+                bind_textures(gl, &[smaller.texture]);
+                frame.draw(gl);
+            }
+            gl.disable(glow::BLEND);
+
+            output.bind(gl);
+            gl.use_program(Some(self.composite_shader));
+            uniform(gl, self.composite_shader, "intensity", |location| {
+                gl.uniform_1_f32(location, self.intensity);
+            });
+            bind_textures(gl, &[input, self.mips[0].texture]);
+            frame.draw(gl);
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let name = self.name();
+        ui.checkbox(&mut self.enabled, name);
+        ui.add_enabled_ui(self.enabled, |ui| {
+            ui.add(egui::Slider::new(&mut self.threshold, 0.0..=4.0).text("Threshold"));
+            ui.add(egui::Slider::new(&mut self.knee, 0.0..=1.0).text("Knee"));
+            ui.add(
+                egui::Slider::new(&mut self.intensity, 0.0..=1.0)
+                    .logarithmic(true)
+                    .text("Intensity"),
+            );
+            ui.add(egui::Slider::new(&mut self.radius, 0.5..=4.0).text("Radius"));
+            ui.add(egui::Slider::new(&mut self.levels, 1..=Self::MAX_LEVELS).text("Levels"));
+        });
+    }
+}
+
+/// Scales the scene by a fixed exposure, or one that adapts to the average brightness.
+pub struct Exposure {
+    pub enabled: bool,
+    /// Exposure in stops, each doubles the brightness.
+    pub stops: f32,
+    pub auto_exposure: bool,
+    /// Brightness the average of the scene is scaled to with auto exposure.
+    pub key: f32,
+    /// How quickly auto exposure follows changes in brightness.
+    pub adaptation_speed: f32,
+    /// Range of average brightness auto exposure adapts to.
+    pub min_luminance: f32,
+    pub max_luminance: f32,
+    luminance_shader: glow::Program,
+    adapt_shader: glow::Program,
+    exposure_shader: glow::Program,
+    /// Log luminance of the scene, averaged by its mipmaps.
+    luminance: Target,
+    /// Luminance adapted to over time, read from one while writing the other.
+    adapted: [Target; 2],
+}
+
+impl Exposure {
+    const LUMINANCE_SIZE: i32 = 256;
+
+    fn new(gl: &glow::Context) -> Self {
+        let luminance = Target::new(gl, Self::LUMINANCE_SIZE, Self::LUMINANCE_SIZE, glow::R16F);
+        let adapted = [
+            Target::new(gl, 1, 1, glow::R16F),
+            Target::new(gl, 1, 1, glow::R16F),
+        ];
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(luminance.texture));
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MIN_FILTER,
+                glow::LINEAR_MIPMAP_NEAREST as i32,
+            );
+            gl.bind_texture(glow::TEXTURE_2D, None);
+            // start from a typical daylight scene
+            gl.clear_color(0.3, 0.0, 0.0, 1.0);
+            for target in &adapted {
+                target.bind(gl);
+                gl.clear(glow::COLOR_BUFFER_BIT);
+            }
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+        Self {
+            enabled: true,
+            stops: 0.0,
+            auto_exposure: false,
+            key: 0.18,
+            adaptation_speed: 1.5,
+            min_luminance: 0.02,
+            max_luminance: 4.0,
+            luminance_shader: post_shader!(gl, "shaders/luminance.frag", ["source"]),
+            adapt_shader: post_shader!(gl, "shaders/adapt.frag", ["luminance", "previous"]),
+            exposure_shader: post_shader!(gl, "shaders/exposure.frag", ["source", "adapted"]),
+            luminance,
+            adapted,
+        }
+    }
+}
+
+impl Effect for Exposure {
+    fn name(&self) -> &'static str {
+        "Exposure"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(&mut self, gl: &glow::Context, frame: &Frame, input: glow::Texture, output: &Target) {
+        unsafe {
+            if self.auto_exposure {
+                self.luminance.bind(gl);
+                gl.use_program(Some(self.luminance_shader));
+                bind_textures(gl, &[input]);
+                frame.draw(gl);
+                gl.bind_texture(glow::TEXTURE_2D, Some(self.luminance.texture));
+                gl.generate_mipmap(glow::TEXTURE_2D);
+
+                self.adapted.swap(0, 1);
+                let [adapted, previous] = &self.adapted;
+                adapted.bind(gl);
+                gl.use_program(Some(self.adapt_shader));
+                uniform(gl, self.adapt_shader, "top_level", |location| {
+                    gl.uniform_1_f32(location, Self::LUMINANCE_SIZE.ilog2() as f32);
+                });
+                uniform(gl, self.adapt_shader, "rate", |location| {
+                    gl.uniform_1_f32(location, 1.0 - (-frame.delta * self.adaptation_speed).exp());
+                });
+                uniform(gl, self.adapt_shader, "min_luminance", |location| {
+                    gl.uniform_1_f32(location, self.min_luminance);
+                });
+                uniform(gl, self.adapt_shader, "max_luminance", |location| {
+                    gl.uniform_1_f32(location, self.max_luminance);
+                });
+                bind_textures(gl, &[self.luminance.texture, previous.texture]);
+                frame.draw(gl);
+            }
+
+            output.bind(gl);
+            gl.use_program(Some(self.exposure_shader));
+            uniform(gl, self.exposure_shader, "exposure", |location| {
+                gl.uniform_1_f32(location, self.stops.exp2());
+            });
+            uniform(gl, self.exposure_shader, "auto_exposure", |location| {
+                gl.uniform_1_i32(location, self.auto_exposure as i32);
+            });
+            uniform(gl, self.exposure_shader, "key", |location| {
+                gl.uniform_1_f32(location, self.key);
+            });
+            bind_textures(gl, &[input, self.adapted[0].texture]);
+            frame.draw(gl);
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let name = self.name();
+        ui.checkbox(&mut self.enabled, name);
+        ui.add_enabled_ui(self.enabled, |ui| {
+            ui.add(egui::Slider::new(&mut self.stops, -4.0..=4.0).text("Stops"));
+            ui.checkbox(&mut self.auto_exposure, "Auto Exposure");
+            ui.add_enabled_ui(self.auto_exposure, |ui| {
+                ui.add(egui::Slider::new(&mut self.key, 0.05..=0.5).text("Key"));
+                ui.add(
+                    egui::Slider::new(&mut self.adaptation_speed, 0.1..=10.0)
+                        .logarithmic(true)
+                        .text("Adaptation Speed"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.min_luminance, 0.001..=1.0)
+                        .logarithmic(true)
+                        .text("Min Luminance"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.max_luminance, 1.0..=16.0)
+                        .logarithmic(true)
+                        .text("Max Luminance"),
+                );
+            });
+        });
+    }
+}
+
+/// Curve that maps HDR colour into the range of the screen, in the order of `mapper` in
+/// `tonemap.frag`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapper {
+    /// Fit of the ACES filmic curve.
+    Aces,
+    /// John Hable's Uncharted 2 curve.
+    Filmic,
+    Reinhard,
+}
+
+impl ToneMapper {
+    pub const ALL: [ToneMapper; 3] = [ToneMapper::Aces, ToneMapper::Filmic, ToneMapper::Reinhard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapper::Aces => "ACES",
+            ToneMapper::Filmic => "Filmic",
+            ToneMapper::Reinhard => "Reinhard",
+        }
+    }
+}
+
+pub struct ToneMap {
+    pub enabled: bool,
+    pub mapper: ToneMapper,
+    shader: glow::Program,
+}
+
+impl ToneMap {
+    fn new(gl: &glow::Context) -> Self {
+        Self {
+            enabled: true,
+            mapper: ToneMapper::Aces,
+            shader: post_shader!(gl, "shaders/tonemap.frag", ["source"]),
+        }
+    }
+}
+
+impl Effect for ToneMap {
+    fn name(&self) -> &'static str {
+        "Tone Mapping"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(&mut self, gl: &glow::Context, frame: &Frame, input: glow::Texture, output: &Target) {
+        output.bind(gl);
+        unsafe {
+            gl.use_program(Some(self.shader));
+            uniform(gl, self.shader, "mapper", |location| {
+                gl.uniform_1_i32(location, self.mapper as i32);
+            });
+        }
+        bind_textures(gl, &[input]);
+        frame.draw(gl);
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let name = self.name();
+            ui.checkbox(&mut self.enabled, name);
+            ui.add_enabled_ui(self.enabled, |ui| {
+                egui::ComboBox::from_id_salt("tone_mapper")
+                    .selected_text(self.mapper.name())
+                    .show_ui(ui, |ui| {
+                        for mapper in ToneMapper::ALL {
+                            ui.selectable_value(&mut self.mapper, mapper, mapper.name());
+                        }
+                    });
+            });
+        });
+    }
+}
+
+/// Encodes linear colour for the screen.
+pub struct Gamma {
+    pub enabled: bool,
+    pub gamma: f32,
+    shader: glow::Program,
+}
+
+impl Gamma {
+    fn new(gl: &glow::Context) -> Self {
+        Self {
+            enabled: true,
+            gamma: 2.2,
+            shader: post_shader!(gl, "shaders/gamma.frag", ["source"]),
+        }
+    }
+}
+
+impl Effect for Gamma {
+    fn name(&self) -> &'static str {
+        "Gamma Correction"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(&mut self, gl: &glow::Context, frame: &Frame, input: glow::Texture, output: &Target) {
+        output.bind(gl);
+        unsafe {
+            gl.use_program(Some(self.shader));
+            uniform(gl, self.shader, "gamma", |location| {
+                gl.uniform_1_f32(location, self.gamma);
+            });
+        }
+        bind_textures(gl, &[input]);
+        frame.draw(gl);
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let name = self.name();
+            ui.checkbox(&mut self.enabled, name);
+            ui.add_enabled(
+                self.enabled,
+                egui::Slider::new(&mut self.gamma, 1.0..=3.0).text("Gamma"),
+            );
+        });
+    }
+}
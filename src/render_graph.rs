@@ -0,0 +1,568 @@
+//! Passes of a frame declared with the targets they read and write.
+//!
+//! The graph owns the targets passes share, allocated at the size of the window, and binds a
+//! framebuffer with the targets a pass writes before running it. A pass runs after every pass
+//! that writes a target it reads, and passes that write the same target run in the order they
+//! were added, so the frame can be declared in any order that keeps those.
+
+use crate::gui::Egui;
+use crate::shader::uniform;
+use glazer::glow::{self, HasContext};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Linear HDR colour.
+    Rgba16F,
+    Rgba8,
+    Depth24,
+}
+
+impl Format {
+    /// Internal format, format and type of the texture.
+    fn gl(self) -> (u32, u32, u32) {
+        match self {
+            Format::Rgba16F => (glow::RGBA16F, glow::RGBA, glow::HALF_FLOAT),
+            Format::Rgba8 => (glow::RGBA8, glow::RGBA, glow::UNSIGNED_BYTE),
+            Format::Depth24 => (
+                glow::DEPTH_COMPONENT24,
+                glow::DEPTH_COMPONENT,
+                glow::UNSIGNED_INT,
+            ),
+        }
+    }
+
+    /// How `preview.frag` shows the texture.
+    fn preview_mode(self) -> i32 {
+        match self {
+            Format::Rgba16F => 0,
+            Format::Rgba8 => 1,
+            Format::Depth24 => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetId(usize);
+
+enum Storage {
+    /// Allocated by the graph at the size of the window, `None` while no pass uses it.
+    Owned {
+        format: Format,
+        texture: Option<glow::Texture>,
+    },
+    /// Owned elsewhere, like the shadow maps or the screen, only declared for ordering.
+    Imported,
+}
+
+struct Target {
+    name: &'static str,
+    storage: Storage,
+    /// Whether a pass of the current frame uses it.
+    used: bool,
+}
+
+/// Draws a pass, given the context passed to [`RenderGraph::execute`].
+type Run<'a, C> = Box<dyn FnOnce(&glow::Context, &mut C) + 'a>;
+
+pub struct Pass<'a, C> {
+    name: &'static str,
+    reads: Vec<TargetId>,
+    writes: Vec<TargetId>,
+    run: Run<'a, C>,
+}
+
+impl<'a, C> Pass<'a, C> {
+    pub fn new(
+        name: &'static str,
+        reads: &[TargetId],
+        writes: &[TargetId],
+        run: impl FnOnce(&glow::Context, &mut C) + 'a,
+    ) -> Self {
+        Self {
+            name,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            run: Box::new(run),
+        }
+    }
+}
+
+/// A pass of the last frame, for the ui.
+struct PassRecord {
+    name: &'static str,
+    reads: Vec<TargetId>,
+    writes: Vec<TargetId>,
+    /// Longest chain of passes it depends on.
+    level: usize,
+}
+
+/// Small copy of a target shown in the ui.
+struct Preview {
+    framebuffer: glow::Framebuffer,
+    texture: glow::Texture,
+    shader: glow::Program,
+    vao: glow::VertexArray,
+    id: Option<egui::TextureId>,
+}
+
+#[derive(Default)]
+pub struct RenderGraph {
+    targets: Vec<Target>,
+    width: i32,
+    height: i32,
+    /// Framebuffers with each combination of targets passes have written.
+    framebuffers: HashMap<Vec<TargetId>, glow::Framebuffer>,
+    /// Passes of the last frame in the order they ran.
+    passes: Vec<PassRecord>,
+    /// Indices into `passes` of each pass and one that depends on it.
+    edges: Vec<(usize, usize)>,
+    /// Target shown in the ui.
+    pub debug_target: Option<TargetId>,
+    preview: Option<Preview>,
+}
+
+impl RenderGraph {
+    const PREVIEW_WIDTH: i32 = 384;
+    const PREVIEW_HEIGHT: i32 = 216;
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width: width.max(1) as i32,
+            height: height.max(1) as i32,
+            ..Default::default()
+        }
+    }
+
+    /// Target owned by the graph called `name`, allocated if this is the first time it is used
+    /// since the window was resized.
+    pub fn target(&mut self, gl: &glow::Context, name: &'static str, format: Format) -> TargetId {
+        let id = self.find(name).unwrap_or_else(|| {
+            self.targets.push(Target {
+                name,
+                storage: Storage::Owned {
+                    format,
+                    texture: None,
+                },
+                used: false,
+            });
+            TargetId(self.targets.len() - 1)
+        });
+        let (width, height) = (self.width, self.height);
+        let target = &mut self.targets[id.0];
+        target.used = true;
+        if let Storage::Owned { format, texture } = &mut target.storage
+            && texture.is_none()
+        {
+            *texture = Some(create_texture(gl, *format, width, height));
+        }
+        id
+    }
+
+    /// Target owned outside the graph called `name`.
+    pub fn import(&mut self, name: &'static str) -> TargetId {
+        let id = self.find(name).unwrap_or_else(|| {
+            self.targets.push(Target {
+                name,
+                storage: Storage::Imported,
+                used: false,
+            });
+            TargetId(self.targets.len() - 1)
+        });
+        self.targets[id.0].used = true;
+        id
+    }
+
+    fn find(&self, name: &str) -> Option<TargetId> {
+        self.targets
+            .iter()
+            .position(|target| target.name == name)
+            .map(TargetId)
+    }
+
+    /// Texture of a target from [`RenderGraph::target`].
+    pub fn texture(&self, id: TargetId) -> glow::Texture {
+        match self.targets[id.0].storage {
+            Storage::Owned {
+                texture: Some(texture),
+                ..
+            } => texture,
+            _ => panic!("{} is not allocated by the graph", self.targets[id.0].name),
+        }
+    }
+
+    /// Frees every target, they are allocated at the new size when they are next used.
+    pub fn resize(&mut self, gl: &glow::Context, width: usize, height: usize) {
+        self.width = width.max(1) as i32;
+        self.height = height.max(1) as i32;
+        for id in 0..self.targets.len() {
+            self.free(gl, TargetId(id));
+        }
+    }
+
+    fn free(&mut self, gl: &glow::Context, id: TargetId) {
+        if let Storage::Owned { texture, .. } = &mut self.targets[id.0].storage
+            && let Some(texture) = texture.take()
+        {
+            unsafe {
+                gl.delete_texture(texture);
+            }
+            self.framebuffers.retain(|targets, framebuffer| {
+                let keep = !targets.contains(&id);
+                if !keep {
+                    unsafe {
+                        gl.delete_framebuffer(*framebuffer);
+                    }
+                }
+                keep
+            });
+        }
+    }
+
+    /// Framebuffer with the owned targets in `writes` attached, colour in order and depth.
+    fn framebuffer(&mut self, gl: &glow::Context, writes: Vec<TargetId>) -> glow::Framebuffer {
+        if let Some(framebuffer) = self.framebuffers.get(&writes) {
+            return *framebuffer;
+        }
+        unsafe {
+            let framebuffer = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            let mut draw_buffers = Vec::new();
+            for &id in &writes {
+                let Storage::Owned { format, .. } = self.targets[id.0].storage else {
+                    continue;
+                };
+                let attachment = if format == Format::Depth24 {
+                    glow::DEPTH_ATTACHMENT
+                } else {
+                    draw_buffers.push(glow::COLOR_ATTACHMENT0 + draw_buffers.len() as u32);
+                    *draw_buffers.last().unwrap()
+                };
+                gl.framebuffer_texture_2d(
+                    glow::FRAMEBUFFER,
+                    attachment,
+                    glow::TEXTURE_2D,
+                    Some(self.texture(id)),
+                    0,
+                );
+            }
+            if draw_buffers.is_empty() {
+                gl.draw_buffer(glow::NONE);
+            } else {
+                gl.draw_buffers(&draw_buffers);
+            }
+            self.framebuffers.insert(writes, framebuffer);
+            framebuffer
+        }
+    }
+
+    /// Runs `passes` in dependency order, binding the owned targets each writes first. Passes
+    /// that only write imported targets bind those themselves, and passes in a cycle are
+    /// skipped.
+    pub fn execute<C>(&mut self, gl: &glow::Context, context: &mut C, passes: Vec<Pass<'_, C>>) {
+        // every writer of a target comes before its readers, and writers keep their order
+        let mut edges = Vec::new();
+        for id in (0..self.targets.len()).map(TargetId) {
+            let writers = (0..passes.len())
+                .filter(|&pass| passes[pass].writes.contains(&id))
+                .collect::<Vec<_>>();
+            edges.extend(writers.windows(2).map(|pair| (pair[0], pair[1])));
+            for (reader, pass) in passes.iter().enumerate() {
+                if pass.reads.contains(&id) {
+                    edges.extend(
+                        writers
+                            .iter()
+                            .filter(|&&writer| writer != reader)
+                            .map(|&writer| (writer, reader)),
+                    );
+                }
+            }
+        }
+        edges.sort_unstable();
+        edges.dedup();
+
+        // https://en.wikipedia.org/wiki/Topological_sorting#Kahn's_algorithm, taking the pass
+        // added first when several are ready
+        let mut dependencies = vec![0; passes.len()];
+        for &(_, to) in &edges {
+            dependencies[to] += 1;
+        }
+        let mut levels = vec![0; passes.len()];
+        let mut order = Vec::new();
+        while let Some(next) =
+            (0..passes.len()).find(|&pass| dependencies[pass] == 0 && !order.contains(&pass))
+        {
+            order.push(next);
+            for &(from, to) in &edges {
+                if from == next {
+                    dependencies[to] -= 1;
+                    levels[to] = levels[to].max(levels[from] + 1);
+                }
+            }
+        }
+        if order.len() < passes.len() {
+            let skipped = (0..passes.len())
+                .filter(|pass| !order.contains(pass))
+                .map(|pass| passes[pass].name)
+                .collect::<Vec<_>>();
+            glazer::log!("[ERROR] render graph has a cycle between {skipped:?}");
+        }
+
+        let position = |pass: usize| order.iter().position(|&ran| ran == pass);
+        self.edges = edges
+            .iter()
+            .filter_map(|&(from, to)| Some((position(from)?, position(to)?)))
+            .collect();
+        self.passes = order
+            .iter()
+            .map(|&pass| PassRecord {
+                name: passes[pass].name,
+                reads: passes[pass].reads.clone(),
+                writes: passes[pass].writes.clone(),
+                level: levels[pass],
+            })
+            .collect();
+
+        let mut passes = passes.into_iter().map(Some).collect::<Vec<_>>();
+        for pass in order {
+            let pass = passes[pass].take().unwrap();
+            let owned = pass
+                .writes
+                .iter()
+                .copied()
+                .filter(|id| matches!(self.targets[id.0].storage, Storage::Owned { .. }))
+                .collect::<Vec<_>>();
+            if !owned.is_empty() {
+                let framebuffer = self.framebuffer(gl, owned);
+                unsafe {
+                    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+                    gl.viewport(0, 0, self.width, self.height);
+                }
+            }
+            (pass.run)(gl, context);
+        }
+
+        self.draw_preview(gl);
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            gl.viewport(0, 0, self.width, self.height);
+        }
+
+        // targets no pass used this frame, like the G-buffer of the forward path
+        for id in 0..self.targets.len() {
+            if !std::mem::take(&mut self.targets[id].used) {
+                self.free(gl, TargetId(id));
+                if self.debug_target == Some(TargetId(id)) {
+                    self.debug_target = None;
+                }
+            }
+        }
+    }
+
+    fn draw_preview(&mut self, gl: &glow::Context) {
+        let Some((texture, format)) =
+            self.debug_target
+                .and_then(|id| match self.targets[id.0].storage {
+                    Storage::Owned {
+                        format,
+                        texture: Some(texture),
+                    } => Some((texture, format)),
+                    _ => None,
+                })
+        else {
+            return;
+        };
+        let preview = self.preview.get_or_insert_with(|| unsafe {
+            let texture =
+                create_texture(gl, Format::Rgba8, Self::PREVIEW_WIDTH, Self::PREVIEW_HEIGHT);
+            let framebuffer = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            Preview {
+                framebuffer,
+                texture,
+                shader: crate::compile_shader!(gl, "shaders/post.vert", "shaders/preview.frag"),
+                // the fullscreen triangle is generated from `gl_VertexID`
+                vao: gl.create_vertex_array().unwrap(),
+                id: None,
+            }
+        });
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(preview.framebuffer));
+            gl.viewport(0, 0, Self::PREVIEW_WIDTH, Self::PREVIEW_HEIGHT);
+            gl.disable(glow::DEPTH_TEST);
+            gl.use_program(Some(preview.shader));
+            uniform(gl, preview.shader, "mode", |location| {
+                gl.uniform_1_i32(location, format.preview_mode());
+            });
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.bind_vertex_array(Some(preview.vao));
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+            gl.bind_vertex_array(None);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+            gl.use_program(None);
+            gl.enable(glow::DEPTH_TEST);
+        }
+    }
+
+    /// Hands the preview to egui the first time it is drawn, which deletes it along with its own
+    /// textures.
+    pub fn register_preview(&mut self, gui: &mut Egui) {
+        if let Some(preview) = self.preview.as_mut().filter(|preview| preview.id.is_none()) {
+            preview.id = Some(gui.register_texture(preview.texture));
+        }
+    }
+}
+
+fn create_texture(gl: &glow::Context, format: Format, width: i32, height: i32) -> glow::Texture {
+    let (internal_format, pixel_format, ty) = format.gl();
+    unsafe {
+        let texture = gl.create_texture().unwrap();
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl.tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            internal_format as i32,
+            width,
+            height,
+            0,
+            pixel_format,
+            ty,
+            glow::PixelUnpackData::Slice(None),
+        );
+        let filter = if format == Format::Depth24 {
+            glow::NEAREST
+        } else {
+            glow::LINEAR
+        };
+        for (parameter, value) in [
+            (glow::TEXTURE_MIN_FILTER, filter),
+            (glow::TEXTURE_MAG_FILTER, filter),
+            (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+            (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+        ] {
+            gl.tex_parameter_i32(glow::TEXTURE_2D, parameter, value as i32);
+        }
+        gl.bind_texture(glow::TEXTURE_2D, None);
+        texture
+    }
+}
+
+pub fn ui(ui: &mut egui::Ui, graph: &mut RenderGraph) {
+    ui.collapsing("Render Graph", |ui| {
+        // passes in columns by how many passes they wait for, with arrows to the passes that
+        // wait for them
+        let size = egui::vec2(96.0, 20.0);
+        let spacing = egui::vec2(24.0, 6.0);
+        let mut rows = HashMap::<usize, usize>::new();
+        let positions = graph
+            .passes
+            .iter()
+            .map(|pass| {
+                let row = rows.entry(pass.level).or_default();
+                *row += 1;
+                egui::vec2(
+                    pass.level as f32 * (size.x + spacing.x),
+                    (*row - 1) as f32 * (size.y + spacing.y),
+                )
+            })
+            .collect::<Vec<_>>();
+        let columns = rows.len();
+        let height = rows.values().max().copied().unwrap_or(0);
+        let (response, painter) = ui.allocate_painter(
+            egui::vec2(
+                columns as f32 * (size.x + spacing.x),
+                height as f32 * (size.y + spacing.y),
+            ),
+            egui::Sense::hover(),
+        );
+        let origin = response.rect.min.to_vec2();
+        let visuals = ui.visuals();
+        let stroke = visuals.widgets.noninteractive.fg_stroke;
+        for &(from, to) in &graph.edges {
+            let start = (origin + positions[from] + egui::vec2(size.x, size.y / 2.0)).to_pos2();
+            let end = (origin + positions[to] + egui::vec2(0.0, size.y / 2.0)).to_pos2();
+            painter.arrow(start, end - start, stroke);
+        }
+        for (pass, position) in graph.passes.iter().zip(&positions) {
+            let rect = egui::Rect::from_min_size((origin + *position).to_pos2(), size);
+            painter.rect(
+                rect,
+                3.0,
+                visuals.widgets.inactive.bg_fill,
+                stroke,
+                egui::StrokeKind::Inside,
+            );
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                pass.name,
+                egui::FontId::proportional(11.0),
+                visuals.text_color(),
+            );
+        }
+
+        let names = |targets: &[TargetId]| {
+            targets
+                .iter()
+                .map(|id| graph.targets[id.0].name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        for (i, pass) in graph.passes.iter().enumerate() {
+            ui.label(format!(
+                "{}. {}: {} → {}",
+                i + 1,
+                pass.name,
+                names(&pass.reads),
+                names(&pass.writes)
+            ));
+        }
+
+        let name = |id: Option<TargetId>| id.map_or("None", |id| graph.targets[id.0].name);
+        egui::ComboBox::from_label("Debug View")
+            .selected_text(name(graph.debug_target))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut graph.debug_target, None, "None");
+                for (id, target) in graph.targets.iter().enumerate() {
+                    if let Storage::Owned {
+                        texture: Some(_), ..
+                    } = target.storage
+                    {
+                        ui.selectable_value(
+                            &mut graph.debug_target,
+                            Some(TargetId(id)),
+                            target.name,
+                        );
+                    }
+                }
+            });
+        if let Some(id) = graph
+            .preview
+            .as_ref()
+            .and_then(|preview| preview.id)
+            .filter(|_| graph.debug_target.is_some())
+        {
+            // textures drawn by OpenGL start at the bottom
+            ui.add(
+                egui::Image::new(egui::load::SizedTexture::new(
+                    id,
+                    [
+                        RenderGraph::PREVIEW_WIDTH as f32,
+                        RenderGraph::PREVIEW_HEIGHT as f32,
+                    ],
+                ))
+                .uv(egui::Rect::from_min_max(
+                    egui::pos2(0.0, 1.0),
+                    egui::pos2(1.0, 0.0),
+                )),
+            );
+        }
+    });
+}
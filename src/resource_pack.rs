@@ -0,0 +1,302 @@
+//! Block textures loaded from a directory with a `pack.txt` manifest and a PNG per tile.
+//!
+//! See `assets/packs/default/pack.txt` for the manifest format. Every face of every block gets
+//! its own layer of the texture array, in the order of [`Block::texture_layers`], so meshes do
+//! not depend on the pack and switching packs only uploads new textures.
+//!
+//! Animated textures are strips of square frames stacked from top to bottom. Their frames are
+//! added after the layers of every face, and `voxel.vert` picks the frame from [`Animation`].
+
+use crate::block::{Block, Face};
+use crate::voxel::VoxelRenderer;
+use glazer::glow;
+use image::{RgbaImage, imageops};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Directory holding a directory per resource pack.
+pub const RESOURCE_PACKS: &str = "assets/packs";
+
+pub const DEFAULT_PACK: &str = "default";
+
+/// Tiles in each row of [`ResourcePack::atlas`].
+pub const ATLAS_COLUMNS: u32 = 16;
+
+/// Layers of every face of every block, passed to `voxel.vert` as `TEXTURE_LAYERS`.
+pub const FACE_LAYERS: usize = Block::ALL.len() * 6;
+
+/// Frames a face of a block cycles through, in the order of `animations` in `voxel.vert`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation {
+    /// Layer of the first frame, the frames of a texture are in consecutive layers.
+    pub first: u32,
+    pub frames: u32,
+    /// Seconds each frame is shown for.
+    pub frame_time: f32,
+    /// Blends each frame into the next instead of switching at once.
+    pub interpolate: bool,
+}
+
+impl Animation {
+    fn still(layer: u32) -> Self {
+        Self {
+            first: layer,
+            frames: 1,
+            frame_time: 1.0,
+            interpolate: false,
+        }
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [
+            self.first as f32,
+            self.frames as f32,
+            self.frame_time,
+            self.interpolate as u32 as f32,
+        ]
+    }
+}
+
+pub struct ResourcePack {
+    /// Directory the pack was loaded from.
+    pub path: PathBuf,
+    pub name: String,
+    /// Width and height of every layer.
+    pub tile_size: u32,
+    /// First frame of each face in the order of [`Block::texture_layers`], [`FACE_LAYERS`] long.
+    pub layers: Vec<RgbaImage>,
+    /// Every frame of the animated textures, uploaded after `layers`.
+    pub frames: Vec<RgbaImage>,
+    /// Animation of each of `layers`.
+    pub animations: Vec<Animation>,
+}
+
+impl ResourcePack {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let manifest = std::fs::read_to_string(path.join("pack.txt"))?;
+
+        let mut name = None;
+        let mut tile_size = None;
+        // texture of each face and how specific the line that set it was
+        let mut faces = vec![[(None, 0); 6]; Block::ALL.len()];
+        // seconds per frame and interpolation of animated textures
+        let mut frame_times = HashMap::new();
+        for line in manifest.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `key = value`"))?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "name" => name = Some(value.to_string()),
+                "tile_size" => {
+                    tile_size = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|size| *size > 0)
+                            .ok_or_else(|| invalid("invalid tile size"))?,
+                    );
+                }
+                file if file.ends_with(".png") => {
+                    let mut words = value.split_whitespace();
+                    let frame_time = words
+                        .next()
+                        .and_then(|time| time.parse::<f32>().ok())
+                        .filter(|time| *time > 0.0)
+                        .ok_or_else(|| invalid("invalid frame time"))?;
+                    let interpolate = match words.next() {
+                        None => false,
+                        Some("interpolate") => true,
+                        Some(_) => return Err(invalid("expected `interpolate` after frame time")),
+                    };
+                    frame_times.insert(file, (frame_time, interpolate));
+                }
+                key => {
+                    let (block, selector) = key.split_once('.').unwrap_or((key, ""));
+                    let block = Block::from_name(block)
+                        .ok_or_else(|| invalid("unknown block in resource pack"))?;
+                    let (selected, specificity) = select_faces(selector)
+                        .ok_or_else(|| invalid("unknown face in resource pack"))?;
+                    for face in selected {
+                        let texture = &mut faces[block as usize][*face as usize];
+                        if specificity >= texture.1 {
+                            *texture = (Some(value), specificity);
+                        }
+                    }
+                }
+            }
+        }
+        let tile_size = tile_size.ok_or_else(|| invalid("missing tile_size"))?;
+
+        let missing = missing_texture(tile_size);
+        // first frame and animation of each image, without the layer it starts at
+        let mut images = HashMap::new();
+        let mut layers = Vec::with_capacity(FACE_LAYERS);
+        let mut frames = Vec::new();
+        let mut animations = Vec::with_capacity(FACE_LAYERS);
+        for (layer, texture) in faces
+            .iter()
+            .flatten()
+            .map(|(texture, _)| *texture)
+            .enumerate()
+        {
+            let Some(file) = texture else {
+                layers.push(missing.clone());
+                animations.push(Animation::still(layer as u32));
+                continue;
+            };
+            if !images.contains_key(file) {
+                let image = image::open(path.join(file))
+                    .map_err(|err| io::Error::other(format!("{file}: {err}")))?
+                    .to_rgba8();
+                let (width, height) = image.dimensions();
+                let count = match frame_times.get(file) {
+                    Some(_) if width > 0 && height > width && height % width == 0 => height / width,
+                    _ => 1,
+                };
+                let mut strip = (0..count).map(|frame| {
+                    let frame =
+                        imageops::crop_imm(&image, 0, frame * width, width, width.min(height))
+                            .to_image();
+                    if frame.dimensions() == (tile_size, tile_size) {
+                        frame
+                    } else {
+                        imageops::resize(
+                            &frame,
+                            tile_size,
+                            tile_size,
+                            imageops::FilterType::Nearest,
+                        )
+                    }
+                });
+                let first_frame = strip.next().unwrap();
+                let animation = (count > 1).then(|| {
+                    let (frame_time, interpolate) = frame_times[file];
+                    let animation = Animation {
+                        first: (FACE_LAYERS + frames.len()) as u32,
+                        frames: count,
+                        frame_time,
+                        interpolate,
+                    };
+                    frames.push(first_frame.clone());
+                    frames.extend(strip);
+                    animation
+                });
+                images.insert(file, (first_frame, animation));
+            }
+            let (first_frame, animation) = &images[file];
+            layers.push(first_frame.clone());
+            animations.push(animation.unwrap_or(Animation::still(layer as u32)));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            name: name.unwrap_or_else(|| file_name(path)),
+            tile_size,
+            layers,
+            frames,
+            animations,
+        })
+    }
+
+    /// Every layer in one image [`ATLAS_COLUMNS`] tiles wide, for formats that take a single
+    /// texture. Layer `i` is in column `i % ATLAS_COLUMNS` of row `i / ATLAS_COLUMNS`.
+    pub fn atlas(&self) -> RgbaImage {
+        let rows = (self.layers.len() as u32).div_ceil(ATLAS_COLUMNS);
+        let mut atlas = RgbaImage::new(ATLAS_COLUMNS * self.tile_size, rows * self.tile_size);
+        for (i, layer) in self.layers.iter().enumerate() {
+            let (column, row) = (i as u32 % ATLAS_COLUMNS, i as u32 / ATLAS_COLUMNS);
+            imageops::replace(
+                &mut atlas,
+                layer,
+                (column * self.tile_size) as i64,
+                (row * self.tile_size) as i64,
+            );
+        }
+        atlas
+    }
+}
+
+/// Faces a manifest key selects after the block name, more specific selectors win.
+fn select_faces(selector: &str) -> Option<(&'static [Face], u8)> {
+    Some(match selector {
+        "" => (&Face::ALL, 0),
+        "side" => (&[Face::Back, Face::Front, Face::Left, Face::Right], 1),
+        "top" => (&[Face::Top], 2),
+        "bottom" => (&[Face::Bottom], 2),
+        "back" => (&[Face::Back], 2),
+        "front" => (&[Face::Front], 2),
+        "left" => (&[Face::Left], 2),
+        "right" => (&[Face::Right], 2),
+        _ => return None,
+    })
+}
+
+/// Magenta and black checkerboard for faces the manifest does not mention.
+fn missing_texture(size: u32) -> RgbaImage {
+    RgbaImage::from_fn(size, size, |x, y| {
+        if (x * 2 / size + y * 2 / size).is_multiple_of(2) {
+            image::Rgba([255, 0, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 255])
+        }
+    })
+}
+
+/// Names of the directories in [`RESOURCE_PACKS`] that hold a manifest.
+pub fn available() -> Vec<String> {
+    let mut packs = std::fs::read_dir(RESOURCE_PACKS)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join("pack.txt").is_file())
+        .map(|path| file_name(&path))
+        .collect::<Vec<_>>();
+    packs.sort();
+    packs
+}
+
+/// Switches between the packs in [`RESOURCE_PACKS`], keeping the current one if a pack fails to
+/// load.
+pub fn ui(
+    ui: &mut egui::Ui,
+    gl: &glow::Context,
+    pack: &mut ResourcePack,
+    voxel_renderer: &mut VoxelRenderer,
+) {
+    let current = file_name(&pack.path);
+    let mut selected = current.clone();
+    egui::ComboBox::from_label("Resource Pack")
+        .selected_text(&pack.name)
+        .show_ui(ui, |ui| {
+            for name in available() {
+                ui.selectable_value(&mut selected, name.clone(), name);
+            }
+        });
+    if selected != current {
+        match ResourcePack::load(Path::new(RESOURCE_PACKS).join(&selected)) {
+            Ok(loaded) => {
+                voxel_renderer.set_resource_pack(gl, &loaded);
+                *pack = loaded;
+            }
+            Err(err) => glazer::log!("[ERROR] failed to load resource pack {selected}: {err}"),
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,16 @@
+uniform sampler2D luminance;
+uniform sampler2D previous;
+// mip level of `luminance` that is a single texel
+uniform float top_level;
+// fraction of the way towards the new luminance to move this frame
+uniform float rate;
+uniform float min_luminance, max_luminance;
+
+out vec4 c;
+
+void main() {
+	float average = exp(textureLod(luminance, vec2(0.5), top_level).r);
+	average = clamp(average, min_luminance, max_luminance);
+	float adapted = texture(previous, vec2(0.5)).r;
+	c = vec4(mix(adapted, average, rate), 0.0, 0.0, 1.0);
+}
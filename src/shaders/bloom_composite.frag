@@ -0,0 +1,11 @@
+uniform sampler2D source;
+uniform sampler2D bloom;
+uniform float intensity;
+
+in vec2 uv;
+
+out vec4 c;
+
+void main() {
+	c = vec4(texture(source, uv).rgb + texture(bloom, uv).rgb * intensity, 1.0);
+}
@@ -0,0 +1,30 @@
+uniform sampler2D source;
+uniform vec2 texel_size;
+// only keeps light brighter than `threshold`, fading in over `knee` below it
+uniform int prefilter;
+uniform float threshold, knee;
+
+in vec2 uv;
+
+out vec4 c;
+
+vec3 tap(float x, float y) {
+	return texture(source, uv + vec2(x, y) * texel_size).rgb;
+}
+
+// 13 taps in overlapping boxes, which keeps single bright pixels from flickering
+// https://www.iryoku.com/next-generation-post-processing-in-call-of-duty-advanced-warfare/
+void main() {
+	vec3 color = tap(0.0, 0.0) * 0.125;
+	color += (tap(-2.0, 2.0) + tap(2.0, 2.0) + tap(-2.0, -2.0) + tap(2.0, -2.0)) * 0.03125;
+	color += (tap(0.0, 2.0) + tap(-2.0, 0.0) + tap(2.0, 0.0) + tap(0.0, -2.0)) * 0.0625;
+	color += (tap(-1.0, 1.0) + tap(1.0, 1.0) + tap(-1.0, -1.0) + tap(1.0, -1.0)) * 0.125;
+
+	if (prefilter == 1) {
+		float brightness = max(color.r, max(color.g, color.b));
+		float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+		soft = soft * soft / (4.0 * knee + 0.00001);
+		color *= max(soft, brightness - threshold) / max(brightness, 0.00001);
+	}
+	c = vec4(color, 1.0);
+}
@@ -0,0 +1,20 @@
+uniform sampler2D source;
+uniform vec2 texel_size;
+// spread of the tent filter in texels of `source`
+uniform float radius;
+
+in vec2 uv;
+
+out vec4 c;
+
+vec3 tap(float x, float y) {
+	return texture(source, uv + vec2(x, y) * texel_size * radius).rgb;
+}
+
+// 3x3 tent filter, added onto the next larger level
+void main() {
+	vec3 color = tap(0.0, 0.0) * 4.0;
+	color += (tap(0.0, 1.0) + tap(-1.0, 0.0) + tap(1.0, 0.0) + tap(0.0, -1.0)) * 2.0;
+	color += tap(-1.0, 1.0) + tap(1.0, 1.0) + tap(-1.0, -1.0) + tap(1.0, -1.0);
+	c = vec4(color / 16.0, 1.0);
+}
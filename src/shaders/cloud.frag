@@ -0,0 +1,25 @@
+uniform vec3 light_direction;
+uniform vec3 light_color;
+uniform float ambient_brightness;
+uniform vec3 fog_color;
+uniform vec3 camera_position;
+// horizontal distance at which the clouds have faded out
+uniform float fade_distance;
+uniform float opacity;
+
+in vec3 world_position;
+in vec3 world_normal;
+
+out vec4 c;
+
+void main() {
+	vec3 normal = normalize(world_normal);
+	float diff = max(dot(normal, normalize(light_direction)), 0.0);
+	// light scattered through the cloud reaches every side, the undersides least
+	float scatter = normal.y < -0.5 ? 0.2 : (normal.y > 0.5 ? 0.5 : 0.35);
+	vec3 color = vec3(ambient_brightness * 1.5) + (diff * 0.6 + scatter) * light_color;
+
+	float distance = length(world_position.xz - camera_position.xz);
+	float fade = smoothstep(fade_distance * 0.5, fade_distance, distance);
+	c = vec4(mix(color, fog_color, fade), opacity * (1.0 - fade));
+}
@@ -0,0 +1,15 @@
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 normal;
+
+uniform mat4 proj, view;
+// drift of the clouds, the mesh is in cloud space
+uniform vec3 offset;
+
+out vec3 world_position;
+out vec3 world_normal;
+
+void main() {
+	world_position = position + offset;
+	world_normal = normal;
+	gl_Position = proj * view * vec4(world_position, 1.0);
+}
@@ -0,0 +1,9 @@
+uniform sampler2D source;
+
+in vec2 uv;
+
+out vec4 c;
+
+void main() {
+	c = vec4(texture(source, uv).rgb, 1.0);
+}
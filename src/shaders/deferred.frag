@@ -0,0 +1,37 @@
+// see `GBuffer` in deferred.rs
+uniform sampler2D albedo_emission;
+uniform sampler2D normal;
+uniform sampler2D light_ao;
+uniform highp sampler2D depth;
+uniform mat4 inverse_proj;
+uniform mat4 inverse_view;
+
+in vec2 uv;
+
+out vec4 c;
+
+// shades each pixel of the G-buffer once, however many faces were drawn over it
+void main() {
+	float fragment_depth = texture(depth, uv).r;
+	if (fragment_depth >= 1.0) {
+		// nothing was drawn, left for the sky
+		discard;
+	}
+	vec4 view_position = inverse_proj * vec4(vec3(uv, fragment_depth) * 2.0 - 1.0, 1.0);
+	view_position /= view_position.w;
+	vec3 position = (inverse_view * view_position).xyz;
+
+	vec4 albedo = texture(albedo_emission, uv);
+	vec3 light = texture(light_ao, uv).rgb;
+	c = vec4(shade(
+		position,
+		-view_position.z,
+		normalize(texture(normal, uv).rgb * 2.0 - 1.0),
+		albedo.rgb,
+		light.xy,
+		light.z,
+		albedo.a
+	), 1.0);
+	// the sky, clouds and translucent faces are depth tested against it afterwards
+	gl_FragDepth = fragment_depth;
+}
@@ -0,0 +1,18 @@
+uniform sampler2D source;
+uniform sampler2D adapted;
+uniform float exposure;
+uniform int auto_exposure;
+// luminance the average of the scene is mapped to
+uniform float key;
+
+in vec2 uv;
+
+out vec4 c;
+
+void main() {
+	float scale = exposure;
+	if (auto_exposure == 1) {
+		scale *= key / max(texture(adapted, vec2(0.5)).r, 0.0001);
+	}
+	c = vec4(texture(source, uv).rgb * scale, 1.0);
+}
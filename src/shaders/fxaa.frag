@@ -0,0 +1,48 @@
+uniform sampler2D source;
+uniform vec2 texel_size;
+// texels an edge is blurred along at most
+uniform float span;
+
+in vec2 uv;
+
+out vec4 c;
+
+#define REDUCE_MIN (1.0 / 128.0)
+#define REDUCE_MUL (1.0 / 8.0)
+
+float luma(vec3 color) {
+	return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+// https://developer.download.nvidia.com/assets/gamedev/files/sdk/11/FXAA_WhitePaper.pdf
+void main() {
+	vec3 middle = texture(source, uv).rgb;
+	float luma_middle = luma(middle);
+	float luma_nw = luma(texture(source, uv + vec2(-1.0, 1.0) * texel_size).rgb);
+	float luma_ne = luma(texture(source, uv + vec2(1.0, 1.0) * texel_size).rgb);
+	float luma_sw = luma(texture(source, uv + vec2(-1.0, -1.0) * texel_size).rgb);
+	float luma_se = luma(texture(source, uv + vec2(1.0, -1.0) * texel_size).rgb);
+	float luma_min = min(luma_middle, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+	float luma_max = max(luma_middle, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+	// blur along the edge, perpendicular to the gradient of luma
+	vec2 direction = vec2(
+		(luma_nw + luma_ne) - (luma_sw + luma_se),
+		(luma_nw + luma_sw) - (luma_ne + luma_se)
+	);
+	float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+	float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+	direction = clamp(direction * scale, -span, span) * texel_size;
+
+	vec3 close_blur = 0.5 * (
+		texture(source, uv + direction * (1.0 / 3.0 - 0.5)).rgb
+		+ texture(source, uv + direction * (2.0 / 3.0 - 0.5)).rgb
+	);
+	vec3 wide_blur = close_blur * 0.5 + 0.25 * (
+		texture(source, uv - direction * 0.5).rgb
+		+ texture(source, uv + direction * 0.5).rgb
+	);
+	// the wide blur crossed into something else
+	float luma_wide = luma(wide_blur);
+	c = vec4(luma_wide < luma_min || luma_wide > luma_max ? close_blur : wide_blur, 1.0);
+}
@@ -0,0 +1,10 @@
+uniform sampler2D source;
+uniform float gamma;
+
+in vec2 uv;
+
+out vec4 c;
+
+void main() {
+	c = vec4(pow(max(texture(source, uv).rgb, 0.0), vec3(1.0 / gamma)), 1.0);
+}
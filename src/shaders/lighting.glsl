@@ -0,0 +1,188 @@
+// Lighting shared by the forward voxel shader and the deferred lighting pass, see voxel.rs.
+
+uniform highp sampler2DArray shadow_map;
+// the same shadow map with hardware depth comparison
+uniform highp sampler2DArrayShadow shadow_map_compare;
+
+// keep in sync with `MAX_CASCADES`
+#define MAX_CASCADES 4
+uniform mat4 light_space[MAX_CASCADES];
+// view space distance where each cascade ends
+uniform float cascade_far[MAX_CASCADES];
+uniform int cascade_count;
+uniform float shadow_texel_size;
+#if defined(SHADOW_FILTER_PCSS)
+// depth range of each cascade divided by its width
+uniform float cascade_depth_scale[MAX_CASCADES];
+uniform float light_size;
+#endif
+
+uniform vec3 light_direction;
+uniform vec3 light_color;
+uniform float ambient_brightness;
+uniform vec3 block_light_color;
+uniform float ao_strength;
+// brightness of emissive textures in linear HDR colour
+const float EMISSION_INTENSITY = 4.0;
+
+// keep in sync with `MAX_POINT_LIGHTS`
+#define MAX_POINT_LIGHTS 128
+struct PointLight {
+	vec4 position_radius;
+	vec4 color_attenuation;
+};
+layout (std140) uniform PointLights {
+	PointLight point_lights[MAX_POINT_LIGHTS];
+};
+uniform int point_light_count;
+// see fog.rs, the modes are in the order of `FogMode`
+const int FOG_OFF = 0;
+const int FOG_LINEAR = 1;
+const int FOG_EXPONENTIAL = 2;
+const int FOG_EXPONENTIAL_SQUARED = 3;
+uniform int fog_mode;
+uniform float fog_start, fog_end, fog_density;
+uniform float height_fog_density, fog_height, fog_height_falloff;
+uniform vec3 fog_color;
+uniform vec3 camera_position;
+
+// averages a square of `SHADOW_KERNEL_SIZE` comparisons that reaches `radius` from `uv`,
+// linear filtering makes each of them a 2x2 comparison on its own
+float pcf(vec3 uv, float depth, float radius) {
+	const int half_size = SHADOW_KERNEL_SIZE / 2;
+	float spacing = radius / float(max(half_size, 1));
+	float lit = 0.0;
+	for (int y = -half_size; y <= half_size; y++) {
+		for (int x = -half_size; x <= half_size; x++) {
+			vec2 offset = vec2(float(x), float(y)) * spacing;
+			lit += texture(shadow_map_compare, vec4(uv.xy + offset, uv.z, depth));
+		}
+	}
+	return 1.0 - lit / float(SHADOW_KERNEL_SIZE * SHADOW_KERNEL_SIZE);
+}
+
+#if defined(SHADOW_FILTER_PCSS)
+// https://developer.download.nvidia.com/shaderlibrary/docs/shadow_PCSS.pdf
+float pcss(vec3 uv, float depth, int cascade) {
+	// the light is directional, so sizes in the shadow map only depend on depth differences
+	float scale = light_size * cascade_depth_scale[cascade];
+
+	// average depth of the blocks between the light and this fragment
+	const int half_size = SHADOW_KERNEL_SIZE / 2;
+	float search = clamp(depth * scale, shadow_texel_size, 16.0 * shadow_texel_size);
+	float spacing = search / float(max(half_size, 1));
+	float blocker_depth = 0.0;
+	float blockers = 0.0;
+	for (int y = -half_size; y <= half_size; y++) {
+		for (int x = -half_size; x <= half_size; x++) {
+			vec2 offset = vec2(float(x), float(y)) * spacing;
+			float sample_depth = texture(shadow_map, vec3(uv.xy + offset, uv.z)).r;
+			if (sample_depth < depth) {
+				blocker_depth += sample_depth;
+				blockers += 1.0;
+			}
+		}
+	}
+	if (blockers == 0.0) {
+		return 0.0;
+	}
+
+	float penumbra = (depth - blocker_depth / blockers) * scale;
+	return pcf(uv, depth, max(penumbra, shadow_texel_size));
+}
+#endif
+
+// https://learnopengl.com/Advanced-Lighting/Shadows/Shadow-Mapping
+// https://learnopengl.com/Guest-Articles/2021/CSM
+float shadow_calculation(vec3 position, float view_depth, vec3 normal, vec3 light_dir) {
+	int cascade = -1;
+	for (int i = 0; i < cascade_count; i++) {
+		if (view_depth < cascade_far[i]) {
+			cascade = i;
+			break;
+		}
+	}
+	if (cascade < 0) {
+		return 0.0;
+	}
+
+	vec4 frag_pos_light_space = light_space[cascade] * vec4(position, 1.0);
+	vec3 proj_coords = frag_pos_light_space.xyz / frag_pos_light_space.w;
+	proj_coords = proj_coords * 0.5 + 0.5;
+	if (proj_coords.z > 1.0) {
+		return 0.0;
+	}
+	// further cascades cover more of the world with each texel
+	float bias = max(0.005 * (1.0 - dot(normal, light_dir)), 0.0005) * float(cascade + 1);
+	float depth = proj_coords.z - bias;
+	vec3 uv = vec3(proj_coords.xy, float(cascade));
+
+#if defined(SHADOW_FILTER_PCSS)
+	return pcss(uv, depth, cascade);
+#elif defined(SHADOW_FILTER_PCF)
+	return pcf(uv, depth, shadow_texel_size * float(SHADOW_KERNEL_SIZE / 2));
+#else
+	float closest_depth = texture(shadow_map, uv).r;
+	return depth > closest_depth ? 1.0 : 0.0;
+#endif
+}
+
+vec3 point_lighting(vec3 position, vec3 normal) {
+	vec3 lighting = vec3(0.0);
+	for (int i = 0; i < point_light_count; i++) {
+		vec3 to_light = point_lights[i].position_radius.xyz - position;
+		float radius = point_lights[i].position_radius.w;
+		float dist = length(to_light);
+		if (dist >= radius) {
+			continue;
+		}
+		// fade to zero at the radius so lights can be skipped beyond it
+		float window = clamp(1.0 - pow(dist / radius, 4.0), 0.0, 1.0);
+		float attenuation = window * window / (1.0 + point_lights[i].color_attenuation.w * dist * dist);
+		float diff = max(dot(normal, to_light / dist), 0.0);
+		lighting += diff * attenuation * point_lights[i].color_attenuation.rgb;
+	}
+	return lighting;
+}
+
+// how much of the colour at `position` is hidden by fog
+float fog_amount(vec3 position) {
+	vec3 ray = position - camera_position;
+	float distance = length(ray);
+	float fog = 0.0;
+	if (fog_mode == FOG_LINEAR) {
+		fog = clamp((distance - fog_start) / max(fog_end - fog_start, 0.001), 0.0, 1.0);
+	} else if (fog_mode == FOG_EXPONENTIAL) {
+		fog = 1.0 - exp(-fog_density * distance);
+	} else if (fog_mode == FOG_EXPONENTIAL_SQUARED) {
+		fog = 1.0 - exp(-pow(fog_density * distance, 2.0));
+	}
+
+	// density falls off exponentially with height, integrated along the ray
+	if (height_fog_density > 0.0) {
+		float falloff = fog_height_falloff;
+		float density = height_fog_density * exp(min(-falloff * (camera_position.y - fog_height), 80.0));
+		float rise = ray.y * falloff;
+		float integral = abs(rise) > 0.0001 ? (1.0 - exp(-rise)) / rise : 1.0;
+		fog = 1.0 - (1.0 - fog) * exp(-density * distance * integral);
+	}
+	return fog;
+}
+
+// colour of a surface lit by the sun or moon, block light and point lights, with ambient
+// occlusion from the mesh, its own emission and fog
+vec3 shade(vec3 position, float view_depth, vec3 normal, vec3 albedo, vec2 light, float ao, float emission) {
+	vec3 light_dir = normalize(light_direction);
+	float diff = max(dot(normal, light_dir), 0.0);
+	vec3 diffuse = diff * light_color;
+	float shadow = shadow_calculation(position, view_depth, normal, light_dir);
+	// each light level is 80% as bright as the one above it
+	float sky_light = pow(0.8, 15.0 * (1.0 - light.x));
+	float block_light = pow(0.8, 15.0 * (1.0 - light.y)) * step(0.001, light.y);
+	vec3 sky = (ambient_brightness + (1.0 - shadow) * diffuse) * sky_light;
+	float occlusion = 1.0 - ao_strength * (1.0 - ao);
+	vec3 lighting = (max(sky, block_light * block_light_color) * occlusion + point_lighting(position, normal)) * albedo;
+	// emissive textures glow on their own, brighter than lit surfaces so they bloom
+	lighting = max(lighting, emission * EMISSION_INTENSITY * albedo);
+	return mix(lighting, fog_color, fog_amount(position));
+}
@@ -0,0 +1,11 @@
+uniform sampler2D source;
+
+in vec2 uv;
+
+out vec4 c;
+
+// log of the luminance, so the average of the mipmap is the geometric mean
+void main() {
+	float luminance = dot(texture(source, uv).rgb, vec3(0.2126, 0.7152, 0.0722));
+	c = vec4(log(max(luminance, 0.0001)), 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,8 @@
+out vec2 uv;
+
+void main() {
+	// a triangle that covers the screen
+	vec2 position = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2)) * 2.0 - 1.0;
+	uv = position * 0.5 + 0.5;
+	gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,20 @@
+// see `Format::preview_mode` in render_graph.rs
+uniform highp sampler2D source;
+uniform int mode;
+
+in vec2 uv;
+
+out vec4 c;
+
+void main() {
+	vec4 texel = texture(source, uv);
+	if (mode == 0) {
+		// linear HDR, tone mapped and gamma corrected to fit the screen
+		c = vec4(pow(texel.rgb / (texel.rgb + 1.0), vec3(1.0 / 2.2)), 1.0);
+	} else if (mode == 1) {
+		c = vec4(texel.rgb, 1.0);
+	} else {
+		// depth is mostly close to 1, spread it out so nearby blocks are distinguishable
+		c = vec4(vec3(pow(texel.r, 64.0)), 1.0);
+	}
+}
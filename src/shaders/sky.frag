@@ -0,0 +1,101 @@
+uniform vec3 sun_direction, moon_direction;
+uniform float star_visibility;
+// see `Atmosphere` in sky.rs
+uniform vec3 rayleigh;
+uniform float rayleigh_height;
+uniform float mie, mie_height, mie_anisotropy;
+uniform float sun_intensity, moon_intensity;
+
+in vec3 direction;
+
+out vec4 c;
+
+// metres, keep in sync with sky.rs
+const float PLANET_RADIUS = 6371e3;
+const float ATMOSPHERE_RADIUS = 6471e3;
+const float VIEW_HEIGHT = 100.0;
+const int VIEW_SAMPLES = 16;
+const int LIGHT_SAMPLES = 8;
+const float PI = 3.14159265;
+
+float hash(vec3 p) {
+	p = fract(p * 0.3183099 + 0.1);
+	p *= 17.0;
+	return fract(p.x * p.y * p.z * (p.x + p.y + p.z));
+}
+
+// distance from `origin` inside the atmosphere to its top along `dir`
+float exit_distance(vec3 origin, vec3 dir) {
+	float b = dot(origin, dir);
+	float d = dot(origin, origin) - ATMOSPHERE_RADIUS * ATMOSPHERE_RADIUS;
+	return -b + sqrt(max(b * b - d, 0.0));
+}
+
+// light scattered towards the viewer along `dir` from a light of intensity 1 in `light_dir`
+vec3 scattering(vec3 dir, vec3 light_dir) {
+	vec3 origin = vec3(0.0, PLANET_RADIUS + VIEW_HEIGHT, 0.0);
+	float step_size = exit_distance(origin, dir) / float(VIEW_SAMPLES);
+
+	vec3 total_rayleigh = vec3(0.0);
+	vec3 total_mie = vec3(0.0);
+	vec2 optical_depth = vec2(0.0);
+	for (int i = 0; i < VIEW_SAMPLES; i++) {
+		vec3 position = origin + dir * (float(i) + 0.5) * step_size;
+		float height = length(position) - PLANET_RADIUS;
+		vec2 density = exp(-height / vec2(rayleigh_height, mie_height)) * step_size;
+		optical_depth += density;
+
+		// light that reaches the sample, none if the planet is in the way
+		float light_step = exit_distance(position, light_dir) / float(LIGHT_SAMPLES);
+		vec2 light_depth = vec2(0.0);
+		bool shadowed = false;
+		for (int j = 0; j < LIGHT_SAMPLES; j++) {
+			vec3 sample_position = position + light_dir * (float(j) + 0.5) * light_step;
+			float sample_height = length(sample_position) - PLANET_RADIUS;
+			if (sample_height < 0.0) {
+				shadowed = true;
+				break;
+			}
+			light_depth += exp(-sample_height / vec2(rayleigh_height, mie_height)) * light_step;
+		}
+		if (shadowed) {
+			continue;
+		}
+
+		vec3 attenuation = exp(-(rayleigh * (optical_depth.x + light_depth.x)
+			+ mie * 1.1 * (optical_depth.y + light_depth.y)));
+		total_rayleigh += density.x * attenuation;
+		total_mie += density.y * attenuation;
+	}
+
+	float mu = dot(dir, light_dir);
+	float g = mie_anisotropy;
+	float rayleigh_phase = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+	float mie_phase = 3.0 / (8.0 * PI) * ((1.0 - g * g) * (1.0 + mu * mu))
+		/ ((2.0 + g * g) * pow(1.0 + g * g - 2.0 * mu * g, 1.5));
+	return rayleigh_phase * rayleigh * total_rayleigh + mie_phase * mie * total_mie;
+}
+
+void main() {
+	vec3 dir = normalize(direction);
+	// below the horizon looks like the horizon, where fog takes its colour from
+	vec3 above = vec3(dir.x, max(dir.y, 0.0), dir.z);
+	vec3 scatter_dir = dot(above, above) > 0.0 ? normalize(above) : vec3(0.0, 1.0, 0.0);
+	// linear HDR, mapped to the screen by post processing
+	vec3 color = scattering(scatter_dir, normalize(sun_direction)) * sun_intensity
+		+ scattering(scatter_dir, normalize(moon_direction)) * moon_intensity;
+
+	// stars on a grid of directions, faded out near the horizon
+	vec3 cell = floor(dir * 250.0);
+	float star = step(0.997, hash(cell)) * hash(cell + 7.0);
+	color += vec3(star * star_visibility * smoothstep(0.0, 0.2, dir.y));
+
+	float sun = smoothstep(0.9995, 0.9998, dot(dir, normalize(sun_direction)));
+	// bright enough to bloom
+	color += sun * vec3(1.0, 0.9, 0.7) * sun_intensity * step(-0.1, sun_direction.y);
+
+	float moon = smoothstep(0.9996, 0.9998, dot(dir, normalize(moon_direction)));
+	color = mix(color, vec3(0.85, 0.87, 0.9), moon);
+
+	c = vec4(color, 1.0);
+}
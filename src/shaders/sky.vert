@@ -0,0 +1,11 @@
+uniform mat4 inverse_view_proj;
+
+out vec3 direction;
+
+void main() {
+	// a triangle that covers the screen
+	vec2 position = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2)) * 2.0 - 1.0;
+	vec4 world = inverse_view_proj * vec4(position, 1.0, 1.0);
+	direction = world.xyz / world.w;
+	gl_Position = vec4(position, 1.0, 1.0);
+}
@@ -0,0 +1,61 @@
+uniform highp sampler2D depth;
+uniform mat4 proj;
+uniform mat4 inverse_proj;
+// view space distance around each pixel that occludes it
+uniform float radius;
+uniform float bias;
+
+in vec2 uv;
+
+out vec4 c;
+
+// keep in sync with `Ssao::SAMPLES`
+#define SAMPLES 16
+#define GOLDEN_ANGLE 2.39996323
+
+vec3 view_position(vec2 at) {
+	vec4 position = inverse_proj * vec4(vec3(at, texture(depth, at).r) * 2.0 - 1.0, 1.0);
+	return position.xyz / position.w;
+}
+
+// http://www.iryoku.com/next-generation-post-processing-in-call-of-duty-advanced-warfare
+float interleaved_gradient_noise(vec2 pixel) {
+	return fract(52.9829189 * fract(dot(pixel, vec2(0.06711056, 0.00583715))));
+}
+
+// https://learnopengl.com/Advanced-Lighting/SSAO
+void main() {
+	if (texture(depth, uv).r >= 1.0) {
+		// the sky is never occluded
+		c = vec4(1.0);
+		return;
+	}
+	vec3 position = view_position(uv);
+	// blocks are flat, so the normal follows from neighbouring positions
+	vec3 normal = normalize(cross(dFdx(position), dFdy(position)));
+
+	// the hemisphere of samples is turned a different way at each pixel in a 4x4 pattern, which
+	// `ssao_blur.frag` averages away
+	float angle = interleaved_gradient_noise(mod(gl_FragCoord.xy, 4.0)) * 6.28318531;
+	vec3 random = vec3(cos(angle), sin(angle), 0.0);
+	vec3 tangent = normalize(random - normal * dot(random, normal));
+	mat3 tbn = mat3(tangent, cross(normal, tangent), normal);
+
+	float occlusion = 0.0;
+	for (int i = 0; i < SAMPLES; i++) {
+		// spiral over the hemisphere, with more samples close to the pixel
+		float t = (float(i) + 0.5) / float(SAMPLES);
+		float theta = float(i) * GOLDEN_ANGLE;
+		float r = sqrt(1.0 - t);
+		vec3 direction = vec3(cos(theta) * r, sin(theta) * r, sqrt(t));
+		vec3 sample_position = position + tbn * direction * radius * mix(0.1, 1.0, t * t);
+
+		vec4 offset = proj * vec4(sample_position, 1.0);
+		vec2 sample_uv = offset.xy / offset.w * 0.5 + 0.5;
+		float sample_depth = view_position(sample_uv).z;
+		// geometry far in front of the pixel does not occlude it
+		float range = smoothstep(0.0, 1.0, radius / abs(position.z - sample_depth));
+		occlusion += (sample_depth >= sample_position.z + bias ? 1.0 : 0.0) * range;
+	}
+	c = vec4(1.0 - occlusion / float(SAMPLES), 0.0, 0.0, 1.0);
+}
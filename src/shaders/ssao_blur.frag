@@ -0,0 +1,25 @@
+uniform sampler2D source;
+uniform sampler2D occlusion;
+uniform vec2 texel_size;
+uniform float intensity;
+uniform int show_occlusion;
+
+in vec2 uv;
+
+out vec4 c;
+
+// averages the 4x4 pattern of `ssao.frag` before darkening the scene with it
+void main() {
+	float ambient = 0.0;
+	for (int x = -2; x < 2; x++) {
+		for (int y = -2; y < 2; y++) {
+			ambient += texture(occlusion, uv + (vec2(x, y) + 0.5) * texel_size).r;
+		}
+	}
+	ambient = pow(ambient / 16.0, intensity);
+	if (show_occlusion == 1) {
+		c = vec4(vec3(ambient), 1.0);
+	} else {
+		c = vec4(texture(source, uv).rgb * ambient, 1.0);
+	}
+}
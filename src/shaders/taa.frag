@@ -0,0 +1,45 @@
+uniform sampler2D source;
+uniform sampler2D history;
+uniform highp sampler2D depth;
+// from this frame's clip space to the last one's
+uniform mat4 reprojection;
+uniform vec2 texel_size;
+// weight of this frame against the history
+uniform float blend;
+
+in vec2 uv;
+
+out vec4 c;
+
+float luminance(vec3 color) {
+	return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// https://de45xmedrsdbp.cloudfront.net/Resources/files/TemporalAA_small-59732822.pdf
+void main() {
+	vec3 current = texture(source, uv).rgb;
+
+	// history further than the colours around the pixel is from something no longer seen there
+	vec3 low = current;
+	vec3 high = current;
+	for (int x = -1; x <= 1; x++) {
+		for (int y = -1; y <= 1; y++) {
+			vec3 neighbour = texture(source, uv + vec2(x, y) * texel_size).rgb;
+			low = min(low, neighbour);
+			high = max(high, neighbour);
+		}
+	}
+
+	vec4 previous = reprojection * vec4(vec3(uv, texture(depth, uv).r) * 2.0 - 1.0, 1.0);
+	vec2 previous_uv = previous.xy / previous.w * 0.5 + 0.5;
+	vec3 history_color = clamp(texture(history, previous_uv).rgb, low, high);
+	bool offscreen = any(lessThan(previous_uv, vec2(0.0))) || any(greaterThan(previous_uv, vec2(1.0)));
+	float weight = offscreen ? 1.0 : blend;
+
+	// weighted by inverse luminance so single bright pixels do not smear
+	float current_weight = weight / (1.0 + luminance(current));
+	float history_weight = (1.0 - weight) / (1.0 + luminance(history_color));
+	vec3 color = (current * current_weight + history_color * history_weight)
+		/ max(current_weight + history_weight, 0.0001);
+	c = vec4(color, 1.0);
+}
@@ -0,0 +1,30 @@
+uniform sampler2D source;
+// in the order of `ToneMapper` in post.rs
+uniform int mapper;
+
+in vec2 uv;
+
+out vec4 c;
+
+// https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+vec3 aces(vec3 x) {
+	return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// http://filmicworlds.com/blog/filmic-tonemapping-operators/
+vec3 hable(vec3 x) {
+	const float A = 0.15, B = 0.50, C = 0.10, D = 0.20, E = 0.02, F = 0.30;
+	return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+void main() {
+	vec3 color = max(texture(source, uv).rgb, 0.0);
+	if (mapper == 0) {
+		color = aces(color);
+	} else if (mapper == 1) {
+		color = hable(color * 2.0) / hable(vec3(11.2));
+	} else {
+		color = color / (1.0 + color);
+	}
+	c = vec4(color, 1.0);
+}
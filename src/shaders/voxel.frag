@@ -1,40 +1,55 @@
-uniform sampler2D texture_atlas;
-uniform sampler2D shadow_map;
-
-uniform vec3 light_source;
-uniform vec3 light_color;
-uniform float ambient_brightness;
-uniform float fog_near, fog_far;
+// one layer per tile of the atlas
+uniform highp sampler2DArray texture_atlas;
+// pixels with less alpha are discarded, 0 for opaque and translucent faces
+uniform float alpha_cutoff;
+// colour blended over the chunk by debug views, alpha 0 leaves it unchanged
+uniform vec4 debug_color;
 
 in VS_OUT {
     vec3 position;
 	vec3 view_position;
-    vec4 light_space_position;
     vec3 normal;
     vec2 uv;
+    flat vec3 frames;
+    vec3 tint;
+    vec2 light;
+    float ao;
+    float emission;
 } fs_in;
 
+#if defined(DEFERRED)
+// see `GBuffer` in deferred.rs
+layout (location = 0) out vec4 albedo_emission;
+layout (location = 1) out vec4 normal_out;
+layout (location = 2) out vec4 light_ao;
+#else
 out vec4 c;
-
-// https://learnopengl.com/Advanced-Lighting/Shadows/Shadow-Mapping
-float shadow_calculation(vec4 frag_pos_light_space, vec3 normal, vec3 light_dir) {
-	vec3 proj_coords = frag_pos_light_space.xyz / frag_pos_light_space.w;
-	proj_coords = proj_coords * 0.5 + 0.5;
-	float depth = proj_coords.z;
-	float closest_depth = texture(shadow_map, proj_coords.xy).r;
-	float bias = max(0.05 * (1.0 - dot(normal, light_dir)), 0.005);
-	return depth - bias > closest_depth ? 1.0 : 0.0;
-}
+#endif
 
 void main() {
-	vec3 norm = normalize(fs_in.normal);
-	vec3 light_dir = normalize(light_source - fs_in.position);  
-	float diff = max(dot(norm, light_dir), 0.0);
-	vec3 diffuse = diff * light_color;
-
-	vec4 sample = texture(texture_atlas, fs_in.uv);
-	float shadow = shadow_calculation(fs_in.light_space_position, norm, light_dir);
-	vec3 lighting = (ambient_brightness + (1.0 - shadow) * diffuse) * vec3(sample);
-    c = vec4(lighting, sample.w);
-	c *= 1.0 - smoothstep(fog_near, fog_far, length(fs_in.view_position));
-} 
+	vec4 sample = mix(
+		texture(texture_atlas, vec3(fs_in.uv, fs_in.frames.x)),
+		texture(texture_atlas, vec3(fs_in.uv, fs_in.frames.y)),
+		fs_in.frames.z
+	) * vec4(fs_in.tint, 1.0);
+	if (sample.a < alpha_cutoff) {
+		discard;
+	}
+#if defined(DEFERRED)
+	// debug views tint the surface before it is lit
+	albedo_emission = vec4(mix(vec3(sample), debug_color.rgb, debug_color.a), fs_in.emission);
+	normal_out = vec4(normalize(fs_in.normal) * 0.5 + 0.5, 1.0);
+	light_ao = vec4(fs_in.light, fs_in.ao, 1.0);
+#else
+	vec3 color = shade(
+		fs_in.position,
+		-fs_in.view_position.z,
+		normalize(fs_in.normal),
+		vec3(sample),
+		fs_in.light,
+		fs_in.ao,
+		fs_in.emission
+	);
+	c = vec4(mix(color, debug_color.rgb, debug_color.a), sample.w);
+#endif
+}
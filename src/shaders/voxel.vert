@@ -1,31 +1,47 @@
 layout (location = 0) in vec3 position;
 layout (location = 1) in vec3 normal;
 layout (location = 2) in vec2 uv;
-layout (location = 3) in vec2 atlas_index;
-layout (location = 4) in vec3 translation;
+// layer of the texture array
+layout (location = 3) in float texture_layer;
+layout (location = 4) in vec3 tint;
+// sky and block light
+layout (location = 5) in vec2 light;
+layout (location = 6) in float ao;
+layout (location = 7) in float emission;
 
-uniform mat4 proj, view, light_space;
-uniform vec2 atlas_size, texture_size;
+uniform mat4 proj, view;
+// first layer, frame count, seconds per frame and whether to blend frames for each face layer
+uniform vec4 animations[TEXTURE_LAYERS];
+uniform float time;
 
 out VS_OUT {
     vec3 position;
 	vec3 view_position;
-    vec4 light_space_position;
     vec3 normal;
     vec2 uv;
+    // layer of the current and next frame and how far to blend into the next
+    flat vec3 frames;
+    vec3 tint;
+    vec2 light;
+    float ao;
+    float emission;
 } vs_out;
 
 void main() {
-	mat4 model;
-	model[0] = vec4(1.0, 0.0, 0.0, 0.0);
-	model[1] = vec4(0.0, 1.0, 0.0, 0.0);
-	model[2] = vec4(0.0, 0.0, 1.0, 0.0);
-	model[3] = vec4(translation, 1.0);
-
-	vs_out.position = vec3(model * vec4(position, 1.0));
+	vs_out.position = position;
 	vs_out.view_position = vec3(view * vec4(vs_out.position, 1.0));
-	vs_out.light_space_position = light_space * vec4(vs_out.position, 1.0);
-	vs_out.normal = normal; //mat3(transpose(inverse(model))) * normal;
-	vs_out.uv = (atlas_index + uv) * (texture_size / atlas_size);
+	vs_out.normal = normal;
+	vs_out.uv = uv;
+	vec4 animation = animations[int(texture_layer)];
+	float frame = time / animation.z;
+	vs_out.frames = vec3(
+		animation.x + mod(floor(frame), animation.y),
+		animation.x + mod(floor(frame) + 1.0, animation.y),
+		animation.w * fract(frame)
+	);
+	vs_out.tint = tint;
+	vs_out.light = light;
+	vs_out.ao = ao;
+	vs_out.emission = emission;
 	gl_Position = proj * view * vec4(vs_out.position, 1.0);
 }
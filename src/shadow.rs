@@ -0,0 +1,207 @@
+//! Cascaded shadow maps for the sun and moon.
+//!
+//! https://learn.microsoft.com/en-us/windows/win32/dxtecharts/cascaded-shadow-maps
+
+use glam::{Mat4, Vec3, Vec4Swizzles};
+
+pub const MAX_CASCADES: usize = 4;
+
+/// Blocks in front of a cascade towards the light that can still cast shadows into it.
+const CASTER_DISTANCE: f32 = 256.0;
+
+/// How shadow edges are smoothed, see `shadow_calculation` in `voxel.frag`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowFilter {
+    /// A single depth comparison with hard, jagged edges.
+    None,
+    /// Averages a square of hardware depth comparisons.
+    Pcf,
+    /// Percentage-closer soft shadows, which widen the PCF kernel with the distance between a
+    /// block and the blocks shading it.
+    Pcss,
+}
+
+impl ShadowFilter {
+    pub const ALL: [ShadowFilter; 3] = [ShadowFilter::None, ShadowFilter::Pcf, ShadowFilter::Pcss];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShadowFilter::None => "None",
+            ShadowFilter::Pcf => "PCF",
+            ShadowFilter::Pcss => "PCSS",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    /// Number of shadow maps the view is split into, at most [`MAX_CASCADES`].
+    pub cascades: usize,
+    /// Width and height of each shadow map.
+    pub resolution: i32,
+    /// Distance from the camera at which shadows end.
+    pub distance: f32,
+    /// Blend between uniform (0) and logarithmic (1) split distances.
+    pub split_lambda: f32,
+    pub filter: ShadowFilter,
+    /// Width of the square of samples taken by PCF and PCSS, always odd.
+    pub kernel_size: u32,
+    /// Apparent size of the sun for PCSS, larger lights cast softer shadows.
+    pub light_size: f32,
+    /// Draws every cascade in the corner of the screen.
+    pub show_maps: bool,
+}
+
+impl ShadowSettings {
+    /// Defines prepended to `voxel.frag`, the shader is recompiled when they change.
+    pub fn defines(&self) -> String {
+        let filter = match self.filter {
+            ShadowFilter::None => "SHADOW_FILTER_NONE",
+            ShadowFilter::Pcf => "SHADOW_FILTER_PCF",
+            ShadowFilter::Pcss => "SHADOW_FILTER_PCSS",
+        };
+        format!(
+            "#define {filter}\n#define SHADOW_KERNEL_SIZE {}\n",
+            self.kernel_size | 1
+        )
+    }
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            cascades: 3,
+            resolution: 2048,
+            distance: 192.0,
+            split_lambda: 0.75,
+            filter: ShadowFilter::Pcf,
+            kernel_size: 3,
+            light_size: 0.05,
+            show_maps: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Cascade {
+    pub light_space: Mat4,
+    /// View space distance at which the next cascade starts.
+    pub far: f32,
+    /// World units covered by the width of the shadow map.
+    pub width: f32,
+    /// World units between the near and far plane of the light.
+    pub depth: f32,
+}
+
+/// Fits a shadow map to each slice of the camera frustum.
+///
+/// Each cascade covers the bounding sphere of its slice, so its size does not change as the
+/// camera turns, and is moved in whole texels so shadow edges do not shimmer.
+pub fn cascades(
+    settings: &ShadowSettings,
+    view: Mat4,
+    fov: f32,
+    aspect: f32,
+    near: f32,
+    light_direction: Vec3,
+) -> Vec<Cascade> {
+    let count = settings.cascades.clamp(1, MAX_CASCADES);
+    let far = settings.distance.max(near + 1.0);
+    let splits = (0..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            let uniform = near + (far - near) * t;
+            let logarithmic = near * (far / near).powf(t);
+            uniform + (logarithmic - uniform) * settings.split_lambda
+        })
+        .collect::<Vec<_>>();
+
+    let up = if light_direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let light_view = Mat4::look_at_rh(Vec3::ZERO, -light_direction, up);
+
+    splits
+        .windows(2)
+        .map(|slice| {
+            let corners = frustum_corners(view, fov, aspect, slice[0], slice[1]);
+            let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+            let radius = corners
+                .iter()
+                .map(|corner| corner.distance(center))
+                .fold(0.0, f32::max);
+            // rounded so floating point noise does not change the texel size
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            let texel = 2.0 * radius / settings.resolution as f32;
+            let center = light_view.transform_point3(center);
+            let x = (center.x / texel).floor() * texel;
+            let y = (center.y / texel).floor() * texel;
+            // the light looks down -Z, blocks towards it have a larger Z
+            let proj = Mat4::orthographic_rh_gl(
+                x - radius,
+                x + radius,
+                y - radius,
+                y + radius,
+                -(center.z + radius + CASTER_DISTANCE),
+                -(center.z - radius),
+            );
+            Cascade {
+                light_space: proj * light_view,
+                far: slice[1],
+                width: 2.0 * radius,
+                depth: 2.0 * radius + CASTER_DISTANCE,
+            }
+        })
+        .collect()
+}
+
+/// World space corners of the part of the view frustum between `near` and `far`.
+fn frustum_corners(view: Mat4, fov: f32, aspect: f32, near: f32, far: f32) -> [Vec3; 8] {
+    let inverse = (Mat4::perspective_rh_gl(fov, aspect, near, far) * view).inverse();
+    let mut corners = [Vec3::ZERO; 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        let ndc = Vec3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        );
+        let world = inverse * ndc.extend(1.0);
+        *corner = world.xyz() / world.w;
+    }
+    corners
+}
+
+pub fn ui(ui: &mut egui::Ui, settings: &mut ShadowSettings) {
+    ui.label("Shadows");
+    ui.add(egui::Slider::new(&mut settings.cascades, 1..=MAX_CASCADES).text("Cascades"));
+    egui::ComboBox::from_label("Resolution")
+        .selected_text(settings.resolution.to_string())
+        .show_ui(ui, |ui| {
+            for resolution in [512, 1024, 2048, 4096] {
+                ui.selectable_value(&mut settings.resolution, resolution, resolution.to_string());
+            }
+        });
+    ui.add(egui::Slider::new(&mut settings.distance, 16.0..=512.0).text("Distance"));
+    ui.add(egui::Slider::new(&mut settings.split_lambda, 0.0..=1.0).text("Split Lambda"));
+    egui::ComboBox::from_label("Filter")
+        .selected_text(settings.filter.name())
+        .show_ui(ui, |ui| {
+            for filter in ShadowFilter::ALL {
+                ui.selectable_value(&mut settings.filter, filter, filter.name());
+            }
+        });
+    ui.add_enabled(
+        settings.filter != ShadowFilter::None,
+        egui::Slider::new(&mut settings.kernel_size, 1..=9)
+            .step_by(2.0)
+            .text("Kernel Size"),
+    );
+    ui.add_enabled(
+        settings.filter == ShadowFilter::Pcss,
+        egui::Slider::new(&mut settings.light_size, 0.0..=0.2).text("Light Size"),
+    );
+    ui.checkbox(&mut settings.show_maps, "Show Shadow Maps");
+}
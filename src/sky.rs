@@ -0,0 +1,324 @@
+//! World clock, the sun and moon that follow it and the sky behind the terrain.
+//!
+//! The sky is single Rayleigh and Mie scattering through a spherical atmosphere, marched in
+//! `sky.frag` for every pixel and in [`scattering`] for the horizon colour used by fog.
+//!
+//! https://github.com/wwwtyro/glsl-atmosphere
+//! https://www.scratchapixel.com/lessons/procedural-generation-virtual-worlds/simulating-sky/simulating-colors-of-the-sky.html
+
+use crate::shader::uniform;
+use glam::{Mat4, Vec3};
+use glazer::glow::{self, HasContext};
+
+pub struct Clock {
+    /// Time of day in hours, the sun rises at 6 and sets at 18.
+    pub time: f32,
+    /// In-game hours per real second.
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            time: 10.0,
+            speed: 0.02,
+            paused: false,
+        }
+    }
+}
+
+impl Clock {
+    pub fn update(&mut self, delta: f32) {
+        if !self.paused {
+            self.time = (self.time + self.speed * delta).rem_euclid(24.0);
+        }
+    }
+
+    /// Direction towards the sun, which rises in +X and passes south of the zenith.
+    pub fn sun_direction(&self) -> Vec3 {
+        const TILT: f32 = 25f32.to_radians();
+        let angle = (self.time - 6.0) / 24.0 * std::f32::consts::TAU;
+        Vec3::new(
+            angle.cos(),
+            angle.sin() * TILT.cos(),
+            -angle.sin() * TILT.sin(),
+        )
+    }
+
+    pub fn moon_direction(&self) -> Vec3 {
+        -self.sun_direction()
+    }
+
+    pub fn sky(&self, atmosphere: &Atmosphere) -> Sky {
+        let sun = self.sun_direction();
+        let moon = self.moon_direction();
+        let daylight = smoothstep(-0.1, 0.15, sun.y);
+
+        let (light_direction, light_color) = if sun.y >= 0.0 {
+            let color = Vec3::new(1.0, 0.55, 0.3)
+                .lerp(Vec3::new(1.0, 0.97, 0.9), smoothstep(0.0, 0.4, sun.y));
+            (sun, color * smoothstep(0.0, 0.1, sun.y))
+        } else {
+            (
+                moon,
+                Vec3::new(0.25, 0.3, 0.45) * smoothstep(0.0, 0.1, moon.y),
+            )
+        };
+
+        // averaged around the horizon, just above it where the sky meets distant terrain
+        let horizon_color = (0..8)
+            .map(|i| {
+                let angle = i as f32 / 8.0 * std::f32::consts::TAU;
+                let direction = Vec3::new(angle.cos(), 0.05, angle.sin()).normalize();
+                atmosphere.sky_color(direction, sun, moon)
+            })
+            .sum::<Vec3>()
+            / 8.0;
+
+        Sky {
+            sun_direction: sun,
+            moon_direction: moon,
+            light_direction,
+            light_color,
+            ambient_brightness: 0.08 + 0.32 * daylight,
+            horizon_color,
+            star_visibility: 1.0 - smoothstep(-0.2, 0.05, sun.y),
+            atmosphere: *atmosphere,
+        }
+    }
+}
+
+/// Everything the time of day changes about the sky and lighting.
+#[derive(Debug, Clone, Copy)]
+pub struct Sky {
+    pub sun_direction: Vec3,
+    pub moon_direction: Vec3,
+    /// Direction towards the sun during the day and the moon at night.
+    pub light_direction: Vec3,
+    pub light_color: Vec3,
+    pub ambient_brightness: f32,
+    /// Colour of the sky at the horizon, also used for fog.
+    pub horizon_color: Vec3,
+    pub star_visibility: f32,
+    pub atmosphere: Atmosphere,
+}
+
+/// Metres from the centre of the planet to the ground.
+const PLANET_RADIUS: f32 = 6_371e3;
+/// Metres from the centre of the planet to the top of the atmosphere.
+const ATMOSPHERE_RADIUS: f32 = 6_471e3;
+/// Metres above the ground the sky is seen from.
+const VIEW_HEIGHT: f32 = 100.0;
+/// Samples along the view ray and along the ray towards the light from each of them, keep in
+/// sync with `sky.frag`.
+const VIEW_SAMPLES: usize = 16;
+const LIGHT_SAMPLES: usize = 8;
+
+/// Scattering properties of the atmosphere, see `sky.frag`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Atmosphere {
+    /// Rayleigh scattering coefficient per metre at the ground for red, green and blue, air
+    /// scatters blue the most.
+    pub rayleigh: Vec3,
+    /// Metres over which the density of air falls by a factor of e.
+    pub rayleigh_height: f32,
+    /// Mie scattering coefficient per metre at the ground, haze scatters every colour alike.
+    pub mie: f32,
+    pub mie_height: f32,
+    /// How much haze scatters light forwards, which makes the glow around the sun.
+    pub mie_anisotropy: f32,
+    /// Brightness of the light, the sky is drawn in linear HDR colour and mapped to the range
+    /// of the screen by post processing.
+    pub sun_intensity: f32,
+    pub moon_intensity: f32,
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Self {
+            rayleigh: Vec3::new(5.5e-6, 13.0e-6, 22.4e-6),
+            rayleigh_height: 8e3,
+            mie: 21e-6,
+            mie_height: 1.2e3,
+            mie_anisotropy: 0.758,
+            sun_intensity: 22.0,
+            moon_intensity: 0.5,
+        }
+    }
+}
+
+impl Atmosphere {
+    /// Colour of the sky in `direction` lit by the sun and moon, as drawn by `sky.frag` without
+    /// the sun, moon and stars.
+    pub fn sky_color(&self, direction: Vec3, sun: Vec3, moon: Vec3) -> Vec3 {
+        scattering(self, direction, sun) * self.sun_intensity
+            + scattering(self, direction, moon) * self.moon_intensity
+    }
+}
+
+/// Light scattered towards the viewer along `direction` from a light of intensity 1 in
+/// `light_direction`, directions below the horizon are treated as the horizon.
+pub fn scattering(atmosphere: &Atmosphere, direction: Vec3, light_direction: Vec3) -> Vec3 {
+    let direction = Vec3::new(direction.x, direction.y.max(0.0), direction.z)
+        .try_normalize()
+        .unwrap_or(Vec3::Y);
+    let origin = Vec3::new(0.0, PLANET_RADIUS + VIEW_HEIGHT, 0.0);
+    let step = exit_distance(origin, direction) / VIEW_SAMPLES as f32;
+
+    let mut rayleigh = Vec3::ZERO;
+    let mut mie = Vec3::ZERO;
+    let mut optical_depth = (0.0, 0.0);
+    for i in 0..VIEW_SAMPLES {
+        let position = origin + direction * (i as f32 + 0.5) * step;
+        let height = position.length() - PLANET_RADIUS;
+        let density = (
+            (-height / atmosphere.rayleigh_height).exp() * step,
+            (-height / atmosphere.mie_height).exp() * step,
+        );
+        optical_depth.0 += density.0;
+        optical_depth.1 += density.1;
+
+        // light that reaches the sample through the atmosphere, none if the planet is in the way
+        let light_step = exit_distance(position, light_direction) / LIGHT_SAMPLES as f32;
+        let mut light_depth = (0.0, 0.0);
+        let mut shadowed = false;
+        for j in 0..LIGHT_SAMPLES {
+            let sample = position + light_direction * (j as f32 + 0.5) * light_step;
+            let height = sample.length() - PLANET_RADIUS;
+            if height < 0.0 {
+                shadowed = true;
+                break;
+            }
+            light_depth.0 += (-height / atmosphere.rayleigh_height).exp() * light_step;
+            light_depth.1 += (-height / atmosphere.mie_height).exp() * light_step;
+        }
+        if shadowed {
+            continue;
+        }
+
+        let attenuation = (-(atmosphere.rayleigh * (optical_depth.0 + light_depth.0)
+            + Vec3::splat(atmosphere.mie * 1.1 * (optical_depth.1 + light_depth.1))))
+        .exp();
+        rayleigh += density.0 * attenuation;
+        mie += density.1 * attenuation;
+    }
+
+    let mu = direction.dot(light_direction);
+    let g = atmosphere.mie_anisotropy;
+    let rayleigh_phase = 3.0 / (16.0 * std::f32::consts::PI) * (1.0 + mu * mu);
+    let mie_phase = 3.0 / (8.0 * std::f32::consts::PI) * ((1.0 - g * g) * (1.0 + mu * mu))
+        / ((2.0 + g * g) * (1.0 + g * g - 2.0 * mu * g).powf(1.5));
+    rayleigh_phase * atmosphere.rayleigh * rayleigh + mie_phase * atmosphere.mie * mie
+}
+
+/// Distance from `origin` inside the atmosphere to its top along `direction`.
+fn exit_distance(origin: Vec3, direction: Vec3) -> f32 {
+    let b = origin.dot(direction);
+    let d = origin.length_squared() - ATMOSPHERE_RADIUS * ATMOSPHERE_RADIUS;
+    -b + (b * b - d).max(0.0).sqrt()
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+pub fn ui(ui: &mut egui::Ui, clock: &mut Clock, atmosphere: &mut Atmosphere) {
+    let hours = clock.time as u32;
+    let minutes = (clock.time.fract() * 60.0) as u32;
+    ui.label(format!("Time of Day {hours:02}:{minutes:02}"));
+    ui.add(egui::Slider::new(&mut clock.time, 0.0..=24.0).text("Hour"));
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::Slider::new(&mut clock.speed, 0.0..=2.0)
+                .logarithmic(true)
+                .text("Hours per Second"),
+        );
+        ui.checkbox(&mut clock.paused, "Paused");
+    });
+    ui.collapsing("Atmosphere", |ui| {
+        let mut rayleigh = atmosphere.rayleigh.to_array().map(|beta| beta * 1e6);
+        ui.horizontal(|ui| {
+            for beta in &mut rayleigh {
+                ui.add(egui::DragValue::new(beta).speed(0.1).range(0.0..=100.0));
+            }
+            ui.label("Rayleigh (10⁻⁶/m)");
+        });
+        atmosphere.rayleigh = Vec3::from_array(rayleigh.map(|beta| beta * 1e-6));
+        let mut mie = atmosphere.mie * 1e6;
+        ui.add(egui::Slider::new(&mut mie, 0.0..=200.0).text("Mie (10⁻⁶/m)"));
+        atmosphere.mie = mie * 1e-6;
+        ui.add(
+            egui::Slider::new(&mut atmosphere.mie_anisotropy, 0.0..=0.99).text("Mie Anisotropy"),
+        );
+        ui.add(egui::Slider::new(&mut atmosphere.sun_intensity, 1.0..=64.0).text("Sun Intensity"));
+        ui.add(egui::Slider::new(&mut atmosphere.moon_intensity, 0.0..=2.0).text("Moon Intensity"));
+        if ui.button("Reset").clicked() {
+            *atmosphere = Atmosphere::default();
+        }
+    });
+}
+
+/// Draws the scattered sky, sun, moon and stars where no terrain was drawn.
+pub struct SkyRenderer {
+    shader: glow::Program,
+    vao: glow::VertexArray,
+}
+
+impl SkyRenderer {
+    pub fn new(gl: &glow::Context) -> Self {
+        unsafe {
+            let shader = crate::compile_shader!(gl, "shaders/sky.vert", "shaders/sky.frag");
+            // the fullscreen triangle is generated from `gl_VertexID`
+            let vao = gl.create_vertex_array().unwrap();
+            Self { shader, vao }
+        }
+    }
+
+    pub fn render(&self, gl: &glow::Context, proj: Mat4, view: Mat4, sky: &Sky) {
+        // only the rotation of the view matters for directions
+        let mut rotation = view;
+        rotation.w_axis = glam::Vec4::W;
+        let inverse_view_proj = (proj * rotation).inverse();
+
+        unsafe {
+            gl.use_program(Some(self.shader));
+            uniform(gl, self.shader, "inverse_view_proj", |location| {
+                gl.uniform_matrix_4_f32_slice(location, false, &inverse_view_proj.to_cols_array());
+            });
+            let atmosphere = &sky.atmosphere;
+            for (name, value) in [
+                ("sun_direction", sky.sun_direction),
+                ("moon_direction", sky.moon_direction),
+                ("rayleigh", atmosphere.rayleigh),
+            ] {
+                uniform(gl, self.shader, name, |location| {
+                    gl.uniform_3_f32(location, value.x, value.y, value.z);
+                });
+            }
+            for (name, value) in [
+                ("star_visibility", sky.star_visibility),
+                ("rayleigh_height", atmosphere.rayleigh_height),
+                ("mie", atmosphere.mie),
+                ("mie_height", atmosphere.mie_height),
+                ("mie_anisotropy", atmosphere.mie_anisotropy),
+                ("sun_intensity", atmosphere.sun_intensity),
+                ("moon_intensity", atmosphere.moon_intensity),
+            ] {
+                uniform(gl, self.shader, name, |location| {
+                    gl.uniform_1_f32(location, value);
+                });
+            }
+
+            // drawn at the far plane, behind everything in the depth buffer
+            gl.enable(glow::DEPTH_TEST);
+            gl.depth_func(glow::LEQUAL);
+            gl.depth_mask(false);
+            gl.bind_vertex_array(Some(self.vao));
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+            gl.depth_mask(true);
+            gl.depth_func(glow::LESS);
+        }
+    }
+}
@@ -23,8 +23,20 @@ void main() {
 } 
 "#;
 
+const FRAG_ARRAY: &str = r#"
+uniform highp sampler2DArray texture1;
+uniform float layer;
+in vec2 uv;
+out vec4 c;
+void main() {
+    c = vec4(vec3(texture(texture1, vec3(uv, layer)).r), 1.0);
+}
+"#;
+
 pub struct SpriteRenderer {
     shader: glow::Program,
+    /// Draws a layer of a `TEXTURE_2D_ARRAY`.
+    array_shader: glow::Program,
     vao: glow::VertexArray,
     _vbo: glow::Buffer,
     _ebo: glow::Buffer,
@@ -72,17 +84,21 @@ impl SpriteRenderer {
             gl.bind_buffer(glow::ARRAY_BUFFER, None);
 
             let shader = crate::compile_shader_with!(gl, VERT, FRAG);
-            gl.use_program(Some(shader));
-
-            uniform(gl, shader, "proj", |location| {
-                let w_2 = width as f32 / 2.0;
-                let h_2 = height as f32 / 2.0;
-                let proj_matrix = Mat4::orthographic_rh_gl(-w_2, w_2, -h_2, h_2, -1000.0, 1000.0);
-                gl.uniform_matrix_4_f32_slice(location, false, &proj_matrix.to_cols_array());
-            });
+            let array_shader = crate::compile_shader_with!(gl, VERT, FRAG_ARRAY);
+            for shader in [shader, array_shader] {
+                gl.use_program(Some(shader));
+                uniform(gl, shader, "proj", |location| {
+                    let w_2 = width as f32 / 2.0;
+                    let h_2 = height as f32 / 2.0;
+                    let proj_matrix =
+                        Mat4::orthographic_rh_gl(-w_2, w_2, -h_2, h_2, -1000.0, 1000.0);
+                    gl.uniform_matrix_4_f32_slice(location, false, &proj_matrix.to_cols_array());
+                });
+            }
 
             Self {
                 shader,
+                array_shader,
                 vao,
                 _vbo: vbo,
                 _ebo: ebo,
@@ -92,13 +108,16 @@ impl SpriteRenderer {
 
     pub fn resize(&self, gl: &glow::Context, width: usize, height: usize) {
         unsafe {
-            gl.use_program(Some(self.shader));
-            uniform(gl, self.shader, "proj", |location| {
-                let w_2 = width as f32 / 2.0;
-                let h_2 = height as f32 / 2.0;
-                let proj_matrix = Mat4::orthographic_rh_gl(-w_2, w_2, -h_2, h_2, -1000.0, 1000.0);
-                gl.uniform_matrix_4_f32_slice(location, false, &proj_matrix.to_cols_array());
-            });
+            for shader in [self.shader, self.array_shader] {
+                gl.use_program(Some(shader));
+                uniform(gl, shader, "proj", |location| {
+                    let w_2 = width as f32 / 2.0;
+                    let h_2 = height as f32 / 2.0;
+                    let proj_matrix =
+                        Mat4::orthographic_rh_gl(-w_2, w_2, -h_2, h_2, -1000.0, 1000.0);
+                    gl.uniform_matrix_4_f32_slice(location, false, &proj_matrix.to_cols_array());
+                });
+            }
         }
     }
 
@@ -130,4 +149,36 @@ impl SpriteRenderer {
             gl.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_INT, 0);
         }
     }
+
+    /// Like [`SpriteRenderer::render`] for one layer of a `TEXTURE_2D_ARRAY`.
+    pub fn render_layer(
+        &self,
+        gl: &glow::Context,
+        translation: Vec3,
+        scale: Vec2,
+        texture: glow::Texture,
+        layer: usize,
+        texture_width: usize,
+        texture_height: usize,
+    ) {
+        unsafe {
+            gl.use_program(Some(self.array_shader));
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(texture));
+
+            uniform(gl, self.array_shader, "layer", |location| {
+                gl.uniform_1_f32(location, layer as f32);
+            });
+            uniform(gl, self.array_shader, "model", |location| {
+                let model_matrix = Mat4::from_scale_rotation_translation(
+                    (scale * Vec2::new(texture_width as f32, texture_height as f32)).extend(1.0),
+                    Quat::default(),
+                    translation,
+                );
+                gl.uniform_matrix_4_f32_slice(location, false, &model_matrix.to_cols_array());
+            });
+
+            gl.bind_vertex_array(Some(self.vao));
+            gl.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_INT, 0);
+        }
+    }
 }
@@ -0,0 +1,106 @@
+//! Screen space ambient occlusion, darkening creases and contact points from the depth buffer.
+
+use crate::post::{Effect, Frame, Target, bind_textures, post_shader};
+use crate::shader::uniform;
+use glazer::glow::{self, HasContext};
+
+pub struct Ssao {
+    pub enabled: bool,
+    /// Blocks around each pixel that occlude it.
+    pub radius: f32,
+    /// Depth difference ignored, which keeps flat faces from occluding themselves.
+    pub bias: f32,
+    /// Power the occlusion is raised to.
+    pub intensity: f32,
+    /// Shows the occlusion alone instead of the scene.
+    pub show_occlusion: bool,
+    shader: glow::Program,
+    blur_shader: glow::Program,
+    occlusion: Option<Target>,
+}
+
+impl Ssao {
+    pub fn new(gl: &glow::Context) -> Self {
+        let shader = post_shader!(gl, "shaders/ssao.frag", ["depth"]);
+        Self {
+            enabled: true,
+            radius: 0.75,
+            bias: 0.02,
+            intensity: 1.5,
+            show_occlusion: false,
+            shader,
+            blur_shader: post_shader!(gl, "shaders/ssao_blur.frag", ["source", "occlusion"]),
+            occlusion: None,
+        }
+    }
+}
+
+impl Effect for Ssao {
+    fn name(&self) -> &'static str {
+        "SSAO"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(&mut self, gl: &glow::Context, frame: &Frame, input: glow::Texture, output: &Target) {
+        let occlusion = match self.occlusion.take() {
+            Some(target) if (target.width, target.height) == (frame.width, frame.height) => target,
+            target => {
+                if let Some(target) = target {
+                    target.delete(gl);
+                }
+                Target::new(gl, frame.width, frame.height, glow::R16F)
+            }
+        };
+
+        unsafe {
+            occlusion.bind(gl);
+            gl.use_program(Some(self.shader));
+            for (name, matrix) in [("proj", frame.proj), ("inverse_proj", frame.proj.inverse())] {
+                uniform(gl, self.shader, name, |location| {
+                    gl.uniform_matrix_4_f32_slice(location, false, &matrix.to_cols_array());
+                });
+            }
+            uniform(gl, self.shader, "radius", |location| {
+                gl.uniform_1_f32(location, self.radius);
+            });
+            uniform(gl, self.shader, "bias", |location| {
+                gl.uniform_1_f32(location, self.bias);
+            });
+            bind_textures(gl, &[frame.depth]);
+            frame.draw(gl);
+
+            output.bind(gl);
+            gl.use_program(Some(self.blur_shader));
+            uniform(gl, self.blur_shader, "texel_size", |location| {
+                gl.uniform_2_f32_slice(location, &frame.texel_size().to_array());
+            });
+            uniform(gl, self.blur_shader, "intensity", |location| {
+                gl.uniform_1_f32(location, self.intensity);
+            });
+            uniform(gl, self.blur_shader, "show_occlusion", |location| {
+                gl.uniform_1_i32(location, self.show_occlusion as i32);
+            });
+            bind_textures(gl, &[input, occlusion.texture]);
+            frame.draw(gl);
+        }
+        self.occlusion = Some(occlusion);
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let name = self.name();
+        ui.checkbox(&mut self.enabled, name);
+        ui.add_enabled_ui(self.enabled, |ui| {
+            ui.add(egui::Slider::new(&mut self.radius, 0.1..=4.0).text("Radius"));
+            ui.add(
+                egui::Slider::new(&mut self.bias, 0.0..=0.2)
+                    .logarithmic(true)
+                    .text("Bias"),
+            );
+            ui.add(egui::Slider::new(&mut self.intensity, 0.25..=4.0).text("Intensity"));
+            ui.checkbox(&mut self.show_occlusion, "Show Occlusion");
+        });
+    }
+}
@@ -0,0 +1,540 @@
+//! MagicaVoxel `.vox` files.
+//!
+//! https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt
+//!
+//! MagicaVoxel is Z-up, voxl is Y-up: a voxel at `(x, y, z)` in the file is at `(x, z, -y)` in
+//! the world.
+
+use crate::block::Block;
+use glam::{IVec3, Mat3, Vec3};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// Largest model MagicaVoxel can open along each axis.
+pub const MAX_SIZE: i32 = 256;
+
+/// Solid blocks of a `.vox` file, relative to the minimum corner of its models.
+pub struct VoxModel {
+    pub size: IVec3,
+    pub blocks: Vec<(IVec3, Block)>,
+}
+
+pub fn load(path: impl AsRef<Path>) -> io::Result<VoxModel> {
+    parse(&std::fs::read(path)?)
+}
+
+pub fn save(path: impl AsRef<Path>, model: &VoxModel) -> io::Result<()> {
+    std::fs::write(path, encode(model)?)
+}
+
+pub fn parse(bytes: &[u8]) -> io::Result<VoxModel> {
+    let mut reader = Reader { bytes, offset: 0 };
+    if reader.take(4)? != b"VOX " {
+        return Err(invalid("missing VOX header"));
+    }
+    let _version = reader.i32()?;
+
+    let mut sizes = Vec::new();
+    let mut models = Vec::new();
+    let mut palette = default_palette();
+    let mut nodes = HashMap::new();
+
+    while reader.offset < bytes.len() {
+        let id: [u8; 4] = reader.take(4)?.try_into().unwrap();
+        let content_size = reader.len()?;
+        let _children_size = reader.i32()?;
+        if &id == b"MAIN" {
+            // children follow directly
+            continue;
+        }
+
+        let content = reader.take(content_size)?;
+        let mut chunk = Reader {
+            bytes: content,
+            offset: 0,
+        };
+        match &id {
+            b"SIZE" => sizes.push(IVec3::new(chunk.i32()?, chunk.i32()?, chunk.i32()?)),
+            b"XYZI" => {
+                let count = chunk.len()?;
+                let mut voxels = Vec::with_capacity(count.min(chunk.remaining() / 4));
+                for _ in 0..count {
+                    let voxel = chunk.take(4)?;
+                    voxels.push((
+                        IVec3::new(voxel[0] as i32, voxel[1] as i32, voxel[2] as i32),
+                        voxel[3],
+                    ));
+                }
+                models.push(voxels);
+            }
+            b"RGBA" => {
+                // entry `i` of the chunk is colour index `i + 1`
+                for i in 0..255 {
+                    let rgba = chunk.take(4)?;
+                    palette[i + 1] = [rgba[0], rgba[1], rgba[2]];
+                }
+            }
+            b"nTRN" => {
+                let id = chunk.i32()?;
+                let _attributes = chunk.dict()?;
+                let child = chunk.i32()?;
+                let _reserved = chunk.i32()?;
+                let _layer = chunk.i32()?;
+                let frames = chunk.i32()?;
+                let mut translation = IVec3::ZERO;
+                let mut rotation = Mat3::IDENTITY;
+                for frame in 0..frames {
+                    let attributes = chunk.dict()?;
+                    if frame != 0 {
+                        continue;
+                    }
+                    if let Some(t) = attributes.get("_t") {
+                        let t = t
+                            .split_whitespace()
+                            .map(|v| v.parse::<i32>().map_err(|_| invalid("invalid translation")))
+                            .collect::<io::Result<Vec<_>>>()?;
+                        if t.len() != 3 {
+                            return Err(invalid("invalid translation"));
+                        }
+                        translation = IVec3::new(t[0], t[1], t[2]);
+                    }
+                    if let Some(r) = attributes.get("_r") {
+                        let r = r.parse().map_err(|_| invalid("invalid rotation"))?;
+                        rotation = parse_rotation(r)?;
+                    }
+                }
+                nodes.insert(
+                    id,
+                    Node::Transform {
+                        translation,
+                        rotation,
+                        child,
+                    },
+                );
+            }
+            b"nGRP" => {
+                let id = chunk.i32()?;
+                let _attributes = chunk.dict()?;
+                let count = chunk.i32()?;
+                let children = (0..count)
+                    .map(|_| chunk.i32())
+                    .collect::<io::Result<Vec<_>>>()?;
+                nodes.insert(id, Node::Group(children));
+            }
+            b"nSHP" => {
+                let id = chunk.i32()?;
+                let _attributes = chunk.dict()?;
+                let count = chunk.i32()?;
+                let mut shapes = Vec::new();
+                for _ in 0..count {
+                    shapes.push(chunk.len()?);
+                    let _attributes = chunk.dict()?;
+                }
+                nodes.insert(id, Node::Shape(shapes));
+            }
+            // materials, layers, cameras and notes do not affect the blocks
+            _ => {}
+        }
+    }
+
+    if sizes.len() != models.len() {
+        return Err(invalid("SIZE and XYZI chunks do not match"));
+    }
+
+    // models with their transform, files without a scene graph place every model at the origin
+    let mut instances = Vec::new();
+    if nodes.is_empty() {
+        instances.extend((0..models.len()).map(|model| (model, Mat3::IDENTITY, IVec3::ZERO)));
+    } else {
+        collect_instances(&nodes, 0, (Mat3::IDENTITY, IVec3::ZERO), &mut instances, 0)?;
+    }
+
+    let mut min = IVec3::MAX;
+    let mut max = IVec3::MIN;
+    let mut blocks = Vec::new();
+    for (model, rotation, translation) in instances {
+        let (Some(size), Some(voxels)) = (sizes.get(model), models.get(model)) else {
+            return Err(invalid("shape references a missing model"));
+        };
+        // models rotate about their center, which translations point at
+        let place = |position: IVec3| {
+            let offset = position.as_vec3() + 0.5 - size.as_vec3() / 2.0;
+            translation + (rotation * offset).floor().as_ivec3()
+        };
+        let a = to_world(place(IVec3::ZERO));
+        let b = to_world(place(*size - 1));
+        min = min.min(a.min(b));
+        max = max.max(a.max(b));
+        for (position, color) in voxels {
+            blocks.push((to_world(place(*position)), *color));
+        }
+    }
+
+    if blocks.is_empty() {
+        return Ok(VoxModel {
+            size: IVec3::ZERO,
+            blocks: Vec::new(),
+        });
+    }
+
+    let blocks = blocks
+        .into_iter()
+        .map(|(position, color)| (position - min, Block::from_color(palette[color as usize])))
+        .collect();
+    Ok(VoxModel {
+        size: max - min + 1,
+        blocks,
+    })
+}
+
+/// Writes a single model with a palette of [`Block::color`], indexed by the block.
+pub fn encode(model: &VoxModel) -> io::Result<Vec<u8>> {
+    if model.size.cmpgt(IVec3::splat(MAX_SIZE)).any() || model.size.cmplt(IVec3::ONE).any() {
+        return Err(invalid(
+            "model size must be between 1 and 256 along each axis",
+        ));
+    }
+    let size = to_file_size(model.size);
+
+    let mut size_chunk = Vec::new();
+    for v in size.to_array() {
+        size_chunk.extend_from_slice(&v.to_le_bytes());
+    }
+
+    let mut xyzi = Vec::new();
+    let voxels = model
+        .blocks
+        .iter()
+        .filter(|(_, block)| !block.is_air())
+        .collect::<Vec<_>>();
+    xyzi.extend_from_slice(&(voxels.len() as i32).to_le_bytes());
+    for (position, block) in voxels {
+        let p = to_file(*position, model.size);
+        if p.cmplt(IVec3::ZERO).any() || p.cmpge(size).any() {
+            return Err(invalid("block outside of the model"));
+        }
+        xyzi.extend_from_slice(&[p.x as u8, p.y as u8, p.z as u8, *block as u8]);
+    }
+
+    let mut rgba = Vec::with_capacity(256 * 4);
+    for i in 1..=256 {
+        let color = Block::ALL
+            .get(i)
+            .map(|block| block.color())
+            .unwrap_or([0, 0, 0]);
+        rgba.extend_from_slice(&[color[0], color[1], color[2], 255]);
+    }
+
+    let mut children = Vec::new();
+    for (id, content) in [(b"SIZE", size_chunk), (b"XYZI", xyzi), (b"RGBA", rgba)] {
+        children.extend_from_slice(id);
+        children.extend_from_slice(&(content.len() as i32).to_le_bytes());
+        children.extend_from_slice(&0i32.to_le_bytes());
+        children.extend_from_slice(&content);
+    }
+
+    let mut bytes = Vec::with_capacity(children.len() + 20);
+    bytes.extend_from_slice(b"VOX ");
+    bytes.extend_from_slice(&150i32.to_le_bytes());
+    bytes.extend_from_slice(b"MAIN");
+    bytes.extend_from_slice(&0i32.to_le_bytes());
+    bytes.extend_from_slice(&(children.len() as i32).to_le_bytes());
+    bytes.extend_from_slice(&children);
+    Ok(bytes)
+}
+
+fn to_world(p: IVec3) -> IVec3 {
+    IVec3::new(p.x, p.z, -p.y)
+}
+
+/// Inverse of [`to_world`] for a model whose minimum corner is at the origin.
+fn to_file(p: IVec3, size: IVec3) -> IVec3 {
+    IVec3::new(p.x, size.z - 1 - p.z, p.y)
+}
+
+fn to_file_size(size: IVec3) -> IVec3 {
+    IVec3::new(size.x, size.z, size.y)
+}
+
+/// Rotation of a transform node, a byte with the column of the non-zero entry of the first two
+/// rows in bits 0-1 and 2-3, and whether the entry of each row is negative in bits 4-6.
+fn parse_rotation(r: u8) -> io::Result<Mat3> {
+    let (first, second) = ((r & 3) as usize, (r >> 2 & 3) as usize);
+    if first > 2 || second > 2 || first == second {
+        return Err(invalid("invalid rotation"));
+    }
+    let columns = [first, second, 3 - first - second];
+    let rows = std::array::from_fn::<_, 3, _>(|row| {
+        let sign = if r >> (4 + row) & 1 == 1 { -1.0 } else { 1.0 };
+        Vec3::from_array(std::array::from_fn(|column| {
+            if column == columns[row] { sign } else { 0.0 }
+        }))
+    });
+    Ok(Mat3::from_cols(rows[0], rows[1], rows[2]).transpose())
+}
+
+enum Node {
+    Transform {
+        translation: IVec3,
+        rotation: Mat3,
+        child: i32,
+    },
+    Group(Vec<i32>),
+    Shape(Vec<usize>),
+}
+
+/// Models under node `id` with the rotation and translation of the nodes above them.
+fn collect_instances(
+    nodes: &HashMap<i32, Node>,
+    id: i32,
+    (rotation, translation): (Mat3, IVec3),
+    instances: &mut Vec<(usize, Mat3, IVec3)>,
+    depth: usize,
+) -> io::Result<()> {
+    if depth > 64 {
+        return Err(invalid("scene graph is too deep"));
+    }
+    match nodes.get(&id) {
+        Some(Node::Transform {
+            translation: t,
+            rotation: r,
+            child,
+        }) => {
+            let transform = (
+                rotation * *r,
+                translation + (rotation * t.as_vec3()).round().as_ivec3(),
+            );
+            collect_instances(nodes, *child, transform, instances, depth + 1)
+        }
+        Some(Node::Group(children)) => {
+            for child in children {
+                collect_instances(nodes, *child, (rotation, translation), instances, depth + 1)?;
+            }
+            Ok(())
+        }
+        Some(Node::Shape(models)) => {
+            instances.extend(models.iter().map(|model| (*model, rotation, translation)));
+            Ok(())
+        }
+        None => Err(invalid("scene graph references a missing node")),
+    }
+}
+
+/// Palette used by files without an `RGBA` chunk: a 6x6x6 colour cube without black, followed
+/// by ramps of red, green, blue and grey.
+fn default_palette() -> [[u8; 3]; 256] {
+    let mut palette = [[0; 3]; 256];
+    let mut i = 1;
+    let steps = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    for r in steps {
+        for g in steps {
+            for b in steps {
+                if i < 216 {
+                    palette[i] = [r, g, b];
+                    i += 1;
+                }
+            }
+        }
+    }
+    let ramp = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    for channel in 0..4 {
+        for v in ramp {
+            palette[i] = match channel {
+                0 => [v, 0, 0],
+                1 => [0, v, 0],
+                2 => [0, 0, v],
+                _ => [v, v, v],
+            };
+            i += 1;
+        }
+    }
+    palette
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or_else(|| invalid("unexpected end of file"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Lengths, counts and model indices, which are never negative.
+    fn len(&mut self) -> io::Result<usize> {
+        usize::try_from(self.i32()?).map_err(|_| invalid("negative length"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.len()?;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn dict(&mut self) -> io::Result<HashMap<String, String>> {
+        let len = self.i32()?;
+        (0..len)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blocks of `model` in a stable order for comparison.
+    fn sorted(model: &VoxModel) -> Vec<(IVec3, Block)> {
+        let mut blocks = model.blocks.clone();
+        blocks.sort_by_key(|(position, block)| (position.to_array(), *block as u8));
+        blocks
+    }
+
+    #[test]
+    fn round_trip_keeps_blocks() {
+        // different along every axis, with a block in each corner so the size survives
+        let size = IVec3::new(3, 5, 2);
+        let model = VoxModel {
+            size,
+            blocks: vec![
+                (IVec3::new(0, 0, 0), Block::Stone),
+                (IVec3::new(2, 0, 0), Block::Dirt),
+                (IVec3::new(0, 4, 0), Block::Grass),
+                (IVec3::new(0, 0, 1), Block::Planks),
+                (IVec3::new(2, 4, 1), Block::Bricks),
+                (IVec3::new(1, 2, 1), Block::Air),
+            ],
+        };
+
+        let parsed = parse(&encode(&model).unwrap()).unwrap();
+        assert_eq!(parsed.size, size);
+        let expected = sorted(&model)
+            .into_iter()
+            .filter(|(_, block)| !block.is_air())
+            .collect::<Vec<_>>();
+        assert_eq!(sorted(&parsed), expected);
+    }
+
+    #[test]
+    fn encode_writes_z_up() {
+        let model = VoxModel {
+            size: IVec3::new(3, 5, 2),
+            blocks: vec![(IVec3::new(1, 4, 0), Block::Stone)],
+        };
+        let bytes = encode(&model).unwrap();
+
+        // header, MAIN and the SIZE chunk header come before the size
+        let size = bytes[32..44]
+            .chunks(4)
+            .map(|v| i32::from_le_bytes(v.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(size, [3, 2, 5]);
+
+        // then the XYZI chunk header and its voxel count, world up is file z and world z
+        // points the opposite way to file y
+        assert_eq!(&bytes[44..48], b"XYZI");
+        assert_eq!(bytes[56..60], 1i32.to_le_bytes());
+        assert_eq!(bytes[60..64], [1, 1, 4, Block::Stone as u8]);
+    }
+
+    #[test]
+    fn encode_rejects_oversized_models() {
+        for size in [
+            IVec3::new(MAX_SIZE + 1, 1, 1),
+            IVec3::new(1, MAX_SIZE + 1, 1),
+            IVec3::new(1, 1, MAX_SIZE + 1),
+        ] {
+            let model = VoxModel {
+                size,
+                blocks: vec![(IVec3::ZERO, Block::Stone)],
+            };
+            assert!(encode(&model).is_err());
+        }
+        let model = VoxModel {
+            size: IVec3::splat(MAX_SIZE),
+            blocks: vec![(IVec3::splat(MAX_SIZE - 1), Block::Stone)],
+        };
+        assert!(encode(&model).is_ok());
+    }
+
+    fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&(content.len() as i32).to_le_bytes());
+        bytes.extend_from_slice(&0i32.to_le_bytes());
+        bytes.extend_from_slice(content);
+        bytes
+    }
+
+    fn ints(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// A model two voxels long along file x, with a scene graph that rotates it by `r`.
+    fn rotated(r: &str) -> Vec<u8> {
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&150i32.to_le_bytes());
+        bytes.extend(chunk(b"SIZE", &ints(&[2, 1, 1])));
+        bytes.extend(chunk(
+            b"XYZI",
+            &[ints(&[2]), vec![0, 0, 0, 1, 1, 0, 0, 2]].concat(),
+        ));
+        // transform 0 with one frame holding `_r`, above shape 1 showing model 0
+        let mut ntrn = ints(&[0, 0, 1, -1, 0, 1, 1, 2]);
+        ntrn.extend_from_slice(b"_r");
+        ntrn.extend(ints(&[r.len() as i32]));
+        ntrn.extend_from_slice(r.as_bytes());
+        bytes.extend(chunk(b"nTRN", &ntrn));
+        bytes.extend(chunk(b"nSHP", &ints(&[1, 0, 1, 0, 0])));
+        bytes
+    }
+
+    #[test]
+    fn parse_applies_rotation() {
+        // no rotation keeps the model along world x
+        let model = parse(&rotated("4")).unwrap();
+        assert_eq!(model.size, IVec3::new(2, 1, 1));
+        let [(first, a), (second, b)] = sorted(&model).try_into().unwrap();
+        assert_eq!((first, second), (IVec3::ZERO, IVec3::X));
+
+        // file x turns into file y, which points along negative world z
+        let model = parse(&rotated("17")).unwrap();
+        assert_eq!(model.size, IVec3::new(1, 1, 2));
+        assert_eq!(sorted(&model), [(IVec3::ZERO, b), (IVec3::Z, a)]);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_rotations() {
+        assert!(parse(&rotated("0")).is_err());
+        assert!(parse(&rotated("3")).is_err());
+        assert!(parse(&rotated("up")).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_negative_lengths() {
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&150i32.to_le_bytes());
+        bytes.extend_from_slice(b"XYZI");
+        bytes.extend_from_slice(&(-1i32).to_le_bytes());
+        bytes.extend_from_slice(&0i32.to_le_bytes());
+        assert!(parse(&bytes).is_err());
+    }
+}
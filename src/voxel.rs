@@ -1,273 +1,366 @@
+use crate::block::RenderLayer;
+use crate::deferred::RenderPath;
+use crate::fog::FogSettings;
+use crate::frustum::{Aabb, CullStats, Frustum};
+use crate::mesh::{Mesh, Vertex};
+use crate::point_light::{MAX_POINT_LIGHTS, PointLight};
+use crate::resource_pack::{FACE_LAYERS, ResourcePack};
 use crate::shader::uniform;
-use glam::{Mat4, Vec2, Vec3};
+use crate::shadow::{self, MAX_CASCADES, ShadowFilter, ShadowSettings};
+use glam::{Mat4, Vec3};
 use glazer::glow::{self, HasContext};
 use image::EncodableLayout;
 
-pub struct Lighting {
-    pub light_source: Vec3,
+#[derive(Clone, Copy)]
+pub struct Lighting<'a> {
+    /// Direction towards the sun or moon.
+    pub light_direction: Vec3,
     pub light_color: Vec3,
     pub ambient_brightness: f32,
+    /// Colour of light given off by blocks, see
+    /// [`Block::light_emission`](crate::block::Block::light_emission).
+    pub block_light_color: Vec3,
+    /// How much ambient occlusion darkens corners, 0 turns it off.
+    pub ao_strength: f32,
+    /// At most [`MAX_POINT_LIGHTS`], the rest are ignored.
+    pub point_lights: &'a [PointLight],
 }
 
+/// GPU buffers of a [`Mesh`], drawn by both the shadow and voxel pass.
 #[derive(Clone, Copy)]
-pub struct VoxelInstanceBuffer {
-    buffer: glow::Buffer,
-    instances: usize,
+pub struct ChunkMesh {
+    vao: glow::VertexArray,
+    vbo: glow::Buffer,
+    ebo: glow::Buffer,
+    /// Number of indices of each [`RenderLayer`], stored one after the other.
+    indices: [usize; 3],
+    bounds: Aabb,
+}
+
+impl ChunkMesh {
+    /// Draws the layers in `layers`, which are next to each other in the index buffer.
+    pub unsafe fn draw_layers(&self, gl: &glow::Context, layers: std::ops::Range<usize>) {
+        let offset = self.indices[..layers.start].iter().sum::<usize>();
+        let count = self.indices[layers].iter().sum::<usize>();
+        if count > 0 {
+            unsafe {
+                gl.bind_vertex_array(Some(self.vao));
+                gl.draw_elements(
+                    glow::TRIANGLES,
+                    count as i32,
+                    glow::UNSIGNED_INT,
+                    (offset * 4) as i32,
+                );
+            }
+        }
+    }
 }
 
 pub struct VoxelRenderer {
     // main pipeline
     voxel_shader: glow::Program,
     texture_atlas: glow::Texture,
-    voxel_vao: glow::VertexArray,
-    _voxel_vbo: glow::Buffer,
-    atlas_offsets: glow::Buffer,
+    pub texture_filtering: TextureFiltering,
+    /// [`Animation`](crate::resource_pack::Animation) of each face layer of `texture_atlas`.
+    animations: Vec<[f32; 4]>,
+    /// Filtering `texture_atlas` was last set up with.
+    applied_texture_filtering: Option<TextureFiltering>,
+    /// Largest anisotropy the driver supports, 1 without the extension.
+    pub max_anisotropy: f32,
     // shadow mapping
     shadow_framebuffer: glow::Framebuffer,
+    /// Depth of each cascade as a layer of a `TEXTURE_2D_ARRAY`.
     pub shadow_map: glow::Texture,
     shadow_shader: glow::Program,
-    shadow_vao: glow::VertexArray,
-    _shadow_vbo: glow::Buffer,
-    // shared
-    _ebo: glow::Buffer,
+    pub shadow_settings: ShadowSettings,
+    /// Cascades drawn by the last [`VoxelRenderer::shadow_pass`].
+    cascades: Vec<shadow::Cascade>,
+    /// Settings `shadow_map` was allocated with.
+    shadow_map_settings: ShadowSettings,
+    /// Depth comparison sampler for `shadow_map`.
+    shadow_sampler: glow::Sampler,
+    // lights
+    point_light_buffer: glow::Buffer,
+    // culling
+    pub frustum_culling: bool,
+    pub occlusion_culling: bool,
+    /// Draws occluded chunks tinted instead of skipping them.
+    pub show_occluded: bool,
+    pub cull_stats: CullStats,
+    /// Chunks with translucent faces left after culling, furthest first.
+    translucent: Vec<(ChunkMesh, [f32; 4])>,
+    // deferred shading
+    pub render_path: RenderPath,
+    /// Voxel shader compiled with `DEFERRED`, which writes the G-buffer instead of shading.
+    gbuffer_shader: glow::Program,
+    /// Shades the G-buffer with the same lighting as `voxel_shader`.
+    lighting_shader: glow::Program,
+    lighting_vao: glow::VertexArray,
+    /// Projection the voxel shaders draw with, see [`VoxelRenderer::set_projection`].
+    projection: Mat4,
 }
 
 impl VoxelRenderer {
-    const SHADOW_SIZE: i32 = 1024;
-
-    pub fn new(gl: &glow::Context, width: usize, height: usize, textures: &str) -> Self {
+    pub fn new(gl: &glow::Context, width: usize, height: usize, pack: &ResourcePack) -> Self {
         unsafe {
             // VOXEL
 
-            let voxel_vao = gl.create_vertex_array().unwrap();
-            let voxel_vbo = gl.create_buffer().unwrap();
-            let ebo = gl.create_buffer().unwrap();
-            let atlas_offsets = gl.create_buffer().unwrap();
+            let texture_atlas = load_texture_array(gl, pack);
+            let extensions = gl.supported_extensions();
+            let max_anisotropy = if [
+                "GL_EXT_texture_filter_anisotropic",
+                "GL_ARB_texture_filter_anisotropic",
+                "EXT_texture_filter_anisotropic",
+            ]
+            .iter()
+            .any(|extension| extensions.contains(*extension))
+            {
+                gl.get_parameter_f32(glow::MAX_TEXTURE_MAX_ANISOTROPY_EXT)
+            } else {
+                1.0
+            };
+            let shadow_settings = ShadowSettings::default();
+            let voxel_shader = Self::compile_voxel_shader(gl, &shadow_settings);
+
+            // LIGHTS
+
+            // two vec4s per light with std140 layout
+            let point_light_buffer = gl.create_buffer().unwrap();
+            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(point_light_buffer));
+            gl.buffer_data_size(
+                glow::UNIFORM_BUFFER,
+                (MAX_POINT_LIGHTS * 8 * 4) as i32,
+                glow::DYNAMIC_DRAW,
+            );
+            gl.bind_buffer(glow::UNIFORM_BUFFER, None);
 
-            gl.bind_vertex_array(Some(voxel_vao));
+            // SHADOW
 
-            gl.bind_buffer(glow::ARRAY_BUFFER, Some(voxel_vbo));
-            let data = core::slice::from_raw_parts(
-                VOXEL_VERTICES.as_ptr() as *const u8,
-                VOXEL_VERTICES.len() * 4,
-            );
-            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, data, glow::STATIC_DRAW);
+            let shadow_shader =
+                crate::compile_shader!(gl, "shaders/shadow.vert", "shaders/shadow.frag");
+            let shadow_framebuffer = gl.create_framebuffer().unwrap();
+            let shadow_map = Self::create_shadow_map(gl, &shadow_settings);
 
-            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(ebo));
-            let data =
-                core::slice::from_raw_parts(INDICES.as_ptr() as *const u8, INDICES.len() * 4);
-            gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, data, glow::STATIC_DRAW);
+            // cascades are attached one at a time in the shadow pass
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(shadow_framebuffer));
+            // tell the framebuffer to not render color data
+            gl.draw_buffer(glow::NONE);
+            gl.read_buffer(glow::NONE);
 
-            let stride = 8 * 4;
-            gl.vertex_attrib_pointer_f32(0, 3, glow::FLOAT, false, stride, 0);
-            gl.enable_vertex_attrib_array(0);
-            gl.vertex_attrib_pointer_f32(1, 3, glow::FLOAT, false, stride, 3 * 4);
-            gl.enable_vertex_attrib_array(1);
-            gl.vertex_attrib_pointer_f32(2, 2, glow::FLOAT, false, stride, 6 * 4);
-            gl.enable_vertex_attrib_array(2);
+            // the same shadow map is bound twice, once with hardware depth comparison for
+            // filtering and once as plain depth for the PCSS blocker search
+            let shadow_sampler = gl.create_sampler().unwrap();
+            for (parameter, value) in [
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_COMPARE_MODE, glow::COMPARE_REF_TO_TEXTURE),
+                (glow::TEXTURE_COMPARE_FUNC, glow::LEQUAL),
+            ] {
+                gl.sampler_parameter_i32(shadow_sampler, parameter, value as i32);
+            }
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
 
-            // gl.bind_buffer(glow::ARRAY_BUFFER, Some(atlas_offsets));
-            // gl.vertex_attrib_pointer_f32(3, 2, glow::FLOAT, false, 8, 0);
-            // gl.enable_vertex_attrib_array(3);
+            let mut renderer = Self {
+                voxel_shader,
+                texture_atlas,
+                texture_filtering: TextureFiltering::default(),
+                animations: animations(pack),
+                applied_texture_filtering: None,
+                max_anisotropy,
+                // shadow
+                shadow_framebuffer,
+                shadow_map,
+                shadow_shader,
+                shadow_settings,
+                shadow_map_settings: shadow_settings,
+                cascades: Vec::new(),
+                shadow_sampler,
+                // lights
+                point_light_buffer,
+                // culling
+                frustum_culling: true,
+                occlusion_culling: true,
+                show_occluded: false,
+                cull_stats: CullStats::default(),
+                translucent: Vec::new(),
+                // deferred shading
+                render_path: RenderPath::Forward,
+                gbuffer_shader: Self::compile_gbuffer_shader(gl),
+                lighting_shader: Self::compile_lighting_shader(gl, &shadow_settings),
+                // the fullscreen triangle is generated from `gl_VertexID`
+                lighting_vao: gl.create_vertex_array().unwrap(),
+                projection: projection(width, height),
+            };
+            renderer.resize(gl, width, height);
+            renderer
+        }
+    }
 
-            gl.bind_vertex_array(None);
-            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+    pub fn resize(&mut self, gl: &glow::Context, width: usize, height: usize) {
+        self.set_projection(gl, projection(width, height));
+    }
 
-            let (texture_atlas, texture_atlas_size) = load_image(gl, textures);
-            let voxel_shader =
-                crate::compile_shader!(gl, "shaders/voxel.vert", "shaders/voxel.frag");
-            gl.use_program(Some(voxel_shader));
-            uniform(gl, voxel_shader, "proj", |location| {
-                let proj_matrix = Mat4::perspective_rh_gl(
-                    100f32.to_radians(),
-                    width as f32 / height as f32,
-                    1.0,
-                    1_000.0,
-                );
-                gl.uniform_matrix_4_f32_slice(location, false, &proj_matrix.to_cols_array());
-            });
-            uniform(gl, voxel_shader, "atlas_size", |location| {
-                gl.uniform_2_f32(location, texture_atlas_size.0, texture_atlas_size.1);
-            });
-            uniform(gl, voxel_shader, "texture_size", |location| {
-                gl.uniform_2_f32(location, 64.0, 64.0);
-            });
-            uniform(gl, voxel_shader, "texture_atlas", |location| {
-                gl.uniform_1_i32(location, 0);
-            });
-            uniform(gl, voxel_shader, "shadow_map", |location| {
-                gl.uniform_1_i32(location, 1);
-            });
+    /// Replaces the projection of the voxel shaders, to jitter it for temporal anti-aliasing.
+    pub fn set_projection(&mut self, gl: &glow::Context, proj: Mat4) {
+        self.projection = proj;
+        unsafe {
+            for program in [self.voxel_shader, self.gbuffer_shader] {
+                gl.use_program(Some(program));
+                uniform(gl, program, "proj", |location| {
+                    gl.uniform_matrix_4_f32_slice(location, false, &proj.to_cols_array());
+                });
+            }
+        }
+    }
 
-            // SHADOW
+    /// Replaces the block textures, meshes only store layers so they do not need rebuilding.
+    pub fn set_resource_pack(&mut self, gl: &glow::Context, pack: &ResourcePack) {
+        unsafe {
+            gl.delete_texture(self.texture_atlas);
+        }
+        self.texture_atlas = load_texture_array(gl, pack);
+        self.animations = animations(pack);
+        self.applied_texture_filtering = None;
+    }
 
-            let shadow_vao = gl.create_vertex_array().unwrap();
-            let shadow_vbo = gl.create_buffer().unwrap();
+    pub fn upload_mesh(&self, gl: &glow::Context, mesh: &Mesh) -> ChunkMesh {
+        unsafe {
+            let vao = gl.create_vertex_array().unwrap();
+            let vbo = gl.create_buffer().unwrap();
+            let ebo = gl.create_buffer().unwrap();
 
-            gl.bind_vertex_array(Some(shadow_vao));
+            gl.bind_vertex_array(Some(vao));
 
-            gl.bind_buffer(glow::ARRAY_BUFFER, Some(shadow_vbo));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo));
             let data = core::slice::from_raw_parts(
-                SHADOW_VERTICES.as_ptr() as *const u8,
-                SHADOW_VERTICES.len() * 4,
+                mesh.vertices.as_ptr() as *const u8,
+                core::mem::size_of_val(mesh.vertices.as_slice()),
             );
             gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, data, glow::STATIC_DRAW);
 
             gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(ebo));
+            let indices = mesh.all_indices();
+            let data = core::slice::from_raw_parts(
+                indices.as_ptr() as *const u8,
+                core::mem::size_of_val(indices.as_slice()),
+            );
+            gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, data, glow::STATIC_DRAW);
 
-            let stride = 3 * 4;
+            let stride = core::mem::size_of::<Vertex>() as i32;
             gl.vertex_attrib_pointer_f32(0, 3, glow::FLOAT, false, stride, 0);
             gl.enable_vertex_attrib_array(0);
+            gl.vertex_attrib_pointer_f32(1, 3, glow::FLOAT, false, stride, 3 * 4);
+            gl.enable_vertex_attrib_array(1);
+            gl.vertex_attrib_pointer_f32(2, 2, glow::FLOAT, false, stride, 6 * 4);
+            gl.enable_vertex_attrib_array(2);
+            gl.vertex_attrib_pointer_f32(3, 1, glow::FLOAT, false, stride, 8 * 4);
+            gl.enable_vertex_attrib_array(3);
+            gl.vertex_attrib_pointer_f32(4, 3, glow::FLOAT, false, stride, 9 * 4);
+            gl.enable_vertex_attrib_array(4);
+            gl.vertex_attrib_pointer_f32(5, 2, glow::FLOAT, false, stride, 12 * 4);
+            gl.enable_vertex_attrib_array(5);
+            gl.vertex_attrib_pointer_f32(6, 1, glow::FLOAT, false, stride, 14 * 4);
+            gl.enable_vertex_attrib_array(6);
+            gl.vertex_attrib_pointer_f32(7, 1, glow::FLOAT, false, stride, 15 * 4);
+            gl.enable_vertex_attrib_array(7);
 
             gl.bind_vertex_array(None);
             gl.bind_buffer(glow::ARRAY_BUFFER, None);
 
-            let shadow_shader =
-                crate::compile_shader!(gl, "shaders/shadow.vert", "shaders/shadow.frag");
-            let shadow_framebuffer = gl.create_framebuffer().unwrap();
-            let shadow_map = Self::create_shadow_map(gl);
-
-            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(shadow_framebuffer));
-            gl.framebuffer_texture_2d(
-                glow::FRAMEBUFFER,
-                glow::DEPTH_ATTACHMENT,
-                glow::TEXTURE_2D,
-                Some(shadow_map),
-                0,
-            );
-            // tell the framebuffer to not render color data
-            gl.draw_buffer(glow::NONE);
-            gl.read_buffer(glow::NONE);
-            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-
-            Self {
-                voxel_shader,
-                texture_atlas,
-                voxel_vao,
-                _voxel_vbo: voxel_vbo,
-                atlas_offsets,
-                // shared
-                shadow_framebuffer,
-                shadow_map,
-                shadow_shader,
-                shadow_vao,
-                _shadow_vbo: shadow_vbo,
-                //
-                _ebo: ebo,
+            ChunkMesh {
+                vao,
+                vbo,
+                ebo,
+                indices: mesh.indices.each_ref().map(Vec::len),
+                bounds: mesh.bounds().unwrap_or(Aabb {
+                    min: Vec3::ZERO,
+                    max: Vec3::ZERO,
+                }),
             }
         }
     }
 
-    pub fn resize(&self, gl: &glow::Context, width: usize, height: usize) {
+    /// Replaces the translucent indices of `mesh`, after sorting them for a new camera position.
+    pub fn update_translucent(&self, gl: &glow::Context, mesh: ChunkMesh, indices: &[u32]) {
+        let translucent = RenderLayer::Translucent as usize;
+        debug_assert_eq!(indices.len(), mesh.indices[translucent]);
+        let offset = mesh.indices[..translucent].iter().sum::<usize>();
         unsafe {
-            gl.use_program(Some(self.voxel_shader));
-            uniform(gl, self.voxel_shader, "proj", |location| {
-                let proj_matrix = Mat4::perspective_rh_gl(
-                    90f32.to_radians(),
-                    width as f32 / height as f32,
-                    0.1,
-                    1_000.0,
-                );
-                gl.uniform_matrix_4_f32_slice(location, false, &proj_matrix.to_cols_array());
-            });
+            // the index buffer is bound through the vertex array
+            gl.bind_vertex_array(Some(mesh.vao));
+            let data =
+                core::slice::from_raw_parts(indices.as_ptr() as *const u8, size_of_val(indices));
+            gl.buffer_sub_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, (offset * 4) as i32, data);
+            gl.bind_vertex_array(None);
         }
     }
 
-    pub fn generate_translation_buffer(
-        &self,
-        gl: &glow::Context,
-        translations: &[Vec3],
-    ) -> VoxelInstanceBuffer {
+    pub fn delete_mesh(&self, gl: &glow::Context, mesh: ChunkMesh) {
         unsafe {
-            let buffer = gl.create_buffer().unwrap();
-            gl.bind_buffer(glow::COPY_WRITE_BUFFER, Some(buffer));
-            let data = core::slice::from_raw_parts(
-                translations.as_ptr() as *const u8,
-                core::mem::size_of_val(translations),
-            );
-            gl.buffer_data_u8_slice(glow::COPY_WRITE_BUFFER, data, glow::STATIC_DRAW);
-            VoxelInstanceBuffer {
-                buffer,
-                instances: translations.len(),
-            }
+            gl.delete_vertex_array(mesh.vao);
+            gl.delete_buffer(mesh.vbo);
+            gl.delete_buffer(mesh.ebo);
         }
     }
 
-    pub fn render_pass<'a>(
-        &self,
+    /// Draws the cascades of the view into the shadow maps, reallocating them and recompiling
+    /// the shaders that sample them if their settings changed.
+    pub fn shadow_pass(
+        &mut self,
         gl: &glow::Context,
         width: usize,
         height: usize,
-        lighting: Lighting,
         view: Mat4,
-        fog_near: f32,
-        fog_far: f32,
-        translations_for_shadow_pass: impl Iterator<Item = VoxelInstanceBuffer>,
-        translations_and_atlas_uvs: impl Iterator<Item = (VoxelInstanceBuffer, &'a [[Vec2; 6]])>,
+        // direction towards the sun or moon
+        light_direction: Vec3,
+        meshes: impl Iterator<Item = ChunkMesh>,
+        // mesh drawn into the shadow maps moved by an offset, for clouds
+        moving_shadow_caster: Option<(ChunkMesh, Vec3)>,
     ) {
-        // write uniform data
-        unsafe {
-            gl.use_program(Some(self.voxel_shader));
-            uniform(gl, self.voxel_shader, "view", |location| {
-                gl.uniform_matrix_4_f32_slice(location, false, &view.to_cols_array());
-            });
-
-            uniform(gl, self.voxel_shader, "light_source", |location| {
-                gl.uniform_3_f32(
-                    location,
-                    lighting.light_source.x,
-                    lighting.light_source.y,
-                    lighting.light_source.z,
-                );
-            });
-            uniform(gl, self.voxel_shader, "light_color", |location| {
-                gl.uniform_3_f32(
-                    location,
-                    lighting.light_color.x,
-                    lighting.light_color.y,
-                    lighting.light_color.z,
-                );
-            });
-            uniform(gl, self.voxel_shader, "ambient_brightness", |location| {
-                gl.uniform_1_f32(location, lighting.ambient_brightness);
-            });
-
-            uniform(gl, self.voxel_shader, "fog_near", |location| {
-                gl.uniform_1_f32(location, fog_near);
-            });
-            uniform(gl, self.voxel_shader, "fog_far", |location| {
-                gl.uniform_1_f32(location, fog_far);
-            });
-
-            // let camera_translation = view.w_axis.xyz();
-            let size = Self::SHADOW_SIZE as f32 / 4.0;
-            let proj = Mat4::orthographic_rh_gl(-size, size, -size, size, 1.0, 1_000.0);
-            let view = Mat4::look_at_rh(
-                // TODO: how the hell do I move this?
-                // lighting.light_source + camera_translation,
-                // Vec3::ZERO + camera_translation,
-                lighting.light_source,
-                Vec3::ZERO,
-                Vec3::Y,
-            );
-            let light_space = proj.mul_mat4(&view);
-
-            uniform(gl, self.voxel_shader, "light_space", |location| {
-                gl.uniform_matrix_4_f32_slice(location, false, &light_space.to_cols_array());
-            });
+        // the shadow filter is chosen with shader defines
+        let settings = self.shadow_settings;
+        if settings.defines() != self.shadow_map_settings.defines() {
+            unsafe {
+                gl.delete_program(self.voxel_shader);
+                gl.delete_program(self.lighting_shader);
+            }
+            self.voxel_shader = Self::compile_voxel_shader(gl, &settings);
+            self.lighting_shader = Self::compile_lighting_shader(gl, &settings);
+            self.set_projection(gl, self.projection);
+        }
 
-            gl.use_program(Some(self.shadow_shader));
-            uniform(gl, self.shadow_shader, "light_space", |location| {
-                gl.uniform_matrix_4_f32_slice(location, false, &light_space.to_cols_array());
-            });
+        // shadow maps are only reallocated when their size changes
+        if (settings.cascades, settings.resolution)
+            != (
+                self.shadow_map_settings.cascades,
+                self.shadow_map_settings.resolution,
+            )
+        {
+            unsafe {
+                gl.delete_texture(self.shadow_map);
+            }
+            self.shadow_map = Self::create_shadow_map(gl, &settings);
         }
+        self.shadow_map_settings = settings;
+
+        self.cascades = shadow::cascades(
+            &settings,
+            view,
+            FOV,
+            width as f32 / height as f32,
+            NEAR,
+            light_direction,
+        );
+        self.cull_stats.shadow_drawn = 0;
+        self.cull_stats.shadow_culled = 0;
 
-        // shadow pass
         unsafe {
             // render to depth buffer with the target resolution
-            gl.viewport(0, 0, Self::SHADOW_SIZE, Self::SHADOW_SIZE);
+            let resolution = self.shadow_map_settings.resolution;
+            gl.viewport(0, 0, resolution, resolution);
             gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.shadow_framebuffer));
-            gl.clear(glow::DEPTH_BUFFER_BIT);
 
             gl.enable(glow::DEPTH_TEST);
             gl.depth_func(glow::LESS);
@@ -275,229 +368,637 @@ impl VoxelRenderer {
             gl.cull_face(glow::BACK);
             gl.front_face(glow::CCW);
             gl.use_program(Some(self.shadow_shader));
-            gl.bind_vertex_array(Some(self.shadow_vao));
-
-            for instances in translations_for_shadow_pass {
-                gl.bind_buffer(glow::ARRAY_BUFFER, Some(instances.buffer));
-                gl.vertex_attrib_pointer_f32(1, 3, glow::FLOAT, false, 12, 0);
-                gl.vertex_attrib_divisor(1, 1);
-                gl.enable_vertex_attrib_array(1);
+            uniform(gl, self.shadow_shader, "offset", |location| {
+                gl.uniform_3_f32(location, 0.0, 0.0, 0.0);
+            });
 
-                gl.draw_elements_instanced(
-                    glow::TRIANGLES,
-                    36,
-                    glow::UNSIGNED_INT,
+            let meshes = meshes.collect::<Vec<_>>();
+            for (layer, cascade) in self.cascades.iter().enumerate() {
+                gl.framebuffer_texture_layer(
+                    glow::FRAMEBUFFER,
+                    glow::DEPTH_ATTACHMENT,
+                    Some(self.shadow_map),
                     0,
-                    instances.instances as i32,
+                    layer as i32,
                 );
+                gl.clear(glow::DEPTH_BUFFER_BIT);
+                uniform(gl, self.shadow_shader, "light_space", |location| {
+                    gl.uniform_matrix_4_f32_slice(
+                        location,
+                        false,
+                        &cascade.light_space.to_cols_array(),
+                    );
+                });
+
+                // the near plane of each cascade is pulled back to include casters outside the view
+                let frustum = Frustum::from_matrix(cascade.light_space);
+                for mesh in &meshes {
+                    if self.frustum_culling && !frustum.intersects(&mesh.bounds) {
+                        self.cull_stats.shadow_culled += 1;
+                        continue;
+                    }
+                    self.cull_stats.shadow_drawn += 1;
+                    // translucent blocks let the light through
+                    mesh.draw_layers(
+                        gl,
+                        RenderLayer::Opaque as usize..RenderLayer::Translucent as usize,
+                    );
+                }
+                // its bounds do not include the offset, so it is never culled
+                if let Some((mesh, offset)) = moving_shadow_caster {
+                    uniform(gl, self.shadow_shader, "offset", |location| {
+                        gl.uniform_3_f32(location, offset.x, offset.y, offset.z);
+                    });
+                    mesh.draw_layers(gl, 0..3);
+                    uniform(gl, self.shadow_shader, "offset", |location| {
+                        gl.uniform_3_f32(location, 0.0, 0.0, 0.0);
+                    });
+                }
             }
 
-            // finish render pass and return viewport to the screen resolution
+            // the render graph binds the target of the next pass
             gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-            gl.viewport(0, 0, width as i32, height as i32);
+            gl.disable(glow::CULL_FACE);
+        }
+    }
+
+    /// Draws the opaque and cutout faces into the bound framebuffer after clearing it, shaded
+    /// with the shadow maps of the last [`VoxelRenderer::shadow_pass`] or written as a G-buffer
+    /// for [`VoxelRenderer::lighting_pass`] with the deferred path.
+    pub fn voxel_pass(
+        &mut self,
+        gl: &glow::Context,
+        width: usize,
+        height: usize,
+        lighting: Lighting,
+        view: Mat4,
+        // seconds since start, for animated textures
+        time: f32,
+        fog: &FogSettings,
+        // blocks, linear fog is relative to it
+        view_distance: f32,
+        fog_color: Vec3,
+        // meshes paired with whether occlusion culling hid them
+        meshes: impl Iterator<Item = (ChunkMesh, bool)>,
+    ) {
+        if self.applied_texture_filtering != Some(self.texture_filtering) {
+            self.texture_filtering
+                .apply(gl, self.texture_atlas, self.max_anisotropy);
+            self.applied_texture_filtering = Some(self.texture_filtering);
+        }
+
+        // write uniform data
+        unsafe {
+            for program in [self.voxel_shader, self.gbuffer_shader] {
+                gl.use_program(Some(program));
+                uniform(gl, program, "view", |location| {
+                    gl.uniform_matrix_4_f32_slice(location, false, &view.to_cols_array());
+                });
+                uniform(gl, program, "time", |location| {
+                    gl.uniform_1_f32(location, time);
+                });
+                uniform(gl, program, "animations", |location| {
+                    gl.uniform_4_f32_slice(location, self.animations.as_flattened());
+                });
+            }
+
+            let point_lights =
+                &lighting.point_lights[..lighting.point_lights.len().min(MAX_POINT_LIGHTS)];
+            let data = point_lights
+                .iter()
+                .flat_map(|light| {
+                    [
+                        light.position.extend(light.radius).to_array(),
+                        light.color.extend(light.attenuation).to_array(),
+                    ]
+                })
+                .flatten()
+                .flat_map(f32::to_ne_bytes)
+                .collect::<Vec<_>>();
+            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(self.point_light_buffer));
+            gl.buffer_sub_data_u8_slice(glow::UNIFORM_BUFFER, 0, &data);
+            gl.bind_buffer(glow::UNIFORM_BUFFER, None);
+            gl.bind_buffer_base(glow::UNIFORM_BUFFER, 0, Some(self.point_light_buffer));
+
+            let camera_position = view.inverse().w_axis.truncate();
+            let light_spaces = self
+                .cascades
+                .iter()
+                .flat_map(|cascade| cascade.light_space.to_cols_array())
+                .collect::<Vec<_>>();
+            let cascade_far = self
+                .cascades
+                .iter()
+                .map(|cascade| cascade.far)
+                .collect::<Vec<_>>();
+            // world units of depth per world unit across each cascade
+            let depth_scale = self
+                .cascades
+                .iter()
+                .map(|cascade| cascade.depth / cascade.width)
+                .collect::<Vec<_>>();
+
+            // both shade with `lighting.glsl`
+            for program in [self.voxel_shader, self.lighting_shader] {
+                gl.use_program(Some(program));
+                for (name, value) in [
+                    ("light_direction", lighting.light_direction),
+                    ("light_color", lighting.light_color),
+                    ("block_light_color", lighting.block_light_color),
+                    ("fog_color", fog_color),
+                    ("camera_position", camera_position),
+                ] {
+                    uniform(gl, program, name, |location| {
+                        gl.uniform_3_f32(location, value.x, value.y, value.z);
+                    });
+                }
+                uniform(gl, program, "point_light_count", |location| {
+                    gl.uniform_1_i32(location, point_lights.len() as i32);
+                });
+                uniform(gl, program, "fog_mode", |location| {
+                    gl.uniform_1_i32(location, fog.mode as i32);
+                });
+                for (name, value) in [
+                    ("ambient_brightness", lighting.ambient_brightness),
+                    ("ao_strength", lighting.ao_strength),
+                    ("fog_start", fog.start * view_distance),
+                    ("fog_end", fog.end * view_distance),
+                    ("fog_density", fog.density),
+                    (
+                        "height_fog_density",
+                        if fog.height_fog {
+                            fog.height_density
+                        } else {
+                            0.0
+                        },
+                    ),
+                    ("fog_height", fog.height),
+                    ("fog_height_falloff", fog.height_falloff),
+                    (
+                        "shadow_texel_size",
+                        1.0 / self.shadow_map_settings.resolution as f32,
+                    ),
+                ] {
+                    uniform(gl, program, name, |location| {
+                        gl.uniform_1_f32(location, value);
+                    });
+                }
+
+                uniform(gl, program, "light_space", |location| {
+                    gl.uniform_matrix_4_f32_slice(location, false, &light_spaces);
+                });
+                uniform(gl, program, "cascade_far", |location| {
+                    gl.uniform_1_f32_slice(location, &cascade_far);
+                });
+                uniform(gl, program, "cascade_count", |location| {
+                    gl.uniform_1_i32(location, self.cascades.len() as i32);
+                });
+                if self.shadow_map_settings.filter == ShadowFilter::Pcss {
+                    uniform(gl, program, "cascade_depth_scale", |location| {
+                        gl.uniform_1_f32_slice(location, &depth_scale);
+                    });
+                    uniform(gl, program, "light_size", |location| {
+                        gl.uniform_1_f32(location, self.shadow_map_settings.light_size);
+                    });
+                }
+            }
         }
 
-        // voxel pass
+        let shader = match self.render_path {
+            RenderPath::Forward => self.voxel_shader,
+            RenderPath::Deferred => self.gbuffer_shader,
+        };
         unsafe {
-            gl.clear_color(113.0 / 255.0, 197.0 / 255.0, 231.0 / 255.0, 1.0);
+            if self.render_path == RenderPath::Deferred {
+                gl.clear_color(0.0, 0.0, 0.0, 0.0);
+            } else {
+                gl.clear_color(fog_color.x, fog_color.y, fog_color.z, 1.0);
+            }
             gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+            gl.enable(glow::DEPTH_TEST);
+            gl.depth_func(glow::LESS);
+            gl.enable(glow::CULL_FACE);
+            gl.cull_face(glow::BACK);
+            gl.front_face(glow::CCW);
+
+            self.cull_stats.drawn = 0;
+            self.cull_stats.culled = 0;
+            self.cull_stats.occluded = 0;
+
+            gl.use_program(Some(shader));
+            self.bind_textures(gl);
+
+            let frustum = Frustum::from_matrix(projection(width, height) * view);
+            // meshes left after culling with the colour the debug view blends over them
+            let mut visible = meshes
+                .filter_map(|(mesh, occluded)| {
+                    if self.frustum_culling && !frustum.intersects(&mesh.bounds) {
+                        self.cull_stats.culled += 1;
+                        return None;
+                    }
+                    if occluded {
+                        self.cull_stats.occluded += 1;
+                        return self.show_occluded.then_some((mesh, [1.0, 0.0, 0.0, 0.6]));
+                    }
+                    self.cull_stats.drawn += 1;
+                    Some((mesh, [0.0; 4]))
+                })
+                .collect::<Vec<_>>();
+
+            // cutout faces come after opaque ones, so those can skip the alpha test
+            for layer in [RenderLayer::Opaque, RenderLayer::Cutout] {
+                uniform(gl, shader, "alpha_cutoff", |location| {
+                    gl.uniform_1_f32(
+                        location,
+                        if layer == RenderLayer::Cutout {
+                            0.5
+                        } else {
+                            0.0
+                        },
+                    );
+                });
+                for (mesh, debug_color) in &visible {
+                    if mesh.indices[layer as usize] == 0 {
+                        continue;
+                    }
+                    uniform(gl, shader, "debug_color", |location| {
+                        gl.uniform_4_f32_slice(location, debug_color);
+                    });
+                    let layer = layer as usize;
+                    mesh.draw_layers(gl, layer..layer + 1);
+                }
+            }
 
+            // translucent chunks are drawn furthest first by `translucent_pass`
+            let eye = view.inverse().w_axis.truncate();
+            let distance = |mesh: &ChunkMesh| {
+                ((mesh.bounds.min + mesh.bounds.max) / 2.0).distance_squared(eye)
+            };
+            visible.retain(|(mesh, _)| mesh.indices[RenderLayer::Translucent as usize] > 0);
+            visible.sort_by(|(a, _), (b, _)| distance(b).total_cmp(&distance(a)));
+            self.translucent = visible;
+
+            gl.use_program(None);
+            gl.bind_sampler(2, None);
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+            gl.bind_vertex_array(None);
+            gl.disable(glow::CULL_FACE);
+        }
+    }
+
+    /// Shades the G-buffer written by [`VoxelRenderer::voxel_pass`] into the bound framebuffer,
+    /// with its depth for what is drawn after.
+    pub fn lighting_pass(
+        &self,
+        gl: &glow::Context,
+        view: Mat4,
+        fog_color: Vec3,
+        // textures of `deferred::GBUFFER`
+        gbuffer: [glow::Texture; 4],
+    ) {
+        unsafe {
+            gl.clear_color(fog_color.x, fog_color.y, fog_color.z, 1.0);
+            gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+            self.bind_textures(gl);
+            // wireframes only apply to the faces written to the G-buffer
+            gl.polygon_mode(glow::FRONT_AND_BACK, glow::FILL);
+            gl.disable(glow::CULL_FACE);
+            gl.enable(glow::DEPTH_TEST);
+            gl.depth_func(glow::ALWAYS);
+            gl.use_program(Some(self.lighting_shader));
+            for (name, matrix) in [
+                ("inverse_proj", self.projection.inverse()),
+                ("inverse_view", view.inverse()),
+            ] {
+                uniform(gl, self.lighting_shader, name, |location| {
+                    gl.uniform_matrix_4_f32_slice(location, false, &matrix.to_cols_array());
+                });
+            }
+            for (unit, texture) in (GBUFFER_UNIT..).zip(gbuffer) {
+                gl.active_texture(glow::TEXTURE0 + unit);
+                gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            }
+            gl.bind_vertex_array(Some(self.lighting_vao));
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+            for unit in (GBUFFER_UNIT..).take(gbuffer.len()) {
+                gl.active_texture(glow::TEXTURE0 + unit);
+                gl.bind_texture(glow::TEXTURE_2D, None);
+            }
+            gl.active_texture(glow::TEXTURE0);
+            gl.depth_func(glow::LESS);
+            gl.use_program(None);
+            gl.bind_sampler(2, None);
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+            gl.bind_vertex_array(None);
+        }
+    }
+
+    /// Blends the translucent faces of the chunks drawn by the last [`VoxelRenderer::voxel_pass`]
+    /// over the frame, after everything opaque and the sky.
+    pub fn translucent_pass(&self, gl: &glow::Context) {
+        if self.translucent.is_empty() {
+            return;
+        }
+        unsafe {
             gl.use_program(Some(self.voxel_shader));
+            self.bind_textures(gl);
+            uniform(gl, self.voxel_shader, "alpha_cutoff", |location| {
+                gl.uniform_1_f32(location, 0.0);
+            });
+
+            // faces are sorted instead of depth tested against each other, and water is seen
+            // from below as well
+            gl.enable(glow::DEPTH_TEST);
+            gl.depth_func(glow::LESS);
+            gl.depth_mask(false);
+            gl.enable(glow::BLEND);
+            gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+
+            let translucent = RenderLayer::Translucent as usize;
+            for (mesh, debug_color) in &self.translucent {
+                uniform(gl, self.voxel_shader, "debug_color", |location| {
+                    gl.uniform_4_f32_slice(location, debug_color);
+                });
+                mesh.draw_layers(gl, translucent..translucent + 1);
+            }
+
+            gl.disable(glow::BLEND);
+            gl.depth_mask(true);
+            gl.use_program(None);
+            gl.bind_sampler(2, None);
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+            gl.bind_vertex_array(None);
+        }
+    }
+
+    unsafe fn bind_textures(&self, gl: &glow::Context) {
+        unsafe {
             gl.active_texture(glow::TEXTURE0);
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.texture_atlas));
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(self.texture_atlas));
             gl.active_texture(glow::TEXTURE1);
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.shadow_map));
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(self.shadow_map));
+            gl.active_texture(glow::TEXTURE2);
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(self.shadow_map));
+            gl.bind_sampler(2, Some(self.shadow_sampler));
             gl.active_texture(glow::TEXTURE0);
-            gl.bind_vertex_array(Some(self.voxel_vao));
-
-            for (instances, atlas_uvs) in translations_and_atlas_uvs {
-                gl.bind_buffer(glow::ARRAY_BUFFER, Some(instances.buffer));
-                gl.vertex_attrib_pointer_f32(4, 3, glow::FLOAT, false, 12, 0);
-                gl.vertex_attrib_divisor(4, 1);
-                gl.enable_vertex_attrib_array(4);
-
-                gl.bind_buffer(glow::COPY_WRITE_BUFFER, Some(self.atlas_offsets));
-                let data = core::slice::from_raw_parts(
-                    atlas_uvs.as_ptr() as *const u8,
-                    core::mem::size_of_val(atlas_uvs),
-                );
-                gl.buffer_data_u8_slice(glow::COPY_WRITE_BUFFER, data, glow::DYNAMIC_DRAW);
+        }
+    }
 
-                gl.draw_elements_instanced(
-                    glow::TRIANGLES,
-                    36,
-                    glow::UNSIGNED_INT,
-                    0,
-                    instances.instances as i32,
-                );
+    /// Compiles the voxel shader with the shadow filter of `settings` and sets the uniforms that
+    /// never change.
+    fn compile_voxel_shader(gl: &glow::Context, settings: &ShadowSettings) -> glow::Program {
+        unsafe {
+            let voxel_shader = crate::compile_shader_with!(
+                gl,
+                voxel_vertex_source(),
+                format!(
+                    "{}{}{}",
+                    settings.defines(),
+                    include_str!("shaders/lighting.glsl"),
+                    include_str!("shaders/voxel.frag")
+                )
+            );
+            gl.use_program(Some(voxel_shader));
+            uniform(gl, voxel_shader, "texture_atlas", |location| {
+                gl.uniform_1_i32(location, 0);
+            });
+            Self::bind_lighting(gl, voxel_shader, settings);
+            voxel_shader
+        }
+    }
+
+    /// Compiles the voxel shader that writes the G-buffer, which does no lighting.
+    fn compile_gbuffer_shader(gl: &glow::Context) -> glow::Program {
+        unsafe {
+            let gbuffer_shader = crate::compile_shader_with!(
+                gl,
+                voxel_vertex_source(),
+                format!("#define DEFERRED\n{}", include_str!("shaders/voxel.frag"))
+            );
+            gl.use_program(Some(gbuffer_shader));
+            uniform(gl, gbuffer_shader, "texture_atlas", |location| {
+                gl.uniform_1_i32(location, 0);
+            });
+            gbuffer_shader
+        }
+    }
+
+    /// Compiles the deferred lighting pass with the shadow filter of `settings`.
+    fn compile_lighting_shader(gl: &glow::Context, settings: &ShadowSettings) -> glow::Program {
+        unsafe {
+            let lighting_shader = crate::compile_shader_with!(
+                gl,
+                include_str!("shaders/post.vert"),
+                format!(
+                    "{}{}{}",
+                    settings.defines(),
+                    include_str!("shaders/lighting.glsl"),
+                    include_str!("shaders/deferred.frag")
+                )
+            );
+            gl.use_program(Some(lighting_shader));
+            for (unit, name) in
+                (GBUFFER_UNIT..).zip(["albedo_emission", "normal", "light_ao", "depth"])
+            {
+                uniform(gl, lighting_shader, name, |location| {
+                    gl.uniform_1_i32(location, unit as i32);
+                });
             }
+            Self::bind_lighting(gl, lighting_shader, settings);
+            lighting_shader
+        }
+    }
 
-            gl.use_program(None);
-            gl.bind_texture(glow::TEXTURE_2D, None);
-            gl.bind_vertex_array(None);
-            gl.disable(glow::CULL_FACE);
+    /// Points the shadow maps and point lights of a shader that includes `lighting.glsl` at where
+    /// [`VoxelRenderer::bind_textures`] and the passes bind them.
+    unsafe fn bind_lighting(gl: &glow::Context, program: glow::Program, settings: &ShadowSettings) {
+        unsafe {
+            if settings.filter != ShadowFilter::Pcf {
+                uniform(gl, program, "shadow_map", |location| {
+                    gl.uniform_1_i32(location, 1);
+                });
+            }
+            if settings.filter != ShadowFilter::None {
+                uniform(gl, program, "shadow_map_compare", |location| {
+                    gl.uniform_1_i32(location, 2);
+                });
+            }
+            match gl.get_uniform_block_index(program, "PointLights") {
+                Some(index) => gl.uniform_block_binding(program, index, 0),
+                None => glazer::log!("[ERROR] failed to find uniform block PointLights"),
+            }
         }
     }
 
-    fn create_shadow_map(gl: &glow::Context) -> glow::Texture {
+    fn create_shadow_map(gl: &glow::Context, settings: &ShadowSettings) -> glow::Texture {
         unsafe {
             let shadow_map = gl.create_texture().unwrap();
-            gl.bind_texture(glow::TEXTURE_2D, Some(shadow_map));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(shadow_map));
+            gl.tex_image_3d(
+                glow::TEXTURE_2D_ARRAY,
                 0,
-                glow::DEPTH_COMPONENT as i32,
-                Self::SHADOW_SIZE,
-                Self::SHADOW_SIZE,
+                glow::DEPTH_COMPONENT32F as i32,
+                settings.resolution,
+                settings.resolution,
+                settings.cascades.clamp(1, MAX_CASCADES) as i32,
                 0,
                 glow::DEPTH_COMPONENT,
                 glow::FLOAT,
                 glow::PixelUnpackData::Slice(None),
             );
             gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
+                glow::TEXTURE_2D_ARRAY,
                 glow::TEXTURE_MIN_FILTER,
                 glow::NEAREST as i32,
             );
             gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
+                glow::TEXTURE_2D_ARRAY,
                 glow::TEXTURE_MAG_FILTER,
                 glow::NEAREST as i32,
             );
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D_ARRAY,
+                glow::TEXTURE_WRAP_S,
+                glow::CLAMP_TO_EDGE as i32,
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D_ARRAY,
+                glow::TEXTURE_WRAP_T,
+                glow::CLAMP_TO_EDGE as i32,
+            );
             shadow_map
         }
     }
 }
 
-fn load_image(gl: &glow::Context, path: &str) -> (glow::Texture, (f32, f32)) {
-    let image = image::open(path).unwrap();
-    let width = image.width();
-    let height = image.height();
-    let rgb = image.to_rgb8();
-    let bytes = rgb.as_bytes();
+/// Vertical field of view of the camera.
+pub const FOV: f32 = 90f32.to_radians();
+pub const NEAR: f32 = 0.1;
+/// Far enough for the largest view distance.
+pub const FAR: f32 = 4_096.0;
+
+/// First texture unit of the G-buffer in the deferred lighting pass, after the atlas and shadow
+/// maps.
+const GBUFFER_UNIT: u32 = 3;
+
+fn voxel_vertex_source() -> String {
+    format!(
+        "#define TEXTURE_LAYERS {FACE_LAYERS}\n{}",
+        include_str!("shaders/voxel.vert")
+    )
+}
+
+pub fn projection(width: usize, height: usize) -> Mat4 {
+    Mat4::perspective_rh_gl(FOV, width as f32 / height as f32, NEAR, FAR)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureFiltering {
+    /// Blend between smaller copies of each tile in the distance instead of shimmering.
+    pub mipmaps: bool,
+    /// Samples along the view direction on faces seen at a steep angle, 1 turns it off.
+    pub anisotropy: f32,
+}
 
-    let texture = unsafe {
+impl Default for TextureFiltering {
+    fn default() -> Self {
+        Self {
+            mipmaps: true,
+            anisotropy: 8.0,
+        }
+    }
+}
+
+impl TextureFiltering {
+    fn apply(&self, gl: &glow::Context, texture: glow::Texture, max_anisotropy: f32) {
+        let min_filter = if self.mipmaps {
+            glow::NEAREST_MIPMAP_LINEAR
+        } else {
+            glow::NEAREST
+        };
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(texture));
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D_ARRAY,
+                glow::TEXTURE_MIN_FILTER,
+                min_filter as i32,
+            );
+            if max_anisotropy > 1.0 {
+                gl.tex_parameter_f32(
+                    glow::TEXTURE_2D_ARRAY,
+                    glow::TEXTURE_MAX_ANISOTROPY_EXT,
+                    self.anisotropy.clamp(1.0, max_anisotropy),
+                );
+            }
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+        }
+    }
+}
+
+pub fn texture_ui(ui: &mut egui::Ui, voxel_renderer: &mut VoxelRenderer) {
+    let filtering = &mut voxel_renderer.texture_filtering;
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut filtering.mipmaps, "Mipmaps");
+        ui.add_enabled(
+            voxel_renderer.max_anisotropy > 1.0,
+            egui::Slider::new(
+                &mut filtering.anisotropy,
+                1.0..=voxel_renderer.max_anisotropy.max(1.0),
+            )
+            .text("Anisotropy"),
+        );
+    });
+}
+
+fn animations(pack: &ResourcePack) -> Vec<[f32; 4]> {
+    pack.animations
+        .iter()
+        .map(|animation| animation.to_array())
+        .collect()
+}
+
+/// Uploads every layer and animation frame of a resource pack to a `TEXTURE_2D_ARRAY`, so
+/// mipmaps and repeating textures never sample a neighbouring tile.
+fn load_texture_array(gl: &glow::Context, pack: &ResourcePack) -> glow::Texture {
+    let bytes = pack
+        .layers
+        .iter()
+        .chain(&pack.frames)
+        .flat_map(|layer| layer.as_bytes())
+        .copied()
+        .collect::<Vec<_>>();
+
+    unsafe {
         let texture = gl.create_texture().unwrap();
-        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
-        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
-        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+        gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(texture));
+        gl.tex_image_3d(
+            glow::TEXTURE_2D_ARRAY,
+            0,
+            glow::SRGB8_ALPHA8 as i32,
+            pack.tile_size as i32,
+            pack.tile_size as i32,
+            (pack.layers.len() + pack.frames.len()) as i32,
+            0,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelUnpackData::Slice(Some(&bytes)),
+        );
+        gl.generate_mipmap(glow::TEXTURE_2D_ARRAY);
         gl.tex_parameter_i32(
-            glow::TEXTURE_2D,
-            glow::TEXTURE_MIN_FILTER,
-            glow::NEAREST as i32,
+            glow::TEXTURE_2D_ARRAY,
+            glow::TEXTURE_WRAP_S,
+            glow::REPEAT as i32,
+        );
+        gl.tex_parameter_i32(
+            glow::TEXTURE_2D_ARRAY,
+            glow::TEXTURE_WRAP_T,
+            glow::REPEAT as i32,
         );
         gl.tex_parameter_i32(
-            glow::TEXTURE_2D,
+            glow::TEXTURE_2D_ARRAY,
             glow::TEXTURE_MAG_FILTER,
             glow::NEAREST as i32,
         );
-
-        gl.tex_image_2d(
-            glow::TEXTURE_2D,
-            0,
-            glow::RGB as i32,
-            width as i32,
-            height as i32,
-            0,
-            glow::RGB,
-            glow::UNSIGNED_BYTE,
-            glow::PixelUnpackData::Slice(Some(bytes)),
-        );
-
         texture
-    };
-
-    (texture, (width as f32, height as f32))
+    }
 }
-
-#[rustfmt::skip]
-const VOXEL_VERTICES: [f32; 192] = [
-    // Back face
-     0.5, -0.5, -0.5,  0.0, 0.0, -1.0,  1.0, 0.0,
-     0.5,  0.5, -0.5,  0.0, 0.0, -1.0,  1.0, 1.0,
-    -0.5, -0.5, -0.5,  0.0, 0.0, -1.0,  0.0, 0.0,
-    -0.5,  0.5, -0.5,  0.0, 0.0, -1.0,  0.0, 1.0,
-    // Front face
-     0.5, -0.5,  0.5,  0.0, 0.0, 1.0,   1.0, 0.0,
-     0.5,  0.5,  0.5,  0.0, 0.0, 1.0,   1.0, 1.0,
-    -0.5,  0.5,  0.5,  0.0, 0.0, 1.0,   0.0, 1.0,
-    -0.5, -0.5,  0.5,  0.0, 0.0, 1.0,   0.0, 0.0,
-    // Left face
-    -0.5,  0.5, -0.5, -1.0, 0.0, 0.0,   1.0, 1.0,
-    -0.5, -0.5, -0.5, -1.0, 0.0, 0.0,   0.0, 1.0,
-    -0.5, -0.5,  0.5, -1.0, 0.0, 0.0,   0.0, 0.0,
-    -0.5,  0.5,  0.5, -1.0, 0.0, 0.0,   1.0, 0.0,
-    // Right face
-     0.5,  0.5, -0.5,  1.0, 0.0, 0.0,   1.0, 1.0,
-     0.5, -0.5, -0.5,  1.0, 0.0, 0.0,   0.0, 1.0,
-     0.5,  0.5,  0.5,  1.0, 0.0, 0.0,   1.0, 0.0,
-     0.5, -0.5,  0.5,  1.0, 0.0, 0.0,   0.0, 0.0,
-    // Bottom face
-     0.5, -0.5, -0.5,  0.0, -1.0, 0.0,  1.0, 1.0,
-     0.5, -0.5,  0.5,  0.0, -1.0, 0.0,  1.0, 0.0,
-    -0.5, -0.5,  0.5,  0.0, -1.0, 0.0,  0.0, 0.0,
-    -0.5, -0.5, -0.5,  0.0, -1.0, 0.0,  0.0, 1.0,
-    // Top face
-     0.5,  0.5, -0.5,  0.0, 1.0, 0.0,   1.0, 1.0,
-     0.5,  0.5,  0.5,  0.0, 1.0, 0.0,   1.0, 0.0,
-    -0.5,  0.5, -0.5,  0.0, 1.0, 0.0,   0.0, 1.0,
-    -0.5,  0.5,  0.5,  0.0, 1.0, 0.0,   0.0, 0.0,
-];
-
-#[rustfmt::skip]
-const SHADOW_VERTICES: [f32; 72] = [
-    // Back face
-     0.5, -0.5, -0.5,
-     0.5,  0.5, -0.5,
-    -0.5, -0.5, -0.5,
-    -0.5,  0.5, -0.5,
-    // Front face
-     0.5, -0.5,  0.5,
-     0.5,  0.5,  0.5,
-    -0.5,  0.5,  0.5,
-    -0.5, -0.5,  0.5,
-    // Left face
-    -0.5,  0.5, -0.5,
-    -0.5, -0.5, -0.5,
-    -0.5, -0.5,  0.5,
-    -0.5,  0.5,  0.5,
-    // Right face
-     0.5,  0.5, -0.5,
-     0.5, -0.5, -0.5,
-     0.5,  0.5,  0.5,
-     0.5, -0.5,  0.5,
-    // Bottom face
-     0.5, -0.5, -0.5,
-     0.5, -0.5,  0.5,
-    -0.5, -0.5,  0.5,
-    -0.5, -0.5, -0.5,
-    // Top face
-     0.5,  0.5, -0.5,
-     0.5,  0.5,  0.5,
-    -0.5,  0.5, -0.5,
-    -0.5,  0.5,  0.5,
-];
-
-#[rustfmt::skip]
-const INDICES: [u32; 36] = [
-    // Back face
-    1, 0, 2,
-    1, 2, 3,
-    // Front face
-    4, 5, 6,
-    4, 6, 7,
-    // Left face
-    8, 9, 10,
-    8, 10, 11,
-    // Right face
-    12, 14, 13,
-    14, 15, 13,
-    // Bottom face
-    16, 17, 18,
-    16, 18, 19,
-    // Top face
-    20, 22, 21,
-    22, 23, 21,
-];